@@ -48,9 +48,129 @@ pub fn parse_args() -> Result<Vec<Flag>, Box<dyn std::error::Error>> {
                 flags.push(Flag::ValidateAll);
             }
             "--timestamp-coverage" => {
+                // Optional argument: the collection to report on, all timestamped collections otherwise
+                let value = optional_argument(i, &args);
+                consumed_next_arg = value.is_some();
+                flags.push(Flag::TimestampCoverage(value));
+            }
+            "--timestamp-backfill" => {
+                validate_flag_accepts_argument(i, args.len())?;
+                validate_next_argument_is_not_flag(i, &args)?;
+                flags.push(Flag::TimestampBackfill(args[i + 1].clone()));
+                consumed_next_arg = true;
+            }
+            "--patch-format" => {
+                validate_flag_accepts_argument(i, args.len())?;
+                validate_next_argument_is_not_flag(i, &args)?;
+                flags.push(Flag::PatchFormat(args[i + 1].clone()));
+                consumed_next_arg = true;
+            }
+            "--max-deviation" => {
+                validate_flag_accepts_argument(i, args.len())?;
+                validate_next_argument_is_not_flag(i, &args)?;
+                flags.push(Flag::MaxDeviation(args[i + 1].clone()));
+                consumed_next_arg = true;
+            }
+            "--at-time" => {
+                validate_flag_accepts_argument(i, args.len())?;
+                validate_next_argument_is_not_flag(i, &args)?;
+                flags.push(Flag::AtTime(args[i + 1].clone()));
+                consumed_next_arg = true;
+            }
+            "--since" => {
+                validate_flag_accepts_argument(i, args.len())?;
+                validate_next_argument_is_not_flag(i, &args)?;
+                flags.push(Flag::Since(args[i + 1].clone()));
+                consumed_next_arg = true;
+            }
+            "--indexing-latency" => {
+                // Optional argument: the collection to report on, all indexed collections otherwise
+                let value = optional_argument(i, &args);
+                consumed_next_arg = value.is_some();
+                flags.push(Flag::IndexingLatency(value));
+            }
+            "--verify-events" => {
+                // Optional argument: how many random events to verify, all events otherwise
+                let value = optional_argument(i, &args);
+                consumed_next_arg = value.is_some();
+                flags.push(Flag::VerifyEvents(value));
+            }
+            "--reorg-check" => {
+                // Optional argument: how many of the last indexed blocks to check
+                let value = optional_argument(i, &args);
+                consumed_next_arg = value.is_some();
+                flags.push(Flag::ReorgCheck(value));
+            }
+            "--check-user-lists" => {
+                let reserve = optional_argument(i, &args);
+                consumed_next_arg = reserve.is_some();
+                flags.push(Flag::CheckUserLists(reserve));
+            }
+            "--find-untracked-holders" => {
+                let reserve = optional_argument(i, &args);
+                consumed_next_arg = reserve.is_some();
+                flags.push(Flag::FindUntrackedHolders(reserve));
+            }
+            "--validate-rates" => {
+                let reserve = optional_argument(i, &args);
+                consumed_next_arg = reserve.is_some();
+                flags.push(Flag::ValidateRates(reserve));
+            }
+            "--validate-reserve-config" => {
+                let reserve = optional_argument(i, &args);
+                consumed_next_arg = reserve.is_some();
+                flags.push(Flag::ValidateReserveConfig(reserve));
+            }
+            "--stable-debt" => {
+                let reserve = optional_argument(i, &args);
+                consumed_next_arg = reserve.is_some();
+                flags.push(Flag::StableDebt(reserve));
+            }
+            "--reserve-health" => {
+                let reserve = optional_argument(i, &args);
+                consumed_next_arg = reserve.is_some();
+                flags.push(Flag::ReserveHealth(reserve));
+            }
+            "--validate-account-data" => {
+                let user = optional_argument(i, &args);
+                consumed_next_arg = user.is_some();
+                flags.push(Flag::ValidateAccountData(user));
+            }
+            "--health-factor-below" => {
+                validate_flag_accepts_argument(i, args.len())?;
+                validate_next_argument_is_not_flag(i, &args)?;
+                flags.push(Flag::HealthFactorBelow(args[i + 1].clone()));
+                consumed_next_arg = true;
+            }
+            "--index-history" => {
+                validate_flag_accepts_argument(i, args.len())?;
+                validate_next_argument_is_not_flag(i, &args)?;
+                flags.push(Flag::IndexHistory(args[i + 1].clone()));
+                consumed_next_arg = true;
+            }
+            "--csv" => {
+                validate_flag_accepts_argument(i, args.len())?;
+                validate_next_argument_is_not_flag(i, &args)?;
+                flags.push(Flag::Csv(args[i + 1].clone()));
+                consumed_next_arg = true;
+            }
+            "--on-chain" => {
                 validate_flag_does_not_accept_argument(i, &args)?;
-                flags.push(Flag::TimestampCoverage);
-                break;
+                flags.push(Flag::OnChain);
+            }
+            "--validate-event-math" => {
+                validate_flag_does_not_accept_argument(i, &args)?;
+                flags.push(Flag::ValidateEventMath);
+            }
+            "--find-missing-events" => {
+                validate_flag_does_not_accept_argument(i, &args)?;
+                flags.push(Flag::FindMissingEvents);
+            }
+            "--bucket-size" => {
+                validate_flag_accepts_argument(i, args.len())?;
+                validate_next_argument_is_not_flag(i, &args)?;
+                flags.push(Flag::BucketSize(args[i + 1].clone()));
+                consumed_next_arg = true;
             }
             "--validate-timestamps" => {
                 // Optional argument: if next token is missing or a flag, treat as None
@@ -87,13 +207,41 @@ pub fn parse_args() -> Result<Vec<Flag>, Box<dyn std::error::Error>> {
                 validate_flag_accepts_argument(i, args.len())?;
                 validate_next_argument_is_not_flag(i, &args)?;
                 flags.push(Flag::GetTokenEvents(args[i + 1].clone()));
-                break;
+                consumed_next_arg = true;
             }
             "--get-user-events" => {
                 validate_flag_accepts_argument(i, args.len())?;
                 validate_next_argument_is_not_flag(i, &args)?;
                 flags.push(Flag::GetUserEvents(args[i + 1].clone()));
-                break;
+                consumed_next_arg = true;
+            }
+            "--user-timeline" => {
+                validate_flag_accepts_argument(i, args.len())?;
+                validate_next_argument_is_not_flag(i, &args)?;
+                flags.push(Flag::UserTimeline(args[i + 1].clone()));
+                consumed_next_arg = true;
+            }
+            "--format" => {
+                validate_flag_accepts_argument(i, args.len())?;
+                validate_next_argument_is_not_flag(i, &args)?;
+                flags.push(Flag::Format(args[i + 1].clone()));
+                consumed_next_arg = true;
+            }
+            "--bisect-user" => {
+                validate_flag_accepts_argument(i, args.len())?;
+                validate_next_argument_is_not_flag(i, &args)?;
+                flags.push(Flag::BisectUser(args[i + 1].clone()));
+                consumed_next_arg = true;
+            }
+            "--get-tx" => {
+                validate_flag_accepts_argument(i, args.len())?;
+                validate_next_argument_is_not_flag(i, &args)?;
+                flags.push(Flag::GetTx(args[i + 1].clone()));
+                consumed_next_arg = true;
+            }
+            "--grouped" => {
+                validate_flag_does_not_accept_argument(i, &args)?;
+                flags.push(Flag::Grouped);
             }
             "--validate-reserve-indexes" => {
                 validate_flag_accepts_argument(i, args.len())?;
@@ -106,6 +254,21 @@ pub fn parse_args() -> Result<Vec<Flag>, Box<dyn std::error::Error>> {
                 flags.push(Flag::ValidateAllReserveIndexes);
                 break;
             }
+            "--address-casing-report" => {
+                validate_flag_does_not_accept_argument(i, &args)?;
+                flags.push(Flag::AddressCasingReport);
+                break;
+            }
+            "--event-anomalies" => {
+                validate_flag_does_not_accept_argument(i, &args)?;
+                flags.push(Flag::EventAnomalies);
+                break;
+            }
+            "--reconcile-reserves" => {
+                validate_flag_does_not_accept_argument(i, &args)?;
+                flags.push(Flag::ReconcileReserves);
+                break;
+            }
             "--scaled" => {
                 validate_flag_does_not_accept_argument(i, &args)?;
                 flags.push(Flag::Scaled);
@@ -116,7 +279,7 @@ pub fn parse_args() -> Result<Vec<Flag>, Box<dyn std::error::Error>> {
                 flags.push(Flag::ValidateUserAll(args[i + 1].clone()));
                 consumed_next_arg = true;
             }
-            "--reserve-token" => {
+            "--reserve-token" | "--reserve" => {
                 validate_flag_accepts_argument(i, args.len())?;
                 validate_next_argument_is_not_flag(i, &args)?;
                 flags.push(Flag::ReserveToken(args[i + 1].clone()));
@@ -240,13 +403,21 @@ pub fn parse_args() -> Result<Vec<Flag>, Box<dyn std::error::Error>> {
     // --orderbook
     // --all-tokens
     // --validate-timestamps
-    // --timestamp-coverage
     // --get-all-users
     // --get-all-reserves
     // --get-all-a-token
     // --get-all-debt-token
     // --validate-reserve-indexes
     // --validate-all-reserve-indexes
+    // --address-casing-report
+    // --event-anomalies
+    // --reconcile-reserves
+    // --reorg-check
+    // --validate-rates
+    // --stable-debt
+    // --validate-account-data
+    // --health-factor-below
+    // --get-tx
     if flags.iter().any(|flag| {
         (matches!(
             flag,
@@ -254,22 +425,204 @@ pub fn parse_args() -> Result<Vec<Flag>, Box<dyn std::error::Error>> {
                 | Flag::Help
                 | Flag::AllTokens
                 | Flag::Orderbook
-                | Flag::TimestampCoverage
                 | Flag::GetAllUsers
                 | Flag::GetAllReserves
                 | Flag::GetAllATokens
                 | Flag::GetAllDebtTokens
                 | Flag::ValidateAllReserveIndexes
+                | Flag::AddressCasingReport
+                | Flag::EventAnomalies
+                | Flag::ReconcileReserves
+                | Flag::ReorgCheck(_)
+                | Flag::ValidateRates(_)
+                | Flag::StableDebt(_)
+                | Flag::ValidateAccountData(_)
+                | Flag::HealthFactorBelow(_)
+                | Flag::GetTx(_)
         ) && flags.len() > 1)
             || (matches!(
                 flag,
-                Flag::ValidateTimestamps(_)
-                    | Flag::GetTokenEvents(_)
-                    | Flag::GetUserEvents(_)
-                    | Flag::ValidateReserveIndexes(_)
+                Flag::ValidateTimestamps(_) | Flag::ValidateReserveIndexes(_)
             ) && flags.len() > 2)
     }) {
-        return Err("You cannot combine --last-block, --help, --orderbook, --all-tokens, --validate-token-timestamp, --timestamp-coverage, --get-all-users, --get-all-reserves, --get-all-a-token, --get-all-debt-token, --validate-all-reserve-indexes with other flags. Use --help for more information.".into());
+        return Err("You cannot combine --last-block, --help, --orderbook, --all-tokens, --validate-token-timestamp, --get-all-users, --get-all-reserves, --get-all-a-token, --get-all-debt-token, --validate-all-reserve-indexes, --address-casing-report, --event-anomalies, --reconcile-reserves, --reorg-check, --validate-rates, --stable-debt, --validate-account-data, --health-factor-below, --get-tx with other flags. Use --help for more information.".into());
+    }
+
+    // --get-token-events, --get-user-events, --indexing-latency, --verify-events,
+    // --find-missing-events, --validate-event-math, --user-timeline, --find-untracked-holders,
+    // --validate-reserve-config and --reserve-health can only be combined with --at-time and --since, and only one of them can be given
+    let has_time_filter = flags
+        .iter()
+        .any(|flag| matches!(flag, Flag::AtTime(_) | Flag::Since(_)));
+    let block_filtered_count = flags
+        .iter()
+        .filter(|flag| {
+            matches!(
+                flag,
+                Flag::GetTokenEvents(_)
+                    | Flag::GetUserEvents(_)
+                    | Flag::IndexingLatency(_)
+                    | Flag::VerifyEvents(_)
+                    | Flag::FindMissingEvents
+                    | Flag::ValidateEventMath
+                    | Flag::UserTimeline(_)
+                    | Flag::FindUntrackedHolders(_)
+                    | Flag::ValidateReserveConfig(_)
+                    | Flag::ReserveHealth(_)
+            )
+        })
+        .count();
+    if (has_time_filter || block_filtered_count > 0)
+        && (block_filtered_count > 1
+            || flags.iter().any(|flag| {
+                !matches!(
+                    flag,
+                    Flag::GetTokenEvents(_)
+                        | Flag::GetUserEvents(_)
+                        | Flag::IndexingLatency(_)
+                        | Flag::VerifyEvents(_)
+                        | Flag::FindMissingEvents
+                        | Flag::ValidateEventMath
+                        | Flag::Grouped
+                        | Flag::UserTimeline(_)
+                        | Flag::FindUntrackedHolders(_)
+                        | Flag::ValidateReserveConfig(_)
+                        | Flag::ReserveHealth(_)
+                        | Flag::Format(_)
+                        | Flag::AtTime(_)
+                        | Flag::Since(_)
+                )
+            }))
+    {
+        return Err("You can only combine one of --get-token-events, --get-user-events, --indexing-latency, --verify-events, --find-missing-events, --validate-event-math, --user-timeline, --find-untracked-holders, --validate-reserve-config or --reserve-health with --at-time and --since. Use --help for more information.".into());
+    }
+    if flags.iter().any(|flag| matches!(flag, Flag::Format(_)))
+        && !flags
+            .iter()
+            .any(|flag| matches!(flag, Flag::UserTimeline(_)))
+    {
+        return Err(
+            "--format can only be combined with --user-timeline. Use --help for more information."
+                .into(),
+        );
+    }
+    if flags.iter().any(|flag| matches!(flag, Flag::Grouped))
+        && !flags
+            .iter()
+            .any(|flag| matches!(flag, Flag::GetTokenEvents(_) | Flag::GetUserEvents(_)))
+    {
+        return Err("--grouped can only be combined with --get-token-events or --get-user-events. Use --help for more information.".into());
+    }
+    if flags
+        .iter()
+        .any(|flag| matches!(flag, Flag::FindMissingEvents))
+        && !flags.iter().any(|flag| matches!(flag, Flag::Since(_)))
+    {
+        return Err("--find-missing-events requires --since to set the start of the block range. Use --help for more information.".into());
+    }
+    if flags.iter().any(|flag| matches!(flag, Flag::OnChain))
+        && !flags
+            .iter()
+            .any(|flag| matches!(flag, Flag::CheckUserLists(_)))
+    {
+        return Err("--on-chain can only be combined with --check-user-lists. Use --help for more information.".into());
+    }
+    if flags
+        .iter()
+        .any(|flag| matches!(flag, Flag::CheckUserLists(_)))
+        && flags
+            .iter()
+            .any(|flag| !matches!(flag, Flag::CheckUserLists(_) | Flag::OnChain))
+    {
+        return Err("--check-user-lists can only be combined with --on-chain. Use --help for more information.".into());
+    }
+    if flags
+        .iter()
+        .any(|flag| matches!(flag, Flag::FindUntrackedHolders(_)))
+        && flags.iter().any(|flag| matches!(flag, Flag::AtTime(_)))
+    {
+        return Err("--find-untracked-holders checks current balances and cannot be combined with --at-time. Use --help for more information.".into());
+    }
+    if flags
+        .iter()
+        .any(|flag| matches!(flag, Flag::ValidateReserveConfig(_)))
+        && flags.iter().any(|flag| matches!(flag, Flag::AtTime(_)))
+    {
+        return Err("--validate-reserve-config checks the current configuration and cannot be combined with --at-time. Use --help for more information.".into());
+    }
+    if flags
+        .iter()
+        .any(|flag| matches!(flag, Flag::ReserveHealth(_)))
+        && flags.iter().any(|flag| matches!(flag, Flag::Since(_)))
+    {
+        return Err("--reserve-health reads a single block and cannot be combined with --since, use --at-time to pin the block. Use --help for more information.".into());
+    }
+    if flags
+        .iter()
+        .any(|flag| matches!(flag, Flag::IndexHistory(_)))
+        && flags
+            .iter()
+            .any(|flag| !matches!(flag, Flag::IndexHistory(_) | Flag::Csv(_)))
+    {
+        return Err(
+            "--index-history can only be combined with --csv. Use --help for more information."
+                .into(),
+        );
+    }
+    if flags.iter().any(|flag| matches!(flag, Flag::Csv(_)))
+        && !flags
+            .iter()
+            .any(|flag| matches!(flag, Flag::IndexHistory(_)))
+    {
+        return Err(
+            "--csv can only be combined with --index-history. Use --help for more information."
+                .into(),
+        );
+    }
+    if flags.iter().any(|flag| matches!(flag, Flag::BisectUser(_)))
+        && (flags.len() != 2
+            || !flags
+                .iter()
+                .any(|flag| matches!(flag, Flag::ReserveToken(_))))
+    {
+        return Err("--bisect-user requires --reserve and cannot be combined with other flags. Use --help for more information.".into());
+    }
+
+    // --timestamp-coverage can only be combined with --bucket-size
+    let has_timestamp_coverage = flags
+        .iter()
+        .any(|flag| matches!(flag, Flag::TimestampCoverage(_)));
+    let has_bucket_size = flags.iter().any(|flag| matches!(flag, Flag::BucketSize(_)));
+    if has_timestamp_coverage
+        && flags
+            .iter()
+            .any(|flag| !matches!(flag, Flag::TimestampCoverage(_) | Flag::BucketSize(_)))
+    {
+        return Err("You can only combine --timestamp-coverage with --bucket-size. Use --help for more information.".into());
+    }
+    if has_bucket_size && !has_timestamp_coverage {
+        return Err("--bucket-size can only be used with --timestamp-coverage. Use --help for more information.".into());
+    }
+
+    // --timestamp-backfill can only be combined with --patch-format and --max-deviation
+    let has_timestamp_backfill = flags
+        .iter()
+        .any(|flag| matches!(flag, Flag::TimestampBackfill(_)));
+    let has_backfill_option = flags
+        .iter()
+        .any(|flag| matches!(flag, Flag::PatchFormat(_) | Flag::MaxDeviation(_)));
+    if has_timestamp_backfill
+        && flags.iter().any(|flag| {
+            !matches!(
+                flag,
+                Flag::TimestampBackfill(_) | Flag::PatchFormat(_) | Flag::MaxDeviation(_)
+            )
+        })
+    {
+        return Err("You can only combine --timestamp-backfill with --patch-format and --max-deviation. Use --help for more information.".into());
+    }
+    if has_backfill_option && !has_timestamp_backfill {
+        return Err("--patch-format and --max-deviation can only be used with --timestamp-backfill. Use --help for more information.".into());
     }
 
     // the following flags can only be combined with --scaled
@@ -370,6 +723,14 @@ fn validate_flag_does_not_accept_argument(
     Ok(())
 }
 
+// The argument of a flag whose argument is optional: the next one, unless it is missing or
+// another flag
+fn optional_argument(i: usize, args: &[String]) -> Option<String> {
+    args.get(i + 1)
+        .filter(|next| !next.starts_with("--"))
+        .cloned()
+}
+
 fn validate_next_argument_is_not_flag(
     i: usize,
     args: &[String],
@@ -1,3 +1,7 @@
+use crate::constants::{
+    DEFAULT_BLOCK_CACHE_PATH, DEFAULT_CHAIN_ID, DEFAULT_LOGS_BLOCK_RANGE,
+    DEFAULT_RPC_REQUEST_DELAY_MS, MAX_CONCURRENT_RPC_REQUESTS,
+};
 use dotenv::dotenv;
 use std::env;
 
@@ -52,3 +56,80 @@ pub fn get_config() -> Config {
     // );
     Config::new()
 }
+
+/// Path of the on-disk block cache, `BLOCK_CACHE_PATH` overrides the default
+pub fn block_cache_path() -> String {
+    dotenv().ok();
+    env::var("BLOCK_CACHE_PATH").unwrap_or_else(|_| DEFAULT_BLOCK_CACHE_PATH.to_string())
+}
+
+/// Chain id the indexed events are expected to carry, `CHAIN_ID` overrides the default
+pub fn configured_chain_id() -> u64 {
+    dotenv().ok();
+    env_or("CHAIN_ID", DEFAULT_CHAIN_ID)
+}
+
+/// Address of the treasury collecting the reserve factor, set with `TREASURY_ADDRESS`
+pub fn treasury_address() -> Option<String> {
+    dotenv().ok();
+    env::var("TREASURY_ADDRESS").ok()
+}
+
+/// Aave oracle pricing the reserves, set with `ORACLE_ADDRESS`; without it the oracle
+/// registered in the pool's addresses provider is used
+pub fn oracle_address() -> Option<String> {
+    dotenv().ok();
+    env::var("ORACLE_ADDRESS").ok()
+}
+
+/// USD amount under which a balance mismatch is only a warning, set with `USD_TOLERANCE`
+pub fn usd_tolerance() -> f64 {
+    dotenv().ok();
+    env_or("USD_TOLERANCE", 0.0)
+}
+
+/// Rate limiting of RPC calls, each setting can be overridden from the environment
+#[derive(Debug, Clone)]
+pub struct RpcSettings {
+    /// RPC_MAX_CONCURRENT_REQUESTS: requests in flight at the same time
+    pub max_concurrent_requests: usize,
+    /// RPC_REQUEST_DELAY_MS: pause before each request
+    pub request_delay_ms: u64,
+    /// RPC_LOGS_BLOCK_RANGE: blocks per eth_getLogs request
+    pub logs_block_range: u64,
+}
+
+impl RpcSettings {
+    pub fn new() -> Self {
+        dotenv().ok();
+
+        RpcSettings {
+            max_concurrent_requests: env_or(
+                "RPC_MAX_CONCURRENT_REQUESTS",
+                MAX_CONCURRENT_RPC_REQUESTS,
+            )
+            .max(1),
+            request_delay_ms: env_or("RPC_REQUEST_DELAY_MS", DEFAULT_RPC_REQUEST_DELAY_MS),
+            logs_block_range: env_or("RPC_LOGS_BLOCK_RANGE", DEFAULT_LOGS_BLOCK_RANGE).max(1),
+        }
+    }
+}
+
+impl Default for RpcSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn get_rpc_settings() -> RpcSettings {
+    RpcSettings::new()
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} must be a valid number", name)),
+        Err(_) => default,
+    }
+}
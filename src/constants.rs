@@ -1,6 +1,37 @@
 // Aave constants
 pub const RAY: u128 = 1_000_000_000_000_000_000_000_000_000; // 10^27
 pub const HALF_RAY: u128 = 500_000_000_000_000_000_000_000_000; // 5e26 use std::env;
+pub const WAD: u128 = 1_000_000_000_000_000_000; // 10^18, health factors
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+// `interestRateMode` of a stable rate Borrow event, 2 is variable
+pub const STABLE_INTEREST_RATE_MODE: i32 = 1;
+pub const PERCENTAGE_FACTOR: u128 = 10_000; // 100% in basis points
+pub const HALF_PERCENTAGE_FACTOR: u128 = 5_000;
+// relative difference, in basis points, accepted between recomputed and on-chain rates, the
+// debt keeps accruing after the rates were last set
+pub const RATE_RECOMPUTE_TOLERANCE_BPS: u128 = 100;
+// relative difference, in basis points, accepted between the account totals recomputed from
+// user_positions and getUserAccountData
+pub const ACCOUNT_DATA_TOLERANCE_BPS: u128 = 10;
+// how far back --validate-reserve-config looks for activity without --since
+pub const DEFAULT_RESERVE_ACTIVITY_WINDOW: &str = "1d";
+// largest difference, in ray units, between a recomputed and a stored index still accepted
+pub const INDEX_ROUNDING_TOLERANCE: u128 = 1_000;
+
+// Upper bound of RPC requests in flight when validating documents from a stream
+pub const MAX_CONCURRENT_RPC_REQUESTS: usize = 16;
+pub const DEFAULT_RPC_REQUEST_DELAY_MS: u64 = 0;
+pub const DEFAULT_LOGS_BLOCK_RANGE: u64 = 2_000;
+pub const DEFAULT_COVERAGE_BUCKET_SIZE: u64 = 100_000;
+pub const DEFAULT_MAX_TIMESTAMP_DEVIATION_SECONDS: u64 = 0;
+pub const BLOCK_PREFETCH_BATCH_SIZE: usize = 500;
+pub const LATENCY_WORST_OFFENDERS: usize = 10;
+pub const DEFAULT_REORG_CHECK_BLOCKS: u64 = 1_000;
+pub const DEFAULT_BLOCK_CACHE_PATH: &str = ".block_cache";
+// Blocks closer to the head than this are kept in memory only, a reorg can still replace them
+pub const BLOCK_CACHE_CONFIRMATIONS: u64 = 64;
+// Sonic mainnet, the chain the indexer runs against
+pub const DEFAULT_CHAIN_ID: u64 = 146;
 pub const HELP_MESSAGE: &str = r#"
 sodax-backend-analizer - A CLI tool for analyzing database data for the SODAX backend
 
@@ -16,13 +47,57 @@ OPTIONS:
     --get-all-reserves      Print all reserve token addresses and symbols
     --get-all-a-token       Print all aToken addresses and symbols
     --get-all-debt-token    Print all debt token addresses and symbols
-    --reserve-token <TOKEN_ADDRESS>  Returns the reserve token data for the given reserve token address
-    --a-token <TOKEN_ADDRESS>         Returns the reserve token data for the given aToken address
-    --debt-token <TOKEN_ADDRESS>      Returns the reserve token data for the given debt token address
-    --user-position <WALLET_ADDRESS>  Returns the user position data for the given wallet address
+    --reserve-token <TOKEN>          Returns the reserve token data for the given reserve (see TOKEN ARGUMENTS), alias --reserve,
+                                     with the decoded on-chain reserve configuration
+    --a-token <TOKEN>                Returns the reserve token data for the given aToken (see TOKEN ARGUMENTS)
+    --debt-token <TOKEN>             Returns the reserve token data for the given debt token (see TOKEN ARGUMENTS)
+    --user-position <WALLET_ADDRESS>  Returns the user position data for the given wallet address, with its on-chain
+                                      account data (collateral, debt, available borrows, LTV, health factor)
     --balance-of <USER_ADDRESS>       Get token balance for a user (requires one of: --reserve-token, --a-token, or --debt-token)
-    --get-token-events <TOKEN_ADDRESS> Get events for a specific token (reserve, aToken, or debt token)
-    --get-user-events <USER_ADDRESS>  Get events for a specific user
+    --get-token-events <TOKEN>        Get events for a specific token (reserve, aToken, or debt token)
+    --get-user-events <USER_ADDRESS>  Get events for a specific user (see TIME ARGUMENTS for --at-time and --since)
+    --grouped                         With --get-token-events or --get-user-events, print every event of the matching
+                                      transactions grouped by transaction, with the action and missing companion events
+    --user-timeline <USER_ADDRESS>    Events of a user across all reserves in block order, with the action, the amount and
+                                      the running scaled and real supply / debt balance after each event, ending with a
+                                      comparison against user_positions and on-chain scaled balances (accepts --at-time
+                                      and --since; the comparison is only made without them)
+    --format <text|json>              Output format of --user-timeline (default: text)
+    --bisect-user <USER_ADDRESS>      With --reserve <TOKEN>, replay the user's events in that reserve and binary-search the
+                                      first block where the replayed scaled balances differ from on-chain scaledBalanceOf,
+                                      printing the transactions of that block (needs an archive RPC)
+    --get-tx <TX_HASH>                Print the events of a transaction, its action (supply, borrow, ...) and the
+                                      companion events missing (e.g. a Supply without its aToken Mint)
+    --indexing-latency [COLLECTION]   Indexing latency (createdAt - block time) per collection and event type: p50/p95/p99/max,
+                                      daily buckets and worst offenders (all collections with blockNumber and createdAt
+                                      when no collection is given, accepts --at-time and --since)
+    --verify-events [COUNT]           Verify money market events against the on-chain log at their txHash / logIndex
+                                      (block number, chain id, emitting contract and every decoded field); COUNT random
+                                      events when given, all events otherwise (accepts --at-time and --since)
+    --find-missing-events             Scan eth_getLogs of the pool, aTokens and variable debt tokens for the block range
+                                      and report logs with no money_market_events document (requires --since); debt
+                                      token Transfer logs are skipped, they only mirror Mint and Burn
+    --validate-event-math             Check the value, balanceIncrease and index of every aToken / debt token Mint and
+                                      Burn event (scaled delta, index of at least 1 ray), compare the index with the
+                                      ReserveDataUpdated of the reserve and the amount with the Supply, Withdraw, Borrow or
+                                      Repay of the user in the same transaction (accepts --at-time and --since)
+    --reorg-check [BLOCKS]            Compare the current receipt of every transaction in the last BLOCKS blocks
+                                      referenced by money market events (default: 1000) with the stored blockNumber,
+                                      the current block hash at that height and the stored log at its logIndex, and
+                                      report dropped, moved or changed events with the affected users and reserves
+    --at-time <TIME>                  Print the last block at or before the given time
+    --since <TIME|DURATION>           Print the first block at or after the given time or duration ago (e.g. 7d)
+    --address-casing-report           Report addresses stored in non-canonical or inconsistent casing across collections
+    --event-anomalies                 Report duplicate (txHash, logIndex) pairs, chainId mismatches (CHAIN_ID, default: 146),
+                                      logIndex conflicts and gaps, and createdAt / __v contradicting block order in
+                                      money_market_events and intentEvents, with the offending _ids
+    --timestamp-coverage [COLLECTION]  Timestamp coverage by block range and by day, with the block ranges missing a timestamp
+                                      (all collections with a timestamp field when no collection is given)
+    --bucket-size <BLOCKS>            Block range size for --timestamp-coverage (default: 100000)
+    --timestamp-backfill <FILE>       Write a patch with the block timestamp of every solver_volume document whose
+                                      timestamp is null or deviates from its block (the database is not modified)
+    --patch-format <jsonl|mongo>      Patch format for --timestamp-backfill: JSON lines or a mongosh script (default: jsonl)
+    --max-deviation <SECONDS>         Deviation tolerated before a timestamp is patched (default: 0)
     --scaled                 Use scaled balances instead of real balances for validation (adds to validation flags)
 
 INDIVIDUAL VALIDATION OPTIONS:
@@ -30,7 +105,29 @@ INDIVIDUAL VALIDATION OPTIONS:
     --validate-user-borrow <USER_ADDRESS>  Validate user's debt token balance (requires --reserve-token)
     --validate-token-supply               Validate total aToken supply for a reserve (requires --reserve-token)
     --validate-token-borrow              Validate total debt token supply for a reserve (requires --reserve-token)
-    --validate-reserve-indexes <RESERVE_ADDRESS> Validate liquidity and borrow indexes for a specific reserve
+    --validate-reserve-indexes <TOKEN>    Validate liquidity and borrow indexes for a specific reserve
+    --index-history <TOKEN>               Check every ReserveDataUpdated of a reserve: both indexes never decrease and
+                                          grow by the previous rates over the elapsed time (linear for the liquidity
+                                          index, compounded for the borrow index), and the reserve document matches
+                                          the latest event
+    --csv <FILE>                          With --index-history, write the index series to a new CSV file (an existing file is not overwritten)
+    --validate-rates [TOKEN]              Compare the stored rates of a reserve (all reserves when omitted) with the on-chain
+                                          current rates, and recompute them from utilization with the parameters of the
+                                          reserve's interest rate strategy, stable debt weighted at its average stable rate;
+                                          prints utilization, optimal utilization and slopes
+    --validate-reserve-config [TOKEN]     Decode the on-chain configuration of a reserve (all reserves when omitted) and report
+                                          supply / debt above the caps, and events indexed since --since (default: 1d)
+                                          while the reserve is inactive, paused or frozen, or borrowing is disabled
+    --stable-debt [TOKEN]                 Report the on-chain stable debt of a reserve (all reserves when omitted), which the
+                                          database has no field for, per user, with the indexed stable rate Borrow events
+    --validate-account-data [USER]        Recompute the collateral and debt of a user (all users when omitted) from user_positions,
+                                          the on-chain stable debt, the current reserve indexes and the oracle prices, and
+                                          compare them with the Pool's getUserAccountData (collateral and debt only in e-mode)
+    --health-factor-below <HF>            List the users whose on-chain health factor is below HF (e.g. 1.1), lowest first
+    --reserve-health [TOKEN]              Read a reserve (all reserves when omitted) at a single block (--at-time, default the
+                                          block of the latest indexed event) and check that the scaled total supply matches the user_positions plus the
+                                          treasury balance (accruedToTreasury is reported, it is not minted yet), the isolation
+                                          mode debt against the debt ceiling and unbacked against the unbacked mint cap
 
 BULK VALIDATION OPTIONS:
     --validate-user-all <USER_ADDRESS>    Validate all positions for a specific user
@@ -38,6 +135,44 @@ BULK VALIDATION OPTIONS:
     --validate-token-all                 Validate all reserves in the marketplace
     --validate-all                       Validate everything (all reserves + all users)
     --validate-all-reserve-indexes       Validate indexes for all reserves
+    --reconcile-reserves                 Compare the scaled totals of every reserve document with the sum of the
+                                         user_positions balances, keeping the treasury (TREASURY_ADDRESS) apart,
+                                         and print the residual (database only, no RPC)
+    --check-user-lists [TOKEN]           Compare the suppliers / borrowers lists of a reserve (all reserves when omitted)
+                                         with the users holding a non-zero balance in user_positions, reporting missing,
+                                         stale and duplicated users
+    --on-chain                           With --check-user-lists, also compare the lists with on-chain scaledBalanceOf
+    --find-untracked-holders [TOKEN]     Collect every recipient of the aToken and debt token Transfer logs of a reserve (all
+                                         reserves when omitted) since the tokens were deployed (or --since) and report the
+                                         ones with a non-zero scaledBalanceOf and no position for that reserve in user_positions
+
+TOKEN ARGUMENTS:
+    Every flag that expects a reserve or token (--reserve-token, --a-token, --debt-token,
+    --get-token-events, --validate-reserve-indexes, --index-history) accepts any of:
+    - the reserve symbol, case-insensitive (e.g. usdc)
+    - the reserve, aToken or debt token address, in lowercase or EIP-55 checksum form
+    The input is resolved to its reserve record. If a symbol matches more than one
+    reserve, the command fails and lists the candidates. User addresses are matched
+    in both lowercase and checksum form, whichever the collection stores.
+    --balance-of uses the flag type to pick the token: --reserve-token queries the
+    underlying asset, --a-token the aToken and --debt-token the variable debt token.
+
+TIME ARGUMENTS:
+    <TIME> is unix seconds, a date (2026-10-01, midnight UTC) or an RFC 3339 date-time
+    (2026-10-01T00:00Z, seconds optional). <DURATION> is a number followed by s, m, h, d or w.
+    Times are resolved to blocks by binary search over block timestamps. Block headers are kept
+    in an on-disk cache (.block_cache, or BLOCK_CACHE_PATH) shared by all commands. The last 64 blocks
+    are not cached on disk, a reorg could still replace them.
+    With --get-token-events, --get-user-events, --indexing-latency, --verify-events,
+    --find-missing-events, --validate-event-math or --user-timeline, --since keeps documents
+    from that block on and --at-time keeps documents up to that block. With
+    --find-untracked-holders, --since sets the first block scanned for Transfer logs.
+
+RPC RATE LIMITING:
+    Optional environment variables (or .env entries) that apply to every command:
+    RPC_MAX_CONCURRENT_REQUESTS   Requests in flight at the same time (default: 16)
+    RPC_REQUEST_DELAY_MS          Pause before each request (default: 0)
+    RPC_LOGS_BLOCK_RANGE          Blocks per eth_getLogs request (default: 2000); a range the RPC rejects is split in half
 
 SCALED VALIDATION:
     The --scaled flag can be combined with validation flags to compare scaled balances instead of real balances:
@@ -45,13 +180,33 @@ SCALED VALIDATION:
     - Real balances are calculated by applying the current liquidity/borrow indices to scaled balances
     - Use --scaled when you want to validate the raw database values against on-chain scaled balances
 
+USD VALUES:
+    Real-balance validations and --reserve-health price differences with the Aave oracle (getAssetsPrices),
+    found through the pool addresses provider. --reserve-health uses the prices of its pinned block.
+    Prices are in the oracle's BASE_CURRENCY_UNIT; USD values are left out if BASE_CURRENCY is not USD.
+    Bulk validations print the total USD mismatch. Optional environment variables:
+    ORACLE_ADDRESS                Oracle to read instead of the addresses provider's one
+    USD_TOLERANCE                 USD difference below which a mismatch is reported as tolerated (default: 0)
+
 RESTRICTIONS:
-    - You cannot combine --last-block, --help, --all-tokens, --orderbook, --get-all-users, --get-all-reserves, --get-all-a-token, --get-all-debt-token, --validate-users-all, --validate-token-all, --validate-all, or --validate-all-reserve-indexes with other flags
+    - You cannot combine --last-block, --help, --all-tokens, --orderbook, --get-all-users, --get-all-reserves, --get-all-a-token, --get-all-debt-token, --validate-users-all, --validate-token-all, --validate-all, --validate-all-reserve-indexes, --address-casing-report, --event-anomalies, --reconcile-reserves, --reorg-check, --validate-rates, --stable-debt, --validate-account-data, --health-factor-below, or --get-tx with other flags
     - You cannot combine --reserve-token, --a-token, and --debt-token together
     - --balance-of requires exactly one token type flag (--reserve-token, --a-token, or --debt-token)
     - Individual validation flags require --reserve-token to be specified
     - --validate-user-all can be combined with --reserve-token for specific reserve validation
     - --scaled can only be combined with validation flags
+    - --timestamp-coverage can only be combined with --bucket-size
+    - --get-token-events, --get-user-events, --indexing-latency, --verify-events, --find-missing-events, --validate-event-math and --user-timeline can only be combined with --at-time and --since
+    - --find-missing-events requires --since
+    - --find-untracked-holders can only be combined with --since
+    - --validate-reserve-config can only be combined with --since
+    - --reserve-health can only be combined with --at-time, which can't be before the latest indexed event
+    - --index-history can only be combined with --csv
+    - --format can only be combined with --user-timeline
+    - --bisect-user requires --reserve and cannot be combined with other flags
+    - --check-user-lists can only be combined with --on-chain
+    - --grouped can only be combined with --get-token-events or --get-user-events (and their --at-time and --since)
+    - --timestamp-backfill can only be combined with --patch-format and --max-deviation
 
 EXAMPLES:
     # Basic operations
@@ -64,14 +219,49 @@ EXAMPLES:
     sodax-backend-analizer --get-all-a-token
     sodax-backend-analizer --get-all-debt-token
     sodax-backend-analizer --reserve-token 0x1234567890abcdef...
+    sodax-backend-analizer --reserve-token usdc
     sodax-backend-analizer --a-token 0x1234567890abcdef...
     sodax-backend-analizer --debt-token 0x1234567890abcdef...
     sodax-backend-analizer --user-position 0x1234567890abcdef...
     sodax-backend-analizer --balance-of 0xuser123... --reserve-token 0xtoken456...
+    sodax-backend-analizer --balance-of 0xuser123... --a-token USDC
     sodax-backend-analizer --get-token-events 0x1234567890abcdef...
     sodax-backend-analizer --get-user-events 0xuser123...
+    sodax-backend-analizer --get-user-events 0xuser123... --since 7d
+    sodax-backend-analizer --get-user-events 0xuser123... --grouped
+    sodax-backend-analizer --get-tx 0xtxhash...
+    sodax-backend-analizer --bisect-user 0xuser123... --reserve USDC
+    sodax-backend-analizer --user-timeline 0xuser123...
+    sodax-backend-analizer --user-timeline 0xuser123... --since 30d --format json
+    sodax-backend-analizer --get-token-events usdc --since 2026-09-01 --at-time 2026-10-01T00:00Z
+    sodax-backend-analizer --at-time 2026-10-01T00:00Z
+    sodax-backend-analizer --indexing-latency money_market_events --since 7d
+    sodax-backend-analizer --verify-events 100
+    sodax-backend-analizer --verify-events --since 1d
+    sodax-backend-analizer --validate-event-math --since 7d
+    sodax-backend-analizer --reorg-check
+    sodax-backend-analizer --reorg-check 200
+    sodax-backend-analizer --find-missing-events --since 2026-10-01 --at-time 2026-10-02
     sodax-backend-analizer --validate-reserve-indexes 0x1234567890abcdef...
+    sodax-backend-analizer --index-history USDC --csv usdc_indexes.csv
+    sodax-backend-analizer --validate-rates USDC
+    sodax-backend-analizer --validate-reserve-config --since 7d
+    sodax-backend-analizer --stable-debt
+    sodax-backend-analizer --validate-account-data
+    sodax-backend-analizer --health-factor-below 1.1
+    sodax-backend-analizer --reserve-health USDC
     sodax-backend-analizer --validate-all-reserve-indexes
+    sodax-backend-analizer --reconcile-reserves
+    sodax-backend-analizer --check-user-lists
+    sodax-backend-analizer --check-user-lists USDC --on-chain
+    sodax-backend-analizer --find-untracked-holders
+    sodax-backend-analizer --find-untracked-holders USDC --since 30d
+    sodax-backend-analizer --address-casing-report
+    sodax-backend-analizer --event-anomalies
+    sodax-backend-analizer --timestamp-coverage
+    sodax-backend-analizer --timestamp-coverage solver_volume --bucket-size 50000
+    sodax-backend-analizer --timestamp-backfill timestamps.jsonl
+    sodax-backend-analizer --timestamp-backfill timestamps.js --patch-format mongo --max-deviation 2
 
     # Individual validation (real balances)
     sodax-backend-analizer --validate-user-supply 0xuser123... --reserve-token 0xtoken456...
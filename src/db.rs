@@ -1,5 +1,4 @@
 use crate::config::get_config;
-use std::str::FromStr;
 use crate::models::{
     OrderbookDocument,
     ReserveTokenDocument,
@@ -7,6 +6,13 @@ use crate::models::{
     SolverVolumeDocument,
     SolverVolumeTimestampAndBlock,
     MoneyMarketEventDocument,
+    UserAddressProjection,
+    BlockTimestampPresence,
+    IndexedDocumentProjection,
+    EventKeyProjection,
+    EventOrderingProjection,
+    ReserveDataUpdatedEvent,
+    BorrowEvent,
     // IntentEventDocument
 };
 // For async iteration over cursor
@@ -14,15 +20,16 @@ use futures::stream::StreamExt;
 use mongodb::{
     bson::{doc, Document},
     options::ClientOptions,
-    Client, Collection,
+    Client, Collection, Cursor,
     options::FindOptions,
 };
-use crate::structs::{Collections, ReserveTokenField};
+use crate::constants::STABLE_INTEREST_RATE_MODE;
+use crate::structs::{BlockRange, Collections, CoverageBucket, DbAddress, ReserveTokenField};
+use std::collections::HashSet;
 
 struct Database {
     client: Client,
 }
-use alloy::primitives::Address;
 
 impl Database {
     async fn new() -> Self {
@@ -85,6 +92,14 @@ pub async fn get_orderbook() -> Result<Vec<OrderbookDocument>, mongodb::error::E
     Ok(docs)
 }
 
+pub async fn stream_orderbook() -> Result<Cursor<OrderbookDocument>, mongodb::error::Error> {
+    let collection: Collection<OrderbookDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().orderbook);
+    stream_helper(collection, doc! {}, None).await
+}
+
 pub async fn get_solver_volume() -> Result<Vec<SolverVolumeDocument>, mongodb::error::Error> {
     let collection: Collection<SolverVolumeDocument> = get_db()
         .await
@@ -94,6 +109,22 @@ pub async fn get_solver_volume() -> Result<Vec<SolverVolumeDocument>, mongodb::e
     Ok(docs)
 }
 
+pub async fn stream_solver_volume() -> Result<Cursor<SolverVolumeDocument>, mongodb::error::Error> {
+    let collection: Collection<SolverVolumeDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().solver_volume);
+    stream_helper(collection, doc! {}, None).await
+}
+
+pub async fn count_solver_volume() -> Result<u64, mongodb::error::Error> {
+    let collection: Collection<SolverVolumeDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().solver_volume);
+    count_with_filter(collection, doc! {}).await
+}
+
 pub async fn find_docs_with_non_null_timestamp()
 -> Result<Vec<SolverVolumeDocument>, mongodb::error::Error> {
     let collection: Collection<SolverVolumeDocument> = get_db()
@@ -101,11 +132,58 @@ pub async fn find_docs_with_non_null_timestamp()
         .database()
         .collection(get_collections_config().solver_volume);
 
-    let filter = doc! { "timestamp": { "$exists": true } };
-    let docs: Vec<SolverVolumeDocument> = collect_all_with_filter(collection, filter).await?;
+    let docs: Vec<SolverVolumeDocument> =
+        collect_all_with_filter(collection, non_null_timestamp_filter()).await?;
+    Ok(docs)
+}
+
+pub async fn stream_docs_with_non_null_timestamp()
+-> Result<Cursor<SolverVolumeDocument>, mongodb::error::Error> {
+    let collection: Collection<SolverVolumeDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().solver_volume);
+    stream_helper(collection, non_null_timestamp_filter(), None).await
+}
+
+pub async fn count_docs_with_non_null_timestamp() -> Result<u64, mongodb::error::Error> {
+    let collection: Collection<SolverVolumeDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().solver_volume);
+    count_with_filter(collection, non_null_timestamp_filter()).await
+}
+
+/// Returns `size` random solver_volume documents with a timestamp, sampled server-side
+pub async fn sample_docs_with_non_null_timestamp(
+    size: usize,
+) -> Result<Vec<SolverVolumeDocument>, mongodb::error::Error> {
+    let collection: Collection<SolverVolumeDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().solver_volume);
+
+    let pipeline = vec![
+        doc! { "$match": non_null_timestamp_filter() },
+        doc! { "$sample": { "size": size as i64 } },
+    ];
+    let mut cursor = collection
+        .aggregate(pipeline)
+        .await?
+        .with_type::<SolverVolumeDocument>();
+
+    let mut docs: Vec<SolverVolumeDocument> = vec![];
+    while let Some(doc_result) = cursor.next().await {
+        docs.push(doc_result?);
+    }
     Ok(docs)
 }
 
+// `$exists` alone would also match documents where timestamp is explicitly null
+fn non_null_timestamp_filter() -> Document {
+    doc! { "timestamp": { "$ne": null } }
+}
+
 pub async fn find_timestamp_and_block_from_solver_volume()
 -> Result<Vec<SolverVolumeTimestampAndBlock>, mongodb::error::Error> {
     let collection: Collection<SolverVolumeTimestampAndBlock> = get_db()
@@ -113,13 +191,30 @@ pub async fn find_timestamp_and_block_from_solver_volume()
         .database()
         .collection(get_collections_config().solver_volume);
 
-    let filter = doc! { "timestamp": { "$exists": true } };
+    let filter = non_null_timestamp_filter();
     let projection = doc! {"_id":1, "timestamp": 1, "blockNumber": 1 };
     let docs: Vec<SolverVolumeTimestampAndBlock> =
         collect_helper(collection, filter, Some(projection)).await?;
     Ok(docs)
 }
 
+/// Streams `_id`, `blockNumber` and `timestamp` of every solver_volume document, in block order
+pub async fn stream_timestamp_and_block_from_solver_volume()
+-> Result<Cursor<SolverVolumeTimestampAndBlock>, mongodb::error::Error> {
+    let collection: Collection<SolverVolumeTimestampAndBlock> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().solver_volume);
+
+    let cursor = collection
+        .find(doc! {})
+        .projection(doc! {"_id": 1, "timestamp": 1, "blockNumber": 1 })
+        .sort(doc! {"blockNumber": 1 })
+        .allow_disk_use(true)
+        .await?;
+    Ok(cursor)
+}
+
 pub async fn find_all_reserves() -> Result<Vec<ReserveTokenDocument>, mongodb::error::Error> {
     let collection: Collection<ReserveTokenDocument> = get_db()
         .await
@@ -138,6 +233,33 @@ pub async fn find_all_users() -> Result<Vec<UserPositionDocument>, mongodb::erro
     Ok(users)
 }
 
+pub async fn stream_user_positions() -> Result<Cursor<UserPositionDocument>, mongodb::error::Error>
+{
+    let collection: Collection<UserPositionDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().user_positions);
+    stream_helper(collection, doc! {}, None).await
+}
+
+pub async fn stream_user_addresses() -> Result<Cursor<UserAddressProjection>, mongodb::error::Error>
+{
+    let collection: Collection<UserAddressProjection> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().user_positions);
+    let projection = doc! { "_id": 0, "userAddress": 1 };
+    stream_helper(collection, doc! {}, Some(projection)).await
+}
+
+pub async fn count_all_users() -> Result<u64, mongodb::error::Error> {
+    let collection: Collection<UserPositionDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().user_positions);
+    count_with_filter(collection, doc! {}).await
+}
+
 pub async fn find_reserve_for_debt_token(
     token: &str,
 ) -> Result<Option<ReserveTokenDocument>, mongodb::error::Error> {
@@ -165,32 +287,55 @@ pub async fn find_reserve_for_token(
         .database()
         .collection(get_collections_config().reserve_tokens);
 
+    let token = parse_db_address(token)?;
     let filter = match token_type {
-        ReserveTokenField::Reserve => doc! { "reserveAddress": token },
-        ReserveTokenField::AToken => doc! { "aTokenAddress": token },
-        ReserveTokenField::VariableDebtToken => doc! { "variableDebtTokenAddress": token },
+        ReserveTokenField::Reserve => doc! { "reserveAddress": token.filter() },
+        ReserveTokenField::AToken => doc! { "aTokenAddress": token.filter() },
+        ReserveTokenField::VariableDebtToken => {
+            doc! { "variableDebtTokenAddress": token.filter() }
+        }
     };
 
     find_one_as_option(collection, filter).await
 }
 
 pub async fn find_all_user_addresses() -> Vec<String> {
-    let users = find_all_users().await.unwrap_or_else(|e| {
-        eprintln!("Failed to find all users: {}", e);
-        vec![]
-    });
+    let mut user_addresses: Vec<String> = vec![];
+    let mut cursor = match stream_user_addresses().await {
+        Ok(cursor) => cursor,
+        Err(e) => {
+            eprintln!("Failed to find all users: {}", e);
+            return user_addresses;
+        }
+    };
 
-    let user_addresses: Vec<String> = users.iter().map(|u| u.userAddress.clone()).collect();
+    while let Some(doc_result) = cursor.next().await {
+        match doc_result {
+            Ok(user) => user_addresses.push(user.userAddress),
+            Err(e) => {
+                eprintln!("Failed to read user address: {}", e);
+                return user_addresses;
+            }
+        }
+    }
 
     // dbg!(&user_addresses);
     user_addresses
 }
 
 pub async fn find_all_block_numbers_from_solver_volume() -> Vec<u64> {
-    let solver_volume = get_solver_volume().await.unwrap_or_else(|e| {
-        eprintln!("Failed to get solver volume: {}", e);
-        vec![]
-    });
+    let collection: Collection<SolverVolumeTimestampAndBlock> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().solver_volume);
+    let projection = doc! { "_id": 1, "blockNumber": 1, "timestamp": 1 };
+
+    let solver_volume = collect_helper(collection, doc! {}, Some(projection))
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to get solver volume: {}", e);
+            vec![]
+        });
 
     let block_numbers: Vec<u64> = solver_volume.iter().map(|s| s.blockNumber).collect();
 
@@ -246,7 +391,8 @@ pub async fn get_user_position(
         .database()
         .collection(get_collections_config().user_positions);
 
-    let filter = doc! { "userAddress": user_address };
+    let user_address = parse_db_address(user_address)?;
+    let filter = doc! { "userAddress": user_address.filter() };
     find_one(collection, filter).await
 }
 
@@ -258,17 +404,35 @@ pub async fn find_user_events(
         .database()
         .collection(get_collections_config().money_market_events);
 
-    let filter = doc! { "$or": [
-        { "user": user_address },
-        { "from": user_address },
-        { "to": user_address },
-        { "onBehalfOf": user_address },
-        { "repayer": user_address },
-        { "target": user_address }
-    ]};
+    let filter = user_events_filter(user_address)?;
     collect_all_with_filter(collection, filter).await
 }
 
+pub async fn stream_user_events(
+    user_address: &str,
+    block_range: &BlockRange,
+) -> Result<Cursor<MoneyMarketEventDocument>, mongodb::error::Error> {
+    let collection: Collection<MoneyMarketEventDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().money_market_events);
+
+    let filter = with_block_range(user_events_filter(user_address)?, block_range);
+    stream_helper(collection, filter, None).await
+}
+
+fn user_events_filter(user_address: &str) -> Result<Document, mongodb::error::Error> {
+    let user = parse_db_address(user_address)?.filter();
+    Ok(doc! { "$or": [
+        { "user": user.clone() },
+        { "from": user.clone() },
+        { "to": user.clone() },
+        { "onBehalfOf": user.clone() },
+        { "repayer": user.clone() },
+        { "target": user }
+    ]})
+}
+
 pub async fn find_token_events(
     token_address: &str,
 ) -> Result<Vec<MoneyMarketEventDocument>, mongodb::error::Error> {
@@ -277,32 +441,579 @@ pub async fn find_token_events(
         .database()
         .collection(get_collections_config().money_market_events);
 
-    let reserve_from_a_token = find_reserve_for_a_token(token_address).await?;
-    let reserve_from_debt_token = find_reserve_for_debt_token(token_address).await?;
+    let filter = token_events_filter(token_address).await?;
+    collect_all_with_filter(collection, filter).await
+}
 
-    let reserve_address = match reserve_from_a_token {
-        Some(reserve) => reserve.reserveAddress,
-        None => match reserve_from_debt_token {
-            Some(reserve) => reserve.reserveAddress,
-            None => {
-                eprintln!("No reserve found for token address: {}", token_address);
-                std::process::exit(1) // No matching reserve found
-            }
+pub async fn stream_token_events(
+    token_address: &str,
+    block_range: &BlockRange,
+) -> Result<Cursor<MoneyMarketEventDocument>, mongodb::error::Error> {
+    let collection: Collection<MoneyMarketEventDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().money_market_events);
+
+    let filter = with_block_range(token_events_filter(token_address).await?, block_range);
+    stream_helper(collection, filter, None).await
+}
+
+/// Streams the money market events in the block range, in block order
+pub async fn stream_money_market_events(
+    block_range: &BlockRange,
+) -> Result<Cursor<MoneyMarketEventDocument>, mongodb::error::Error> {
+    let collection: Collection<MoneyMarketEventDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().money_market_events);
+
+    let cursor = collection
+        .find(with_block_range(doc! {}, block_range))
+        .sort(doc! { "blockNumber": 1, "logIndex": 1 })
+        .allow_disk_use(true)
+        .await?;
+    Ok(cursor)
+}
+
+/// Returns every money market event of the given transactions, in (blockNumber, logIndex) order
+pub async fn find_transaction_events(
+    tx_hashes: &[String],
+) -> Result<Vec<MoneyMarketEventDocument>, mongodb::error::Error> {
+    let collection: Collection<MoneyMarketEventDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().money_market_events);
+
+    let hashes: Vec<String> = tx_hashes
+        .iter()
+        .flat_map(|hash| [hash.clone(), hash.to_lowercase()])
+        .collect();
+    let mut cursor = collection
+        .find(doc! { "txHash": { "$in": hashes } })
+        .sort(doc! { "blockNumber": 1, "logIndex": 1 })
+        .await?;
+
+    let mut events: Vec<MoneyMarketEventDocument> = vec![];
+    while let Some(doc_result) = cursor.next().await {
+        events.push(doc_result?);
+    }
+    Ok(events)
+}
+
+/// Returns the ReserveDataUpdated events of a reserve, in (blockNumber, logIndex) order
+pub async fn find_reserve_data_updates(
+    reserve_address: &str,
+) -> Result<Vec<ReserveDataUpdatedEvent>, mongodb::error::Error> {
+    let collection: Collection<MoneyMarketEventDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().money_market_events);
+
+    let reserve = parse_db_address(reserve_address)?;
+    let mut cursor = collection
+        .find(doc! { "eventType": "reserve-data-updated", "reserve": reserve.filter() })
+        .sort(doc! { "blockNumber": 1, "logIndex": 1 })
+        .await?;
+
+    let mut events: Vec<ReserveDataUpdatedEvent> = vec![];
+    while let Some(doc_result) = cursor.next().await {
+        if let MoneyMarketEventDocument::ReserveDataUpdated(event) = doc_result? {
+            events.push(event);
+        }
+    }
+    Ok(events)
+}
+
+/// Returns the stable rate Borrow events of a reserve, in (blockNumber, logIndex) order
+pub async fn find_stable_borrows(
+    reserve_address: &str,
+) -> Result<Vec<BorrowEvent>, mongodb::error::Error> {
+    let collection: Collection<MoneyMarketEventDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().money_market_events);
+
+    let reserve = parse_db_address(reserve_address)?;
+    let mut cursor = collection
+        .find(doc! {
+            "eventType": "borrow",
+            "reserve": reserve.filter(),
+            "interestRateMode": STABLE_INTEREST_RATE_MODE,
+        })
+        .sort(doc! { "blockNumber": 1, "logIndex": 1 })
+        .await?;
+
+    let mut events: Vec<BorrowEvent> = vec![];
+    while let Some(doc_result) = cursor.next().await {
+        if let MoneyMarketEventDocument::Borrow(event) = doc_result? {
+            events.push(event);
+        }
+    }
+    Ok(events)
+}
+
+/// Returns the highest blockNumber referenced by a money market event
+pub async fn find_latest_event_block() -> Result<Option<u64>, mongodb::error::Error> {
+    find_event_block(-1).await
+}
+
+// blockNumber of the first money market event in the given sort direction
+async fn find_event_block(direction: i32) -> Result<Option<u64>, mongodb::error::Error> {
+    let collection: Collection<Document> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().money_market_events);
+
+    let edge = collection
+        .find_one(doc! { "blockNumber": { "$type": "number" } })
+        .projection(doc! { "_id": 0, "blockNumber": 1 })
+        .sort(doc! { "blockNumber": direction })
+        .await?;
+    Ok(edge.and_then(|doc| match doc.get("blockNumber") {
+        Some(mongodb::bson::Bson::Int32(block)) => Some(*block as u64),
+        Some(mongodb::bson::Bson::Int64(block)) => Some(*block as u64),
+        Some(mongodb::bson::Bson::Double(block)) => Some(*block as u64),
+        _ => None,
+    }))
+}
+
+/// Returns `size` random money market events from the block range, sampled server-side
+pub async fn sample_money_market_events(
+    size: usize,
+    block_range: &BlockRange,
+) -> Result<Vec<MoneyMarketEventDocument>, mongodb::error::Error> {
+    let collection: Collection<MoneyMarketEventDocument> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().money_market_events);
+
+    let pipeline = vec![
+        doc! { "$match": with_block_range(doc! {}, block_range) },
+        doc! { "$sample": { "size": size as i64 } },
+    ];
+    let mut cursor = collection
+        .aggregate(pipeline)
+        .await?
+        .with_type::<MoneyMarketEventDocument>();
+
+    let mut docs: Vec<MoneyMarketEventDocument> = vec![];
+    while let Some(doc_result) = cursor.next().await {
+        docs.push(doc_result?);
+    }
+    Ok(docs)
+}
+
+/// Returns the (lowercase txHash, logIndex) pairs of the money market events in the block range
+pub async fn find_event_keys_in_range(
+    block_range: &BlockRange,
+) -> Result<HashSet<(String, i64)>, mongodb::error::Error> {
+    let collection: Collection<EventKeyProjection> = get_db()
+        .await
+        .database()
+        .collection(get_collections_config().money_market_events);
+
+    let mut cursor = stream_helper(
+        collection,
+        with_block_range(doc! {}, block_range),
+        Some(doc! { "_id": 0, "txHash": 1, "logIndex": 1 }),
+    )
+    .await?;
+
+    let mut keys = HashSet::new();
+    while let Some(doc_result) = cursor.next().await {
+        let key = doc_result?;
+        keys.insert((key.txHash.to_lowercase(), key.logIndex));
+    }
+    Ok(keys)
+}
+
+/// Returns (txHash, logIndex, `_id`s) for every pair stored more than once in the collection,
+/// txHashes are compared case-insensitively
+pub async fn find_duplicate_event_keys(
+    collection_name: &str,
+) -> Result<Vec<(String, i64, Vec<String>)>, mongodb::error::Error> {
+    let pipeline = vec![
+        doc! { "$match": { "txHash": { "$type": "string" } } },
+        doc! { "$group": {
+            "_id": { "txHash": { "$toLower": "$txHash" }, "logIndex": { "$toLong": "$logIndex" } },
+            "ids": { "$push": "$_id" },
+            "count": { "$sum": 1 },
+        }},
+        doc! { "$match": { "count": { "$gt": 1 } } },
+        doc! { "$sort": { "_id.txHash": 1, "_id.logIndex": 1 } },
+    ];
+
+    let mut duplicates = vec![];
+    for row in aggregate_documents(collection_name, pipeline).await? {
+        let key = row.get_document("_id").cloned().unwrap_or_default();
+        duplicates.push((
+            key.get_str("txHash").unwrap_or_default().to_string(),
+            key.get_i64("logIndex").unwrap_or_default(),
+            object_ids(&row, "ids"),
+        ));
+    }
+    Ok(duplicates)
+}
+
+/// Returns (`_id`, chainId) of the documents whose chainId is not `chain_id`
+pub async fn find_chain_id_mismatches(
+    collection_name: &str,
+    chain_id: u64,
+) -> Result<Vec<(String, String)>, mongodb::error::Error> {
+    let collection: Collection<Document> = get_db().await.database().collection(collection_name);
+    let mut cursor = collection
+        .find(doc! { "chainId": { "$ne": chain_id as i64 } })
+        .projection(doc! { "_id": 1, "chainId": 1 })
+        .await?;
+
+    let mut mismatches = vec![];
+    while let Some(doc_result) = cursor.next().await {
+        let doc = doc_result?;
+        let id = doc
+            .get_object_id("_id")
+            .map(|id| id.to_hex())
+            .unwrap_or_default();
+        let chain = match doc.get("chainId") {
+            Some(value) => value.to_string(),
+            None => "missing".to_string(),
+        };
+        mismatches.push((id, chain));
+    }
+    Ok(mismatches)
+}
+
+/// Returns (txHash, blockNumbers, `_id`s) for every transaction stored in more than one block
+pub async fn find_transactions_in_several_blocks(
+    collection_name: &str,
+) -> Result<Vec<(String, Vec<String>, Vec<String>)>, mongodb::error::Error> {
+    let pipeline = vec![
+        doc! { "$match": { "txHash": { "$type": "string" } } },
+        doc! { "$group": {
+            "_id": { "$toLower": "$txHash" },
+            "blocks": { "$addToSet": "$blockNumber" },
+            "ids": { "$push": "$_id" },
+        }},
+        doc! { "$match": { "blocks.1": { "$exists": true } } },
+        doc! { "$sort": { "_id": 1 } },
+    ];
+
+    let mut transactions = vec![];
+    for row in aggregate_documents(collection_name, pipeline).await? {
+        let blocks = row
+            .get_array("blocks")
+            .map(|blocks| blocks.iter().map(|block| block.to_string()).collect())
+            .unwrap_or_default();
+        transactions.push((
+            row.get_str("_id").unwrap_or_default().to_string(),
+            blocks,
+            object_ids(&row, "ids"),
+        ));
+    }
+    Ok(transactions)
+}
+
+/// Streams `_id`, `txHash`, `logIndex`, `blockNumber`, `createdAt` and `__v`, in
+/// (blockNumber, logIndex) order
+pub async fn stream_event_ordering(
+    collection_name: &str,
+) -> Result<Cursor<EventOrderingProjection>, mongodb::error::Error> {
+    let collection: Collection<EventOrderingProjection> =
+        get_db().await.database().collection(collection_name);
+    let cursor = collection
+        .find(doc! {
+            "txHash": { "$type": "string" },
+            "blockNumber": { "$type": "number" },
+            "logIndex": { "$type": "number" },
+        })
+        .projection(doc! {
+            "_id": 1, "txHash": 1, "logIndex": 1, "blockNumber": 1, "createdAt": 1, "__v": 1
+        })
+        .sort(doc! { "blockNumber": 1, "logIndex": 1 })
+        .allow_disk_use(true)
+        .await?;
+    Ok(cursor)
+}
+
+async fn aggregate_documents(
+    collection_name: &str,
+    pipeline: Vec<Document>,
+) -> Result<Vec<Document>, mongodb::error::Error> {
+    let collection: Collection<Document> = get_db().await.database().collection(collection_name);
+    let mut cursor = collection.aggregate(pipeline).allow_disk_use(true).await?;
+
+    let mut rows = vec![];
+    while let Some(doc_result) = cursor.next().await {
+        rows.push(doc_result?);
+    }
+    Ok(rows)
+}
+
+// Hex strings of the ObjectIds in an array field
+fn object_ids(doc: &Document, key: &str) -> Vec<String> {
+    doc.get_array(key)
+        .map(|ids| {
+            ids.iter()
+                .map(|id| match id {
+                    mongodb::bson::Bson::ObjectId(id) => id.to_hex(),
+                    other => other.to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Restricts a filter to the documents whose blockNumber falls in the range
+fn with_block_range(filter: Document, block_range: &BlockRange) -> Document {
+    match block_range.filter() {
+        Some(condition) => doc! { "$and": [filter, { "blockNumber": condition }] },
+        None => filter,
+    }
+}
+
+async fn token_events_filter(token_address: &str) -> Result<Document, mongodb::error::Error> {
+    let reserve = match find_reserve_for_a_token(token_address).await? {
+        Some(reserve) => reserve,
+        None => match find_reserve_for_debt_token(token_address).await? {
+            Some(reserve) => reserve,
+            None => match find_reserve_for_reserve_address(token_address).await? {
+                Some(reserve) => reserve,
+                None => {
+                    eprintln!("No reserve found for token address: {}", token_address);
+                    std::process::exit(1) // No matching reserve found
+                }
+            },
         },
     };
 
-    // parse to valid eip55 Address
-    let token_address_eip: Address =
-        Address::from_str(&reserve_address).expect("Invalid token address format");
-    let checksummed = token_address_eip.to_checksum(None);
+    // a reserve address matches the events of both of its tokens
+    let token = parse_db_address(token_address)?;
+    let token_addresses: Vec<String> = if token.matches(&reserve.reserveAddress) {
+        [&reserve.aTokenAddress, &reserve.variableDebtTokenAddress]
+            .iter()
+            .filter_map(|address| address.parse::<DbAddress>().ok())
+            .flat_map(|address| address.variants())
+            .collect()
+    } else {
+        token.variants()
+    };
+    let reserve_address = parse_db_address(&reserve.reserveAddress)?;
 
     // Create filter to match either tokenAddress or reserve
-    let filter = doc! { "$or": [
-        { "tokenAddress": token_address },
-        { "reserve": checksummed },
-    ]};
+    Ok(doc! { "$or": [
+        { "tokenAddress": { "$in": token_addresses } },
+        { "reserve": reserve_address.filter() },
+    ]})
+}
 
-    collect_all_with_filter(collection, filter).await
+/// Returns the collections where at least one document has a `timestamp` field
+pub async fn find_timestamped_collections() -> Result<Vec<String>, mongodb::error::Error> {
+    find_collections_with_fields(&["timestamp"]).await
+}
+
+/// Returns the collections where at least one document has a `blockNumber` and a `createdAt` date
+pub async fn find_indexed_collections() -> Result<Vec<String>, mongodb::error::Error> {
+    find_collections_with_fields(&["blockNumber", "createdAt"]).await
+}
+
+async fn find_collections_with_fields(
+    fields: &[&str],
+) -> Result<Vec<String>, mongodb::error::Error> {
+    let database = get_db().await.database();
+    let mut matching: Vec<String> = vec![];
+
+    let mut filter = Document::new();
+    for field in fields {
+        filter.insert(*field, doc! { "$exists": true });
+    }
+
+    let mut collection_names = get_collections().await;
+    collection_names.sort();
+    for name in collection_names {
+        let collection: Collection<Document> = database.collection(&name);
+        let count = collection.count_documents(filter.clone()).limit(1).await?;
+        if count > 0 {
+            matching.push(name);
+        }
+    }
+    Ok(matching)
+}
+
+/// Streams `_id`, `blockNumber`, `createdAt` and `eventType` of the documents in the
+/// block range, in block order
+pub async fn stream_indexed_documents(
+    collection_name: &str,
+    block_range: &BlockRange,
+) -> Result<Cursor<IndexedDocumentProjection>, mongodb::error::Error> {
+    let collection: Collection<IndexedDocumentProjection> =
+        get_db().await.database().collection(collection_name);
+
+    let filter = with_block_range(
+        doc! {
+            "blockNumber": { "$type": "number" },
+            "createdAt": { "$type": "date" },
+        },
+        block_range,
+    );
+    let cursor = collection
+        .find(filter)
+        .projection(doc! { "_id": 1, "blockNumber": 1, "createdAt": 1, "eventType": 1 })
+        .sort(doc! { "blockNumber": 1 })
+        .allow_disk_use(true)
+        .await?;
+    Ok(cursor)
+}
+
+/// Returns (total documents, documents with a non-null timestamp) for a collection
+pub async fn count_timestamp_coverage(
+    collection_name: &str,
+) -> Result<(u64, u64), mongodb::error::Error> {
+    let collection: Collection<Document> = get_db().await.database().collection(collection_name);
+    let total = count_with_filter(collection.clone(), doc! {}).await?;
+    let with_timestamp = count_with_filter(collection, non_null_timestamp_filter()).await?;
+    Ok((total, with_timestamp))
+}
+
+/// Timestamp coverage grouped in buckets of `bucket_size` blocks, computed server-side
+pub async fn timestamp_coverage_by_block(
+    collection_name: &str,
+    bucket_size: u64,
+) -> Result<Vec<CoverageBucket>, mongodb::error::Error> {
+    let bucket_size = bucket_size as i64;
+    let pipeline = vec![
+        doc! { "$match": { "blockNumber": { "$type": "number" } } },
+        doc! { "$group": {
+            "_id": { "$multiply": [
+                { "$floor": { "$divide": ["$blockNumber", bucket_size] } },
+                bucket_size
+            ]},
+            "total": { "$sum": 1 },
+            "withTimestamp": { "$sum": has_timestamp_expression() },
+        }},
+        doc! { "$sort": { "_id": 1 } },
+    ];
+
+    let buckets = aggregate_coverage(collection_name, pipeline).await?;
+    Ok(buckets
+        .into_iter()
+        .map(|(start, total, with_timestamp)| {
+            let start = start.parse::<f64>().unwrap_or(0.0) as u64;
+            CoverageBucket {
+                label: format!("{} - {}", start, start + bucket_size as u64 - 1),
+                total,
+                with_timestamp,
+            }
+        })
+        .collect())
+}
+
+/// Timestamp coverage grouped by the day each document was indexed (`createdAt`)
+pub async fn timestamp_coverage_by_day(
+    collection_name: &str,
+) -> Result<Vec<CoverageBucket>, mongodb::error::Error> {
+    let pipeline = vec![
+        doc! { "$group": {
+            "_id": { "$ifNull": [
+                { "$dateToString": { "format": "%Y-%m-%d", "date": "$createdAt" } },
+                "unknown"
+            ]},
+            "total": { "$sum": 1 },
+            "withTimestamp": { "$sum": has_timestamp_expression() },
+        }},
+        doc! { "$sort": { "_id": 1 } },
+    ];
+
+    let buckets = aggregate_coverage(collection_name, pipeline).await?;
+    Ok(buckets
+        .into_iter()
+        .map(|(label, total, with_timestamp)| CoverageBucket {
+            label,
+            total,
+            with_timestamp,
+        })
+        .collect())
+}
+
+/// Streams `blockNumber` and whether `timestamp` is set for every document, in block order
+pub async fn stream_block_timestamp_presence(
+    collection_name: &str,
+) -> Result<Cursor<BlockTimestampPresence>, mongodb::error::Error> {
+    let collection: Collection<Document> = get_db().await.database().collection(collection_name);
+    let pipeline = vec![
+        doc! { "$match": { "blockNumber": { "$type": "number" } } },
+        doc! { "$sort": { "blockNumber": 1 } },
+        doc! { "$project": {
+            "_id": 0,
+            "blockNumber": { "$toLong": "$blockNumber" },
+            "hasTimestamp": { "$ne": [{ "$ifNull": ["$timestamp", null] }, null] },
+        }},
+    ];
+    let cursor = collection.aggregate(pipeline).allow_disk_use(true).await?;
+    Ok(cursor.with_type::<BlockTimestampPresence>())
+}
+
+// 1 when the document has a non-null timestamp, 0 otherwise (missing fields count as null)
+fn has_timestamp_expression() -> Document {
+    doc! { "$cond": [{ "$ne": [{ "$ifNull": ["$timestamp", null] }, null] }, 1, 0] }
+}
+
+// Runs a coverage pipeline and returns (bucket id, total, with timestamp) rows
+async fn aggregate_coverage(
+    collection_name: &str,
+    pipeline: Vec<Document>,
+) -> Result<Vec<(String, u64, u64)>, mongodb::error::Error> {
+    let collection: Collection<Document> = get_db().await.database().collection(collection_name);
+    let mut cursor = collection.aggregate(pipeline).allow_disk_use(true).await?;
+
+    let mut rows: Vec<(String, u64, u64)> = vec![];
+    while let Some(doc_result) = cursor.next().await {
+        let doc = doc_result?;
+        let id = match doc.get("_id") {
+            Some(mongodb::bson::Bson::String(label)) => label.clone(),
+            Some(other) => other.to_string(),
+            None => "unknown".to_string(),
+        };
+        let count = |key: &str| -> u64 {
+            doc.get_i32(key)
+                .map(|v| v as u64)
+                .or_else(|_| doc.get_i64(key).map(|v| v as u64))
+                .unwrap_or(0)
+        };
+        rows.push((id, count("total"), count("withTimestamp")));
+    }
+    Ok(rows)
+}
+
+/// Returns every distinct string stored at `field` in the given collection.
+/// Array fields (e.g. `suppliers` or `positions.reserveAddress`) are unwound so
+/// each element is reported on its own.
+pub async fn find_distinct_string_values(
+    collection_name: &str,
+    field: &str,
+) -> Result<Vec<String>, mongodb::error::Error> {
+    let collection: Collection<Document> = get_db().await.database().collection(collection_name);
+
+    let pipeline = vec![
+        doc! { "$project": { "_id": 0, "value": format!("${}", field) } },
+        doc! { "$unwind": "$value" },
+        doc! { "$match": { "value": { "$type": "string" } } },
+        doc! { "$group": { "_id": "$value" } },
+    ];
+
+    let mut cursor = collection.aggregate(pipeline).await?;
+    let mut values: Vec<String> = vec![];
+    while let Some(doc_result) = cursor.next().await {
+        let doc = doc_result?;
+        if let Ok(value) = doc.get_str("_id") {
+            values.push(value.to_string());
+        }
+    }
+    Ok(values)
+}
+
+fn parse_db_address(address: &str) -> Result<DbAddress, mongodb::error::Error> {
+    address.parse::<DbAddress>().map_err(|e| {
+        mongodb::error::Error::from(std::io::Error::new(std::io::ErrorKind::InvalidInput, e))
+    })
 }
 
 // GENERICS
@@ -371,17 +1082,7 @@ where
     T: serde::de::DeserializeOwned + serde::Serialize + std::marker::Send + std::marker::Sync,
 {
     let mut docs: Vec<T> = vec![];
-    let apply_return_filter = some_return_filter.is_some();
-    let mut cursor = if apply_return_filter {
-        let return_filter = some_return_filter.unwrap();
-        let find_options = FindOptions::builder().projection(return_filter).build();
-        collection
-            .find(search_filter)
-            .with_options(find_options)
-            .await?
-    } else {
-        collection.find(search_filter).await?
-    };
+    let mut cursor = stream_helper(collection, search_filter, some_return_filter).await?;
 
     while let Some(doc_result) = cursor.next().await {
         match doc_result {
@@ -394,3 +1095,36 @@ where
     }
     Ok(docs)
 }
+
+// Opens a cursor over the documents matching the filter. The cursor is a
+// `Stream`, so callers can process documents one by one instead of collecting
+// the whole result set into memory.
+async fn stream_helper<T>(
+    collection: Collection<T>,
+    search_filter: Document,
+    some_return_filter: Option<Document>,
+) -> Result<Cursor<T>, mongodb::error::Error>
+where
+    T: serde::de::DeserializeOwned + std::marker::Send + std::marker::Sync,
+{
+    match some_return_filter {
+        Some(return_filter) => {
+            let find_options = FindOptions::builder().projection(return_filter).build();
+            collection
+                .find(search_filter)
+                .with_options(find_options)
+                .await
+        }
+        None => collection.find(search_filter).await,
+    }
+}
+
+async fn count_with_filter<T>(
+    collection: Collection<T>,
+    filter: Document,
+) -> Result<u64, mongodb::error::Error>
+where
+    T: std::marker::Send + std::marker::Sync,
+{
+    collection.count_documents(filter).await
+}
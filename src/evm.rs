@@ -1,9 +1,24 @@
 use alloy::{
-    primitives::Address,
+    primitives::{Address, B256, U256},
     providers::{Provider, ProviderBuilder},
+    rpc::types::{Filter, Log, TransactionReceipt},
     sol,
-    eips::BlockNumberOrTag,
+    sol_types::SolEvent,
+    eips::{BlockId, BlockNumberOrTag},
 };
+use crate::config::block_cache_path;
+use crate::constants::BLOCK_CACHE_CONFIRMATIONS;
+use crate::config::{get_rpc_settings, oracle_address, RpcSettings};
+use crate::functions::decode_reserve_configuration;
+use crate::structs::{AccountData, CachedBlock, EventEmitter, RateStrategyParams, ReserveConfiguration};
+use primitive_types::U256 as RayU256;
+use futures::stream::{self, StreamExt};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::cmp::min;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::Duration;
 
 sol! {
     #[sol(rpc)]
@@ -12,6 +27,15 @@ sol! {
         function totalSupply() public view returns (uint256);
         function scaledTotalSupply() public view returns (uint256);
         function scaledBalanceOf(address user) public view returns (uint256);
+        function decimals() public view returns (uint8);
+        function RESERVE_TREASURY_ADDRESS() public view returns (address);
+    }
+}
+
+sol! {
+    #[sol(rpc)]
+    contract STABLE_DEBT_TOKEN {
+        function getTotalSupplyAndAvgRate() external view returns (uint256, uint256);
     }
 }
 
@@ -40,13 +64,91 @@ sol! {
         uint128 isolationModeTotalDebt;
     }
 
+    #[derive(Debug)]
+    struct UserConfigurationMap {
+        uint256 data;
+    }
+
     #[sol(rpc)]
     contract Pool {
         function getReserveData(address asset) public view returns (ReserveDataLegacy);
+        function getReserveNormalizedIncome(address asset) external view returns (uint256);
+        function getReserveNormalizedVariableDebt(address asset) external view returns (uint256);
+        function getUserAccountData(address user) external view returns (uint256 totalCollateralBase, uint256 totalDebtBase, uint256 availableBorrowsBase, uint256 currentLiquidationThreshold, uint256 ltv, uint256 healthFactor);
+        function getUserConfiguration(address user) external view returns (UserConfigurationMap memory);
+        function getUserEMode(address user) external view returns (uint256);
+        function ADDRESSES_PROVIDER() external view returns (address);
+    }
+}
+
+sol! {
+    #[sol(rpc)]
+    contract POOL_ADDRESSES_PROVIDER {
+        function getPriceOracle() external view returns (address);
+    }
+
+    #[sol(rpc)]
+    contract PRICE_ORACLE {
+        function getAssetsPrices(address[] calldata assets) external view returns (uint256[] memory);
+        function BASE_CURRENCY() external view returns (address);
+        function BASE_CURRENCY_UNIT() external view returns (uint256);
+    }
+}
+
+impl ReserveDataLegacy {
+    /// The configuration bitmap decoded into the reserve settings
+    pub fn decoded_configuration(&self) -> ReserveConfiguration {
+        decode_reserve_configuration(RayU256::from_big_endian(
+            &self.configuration.data.to_be_bytes::<32>(),
+        ))
+    }
+}
+
+sol! {
+    #[derive(Debug)]
+    struct InterestRateDataRay {
+        uint256 optimalUsageRatio;
+        uint256 baseVariableBorrowRate;
+        uint256 variableRateSlope1;
+        uint256 variableRateSlope2;
+    }
+
+    // strategy shared by every reserve (Aave v3.1+)
+    #[sol(rpc)]
+    contract RATE_STRATEGY_V2 {
+        function getInterestRateData(address reserve) external view returns (InterestRateDataRay memory);
+    }
+
+    // one strategy contract per reserve (Aave v3.0)
+    #[sol(rpc)]
+    contract RATE_STRATEGY_V1 {
+        function OPTIMAL_USAGE_RATIO() external view returns (uint256);
+        function getBaseVariableBorrowRate() external view returns (uint256);
+        function getVariableRateSlope1() external view returns (uint256);
+        function getVariableRateSlope2() external view returns (uint256);
     }
 }
 
-const POOL_ADDRESS: &str = "0x553434896d39f867761859d0fe7189d2af70514e";
+sol! {
+    #[derive(Debug)]
+    interface PoolEvents {
+        event Supply(address indexed reserve, address user, address indexed onBehalfOf, uint256 amount, uint16 indexed referralCode);
+        event Withdraw(address indexed reserve, address indexed user, address indexed to, uint256 amount);
+        event Borrow(address indexed reserve, address user, address indexed onBehalfOf, uint256 amount, uint8 interestRateMode, uint256 borrowRate, uint16 indexed referralCode);
+        event Repay(address indexed reserve, address indexed user, address indexed repayer, uint256 amount, bool useATokens);
+        event ReserveDataUpdated(address indexed reserve, uint256 liquidityRate, uint256 stableBorrowRate, uint256 variableBorrowRate, uint256 liquidityIndex, uint256 variableBorrowIndex);
+    }
+
+    #[derive(Debug)]
+    interface ScaledTokenEvents {
+        event Mint(address indexed caller, address indexed onBehalfOf, uint256 value, uint256 balanceIncrease, uint256 index);
+        event Burn(address indexed from, address indexed target, uint256 value, uint256 balanceIncrease, uint256 index);
+        event BalanceTransfer(address indexed from, address indexed to, uint256 value, uint256 index);
+        event Transfer(address indexed from, address indexed to, uint256 value);
+    }
+}
+
+pub const POOL_ADDRESS: &str = "0x553434896d39f867761859d0fe7189d2af70514e";
 
 async fn get_provider() -> Result<impl Provider, Box<dyn std::error::Error>> {
     let provider = ProviderBuilder::new()
@@ -85,6 +187,39 @@ pub async fn get_scaled_balance_of(
     }
 }
 
+/// `scaledBalanceOf` at the end of `block_number`, needs an archive RPC for old blocks
+pub async fn get_scaled_balance_of_at_block(
+    token_address: &str,
+    owner_address: &str,
+    block_number: u64,
+) -> Result<u128, Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    let token_address = token_address.parse::<Address>()?;
+    let owner_address = owner_address.parse::<Address>()?;
+
+    let contract = A_TOKEN::new(token_address, provider);
+    match contract
+        .scaledBalanceOf(owner_address)
+        .call()
+        .block(BlockId::number(block_number))
+        .await
+    {
+        Ok(balance) => Ok(u128::try_from(balance)
+            .map_err(|_| format!("scaled balance {} does not fit in 128 bits", balance))?),
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+pub async fn get_decimals(token_address: &str) -> Result<u8, Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    let token_address = token_address.parse::<Address>()?;
+    let contract = A_TOKEN::new(token_address, provider);
+    match contract.decimals().call().await {
+        Ok(decimals) => Ok(decimals),
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
 pub async fn get_total_supply(token_address: &str) -> Result<u128, Box<dyn std::error::Error>> {
     let provider = get_provider().await.unwrap();
     let token_address = token_address.parse::<Address>()?;
@@ -95,6 +230,20 @@ pub async fn get_total_supply(token_address: &str) -> Result<u128, Box<dyn std::
     }
 }
 
+/// Total supply of a stable debt token and its average stable rate (ray)
+pub async fn get_stable_debt_supply_and_rate(
+    token_address: &str,
+) -> Result<(RayU256, RayU256), Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    let token_address = token_address.parse::<Address>()?;
+    let contract = STABLE_DEBT_TOKEN::new(token_address, provider);
+    let supply_and_rate = contract.getTotalSupplyAndAvgRate().call().await?;
+    Ok((
+        RayU256::from_big_endian(&supply_and_rate._0.to_be_bytes::<32>()),
+        RayU256::from_big_endian(&supply_and_rate._1.to_be_bytes::<32>()),
+    ))
+}
+
 pub async fn get_scaled_total_supply(
     token_address: &str,
 ) -> Result<u128, Box<dyn std::error::Error>> {
@@ -107,18 +256,377 @@ pub async fn get_scaled_total_supply(
     }
 }
 
+/// `scaledTotalSupply` at the end of `block_number`, needs an archive RPC for old blocks
+pub async fn get_scaled_total_supply_at_block(
+    token_address: &str,
+    block_number: u64,
+) -> Result<u128, Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    let token_address = token_address.parse::<Address>()?;
+    let contract = A_TOKEN::new(token_address, provider);
+    match contract
+        .scaledTotalSupply()
+        .call()
+        .block(BlockId::number(block_number))
+        .await
+    {
+        Ok(total_supply) => Ok(u128::try_from(total_supply).map_err(|_| {
+            format!(
+                "scaled total supply {} does not fit in 128 bits",
+                total_supply
+            )
+        })?),
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+/// Treasury receiving the reserve factor of an aToken, lowercase
+pub async fn get_treasury_address(
+    a_token_address: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    let a_token_address = a_token_address.parse::<Address>()?;
+    let contract = A_TOKEN::new(a_token_address, provider);
+    match contract.RESERVE_TREASURY_ADDRESS().call().await {
+        Ok(treasury) => Ok(format!("{:#x}", treasury)),
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+pub async fn get_chain_id() -> Result<u64, Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    Ok(provider.get_chain_id().await?)
+}
+
+/// Fetches the receipts of the given transactions concurrently over a single provider and
+/// returns their logs by transaction hash (`None` when the receipt does not exist)
+pub async fn get_receipt_logs(
+    tx_hashes: &[String],
+) -> Result<HashMap<String, Result<Option<Vec<Log>>, String>>, Box<dyn std::error::Error>> {
+    fetch_receipts(tx_hashes, |receipt| receipt.logs().to_vec()).await
+}
+
+/// Fetches the current hash of the given blocks from the RPC, bypassing the block cache so
+/// that a block replaced since it was cached is seen, by block number
+pub async fn get_block_hashes(
+    block_numbers: &[u64],
+) -> Result<HashMap<u64, Result<B256, String>>, Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    let settings = get_rpc_settings();
+    let unique: BTreeSet<u64> = block_numbers.iter().copied().collect();
+
+    let results = stream::iter(unique)
+        .map(|block_number| {
+            let provider = &provider;
+            let settings = &settings;
+            async move {
+                throttle(settings).await;
+                let result = fetch_block(provider, block_number)
+                    .await
+                    .map(|block| block.hash)
+                    .map_err(|e| e.to_string());
+                (block_number, result)
+            }
+        })
+        .buffer_unordered(settings.max_concurrent_requests)
+        .collect()
+        .await;
+    Ok(results)
+}
+
+// Fetches the receipts concurrently and keeps what `extract` takes out of each one
+async fn fetch_receipts<T>(
+    tx_hashes: &[String],
+    extract: impl Fn(TransactionReceipt) -> T,
+) -> Result<HashMap<String, Result<Option<T>, String>>, Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    let settings = get_rpc_settings();
+    let unique: BTreeSet<&String> = tx_hashes.iter().collect();
+
+    let results = stream::iter(unique)
+        .map(|tx_hash| {
+            let provider = &provider;
+            let settings = &settings;
+            let extract = &extract;
+            async move {
+                throttle(settings).await;
+                let result = match tx_hash.parse::<B256>() {
+                    Ok(hash) => provider
+                        .get_transaction_receipt(hash)
+                        .await
+                        .map(|receipt| receipt.map(extract))
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(format!("Invalid transaction hash: {}", e)),
+                };
+                (tx_hash.clone(), result)
+            }
+        })
+        .buffer_unordered(settings.max_concurrent_requests)
+        .collect()
+        .await;
+    Ok(results)
+}
+
 pub async fn get_last_block() -> Result<u64, Box<dyn std::error::Error>> {
     let provider = get_provider().await?;
 
     match provider.get_block_number().await {
-        Ok(block_number) => Ok(block_number),
+        Ok(block_number) => {
+            lock_block_cache().set_head(block_number);
+            Ok(block_number)
+        }
         Err(e) => Err(Box::new(e)),
     }
 }
 
+/// First block at which `address` has code, found by bisecting `eth_getCode` up to
+/// `latest_block`. None when it has no code at `latest_block`.
+pub async fn get_deployment_block(
+    address: Address,
+    latest_block: u64,
+) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    let (mut low, mut high) = (0, latest_block);
+    if provider.get_code_at(address).number(high).await?.is_empty() {
+        return Ok(None);
+    }
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if provider
+            .get_code_at(address)
+            .number(middle)
+            .await?
+            .is_empty()
+        {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(Some(low))
+}
+
 pub async fn get_block_timestamp(block_number: u64) -> Result<u64, Box<dyn std::error::Error>> {
+    Ok(get_block(block_number).await?.timestamp)
+}
+
+/// Returns the header of a block, from the on-disk block cache when possible
+pub async fn get_block(block_number: u64) -> Result<CachedBlock, Box<dyn std::error::Error>> {
+    if let Some(block) = cached_block(block_number) {
+        return Ok(block);
+    }
+    let provider = get_provider().await?;
+    let block = fetch_block(&provider, block_number).await?;
+    lock_block_cache().insert(&[block]);
+    Ok(block)
+}
+
+/// Returns a block from the on-disk block cache without querying the RPC
+pub fn cached_block(block_number: u64) -> Option<CachedBlock> {
+    lock_block_cache().blocks.get(&block_number).copied()
+}
+
+/// Fetches the headers of the given blocks that are not cached yet, with concurrent
+/// requests over a single provider. Returns the blocks that could not be fetched.
+pub async fn prefetch_blocks(
+    block_numbers: &[u64],
+) -> Result<Vec<(u64, String)>, Box<dyn std::error::Error>> {
+    let missing: BTreeSet<u64> = {
+        let cache = lock_block_cache();
+        block_numbers
+            .iter()
+            .copied()
+            .filter(|block_number| !cache.blocks.contains_key(block_number))
+            .collect()
+    };
+    if missing.is_empty() {
+        return Ok(vec![]);
+    }
+
     let provider = get_provider().await?;
+    let settings = get_rpc_settings();
+    let results: Vec<(u64, Result<CachedBlock, String>)> = stream::iter(missing)
+        .map(|block_number| {
+            let provider = &provider;
+            let settings = &settings;
+            async move {
+                throttle(settings).await;
+                let result = fetch_block(provider, block_number)
+                    .await
+                    .map_err(|e| e.to_string());
+                (block_number, result)
+            }
+        })
+        .buffer_unordered(settings.max_concurrent_requests)
+        .collect()
+        .await;
 
+    let mut fetched = vec![];
+    let mut failed = vec![];
+    for (block_number, result) in results {
+        match result {
+            Ok(block) => fetched.push(block),
+            Err(e) => failed.push((block_number, e)),
+        }
+    }
+    lock_block_cache().insert(&fetched);
+    Ok(failed)
+}
+
+/// Returns the logs emitted by `addresses` with one of the `signatures` as first topic in
+/// `from_block..=to_block`. The range is split in `RPC_LOGS_BLOCK_RANGE` chunks queried
+/// concurrently; a chunk the RPC rejects (e.g. too many results) is split in half and retried.
+pub async fn get_logs_in_range(
+    addresses: &[Address],
+    signatures: &[B256],
+    from_block: u64,
+    to_block: u64,
+) -> Result<Vec<Log>, Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    let settings = get_rpc_settings();
+
+    let chunks: Vec<(u64, u64)> = (from_block..=to_block)
+        .step_by(settings.logs_block_range as usize)
+        .map(|start| (start, min(start + settings.logs_block_range - 1, to_block)))
+        .collect();
+
+    let results: Vec<Result<Vec<Log>, String>> = stream::iter(chunks)
+        .map(|(start, end)| {
+            let provider = &provider;
+            let settings = &settings;
+            async move {
+                get_logs_with_split(provider, settings, addresses, signatures, start, end).await
+            }
+        })
+        .buffered(settings.max_concurrent_requests)
+        .collect()
+        .await;
+
+    let mut logs = vec![];
+    for result in results {
+        logs.extend(result?);
+    }
+    Ok(logs)
+}
+
+async fn get_logs_with_split(
+    provider: &impl Provider,
+    settings: &RpcSettings,
+    addresses: &[Address],
+    signatures: &[B256],
+    from_block: u64,
+    to_block: u64,
+) -> Result<Vec<Log>, String> {
+    // (start, end) ranges still to query, the last one is queried next
+    let mut pending = vec![(from_block, to_block)];
+    let mut logs = vec![];
+    while let Some((start, end)) = pending.pop() {
+        throttle(settings).await;
+        let filter = Filter::new()
+            .address(addresses.to_vec())
+            .event_signature(signatures.to_vec())
+            .from_block(start)
+            .to_block(end);
+        match provider.get_logs(&filter).await {
+            Ok(chunk_logs) => logs.extend(chunk_logs),
+            Err(e) if start < end => {
+                let middle = start + (end - start) / 2;
+                eprintln!(
+                    "eth_getLogs failed for blocks {} - {} ({}), splitting the range",
+                    start, end, e
+                );
+                pending.push((middle + 1, end));
+                pending.push((start, middle));
+            }
+            Err(e) => return Err(format!("eth_getLogs failed for block {}: {}", start, e)),
+        }
+    }
+    Ok(logs)
+}
+
+/// First topics of the events of `emitter` stored in money_market_events, with their event
+/// name. Debt token transfers only mirror mints and burns and are not stored.
+pub fn indexed_event_signatures(emitter: EventEmitter) -> Vec<(B256, &'static str)> {
+    match emitter {
+        EventEmitter::Pool => vec![
+            (PoolEvents::Supply::SIGNATURE_HASH, "Supply"),
+            (PoolEvents::Withdraw::SIGNATURE_HASH, "Withdraw"),
+            (PoolEvents::Borrow::SIGNATURE_HASH, "Borrow"),
+            (PoolEvents::Repay::SIGNATURE_HASH, "Repay"),
+            (
+                PoolEvents::ReserveDataUpdated::SIGNATURE_HASH,
+                "ReserveDataUpdated",
+            ),
+        ],
+        EventEmitter::AToken => vec![
+            (ScaledTokenEvents::Mint::SIGNATURE_HASH, "Mint"),
+            (ScaledTokenEvents::Burn::SIGNATURE_HASH, "Burn"),
+            (
+                ScaledTokenEvents::BalanceTransfer::SIGNATURE_HASH,
+                "BalanceTransfer",
+            ),
+            (ScaledTokenEvents::Transfer::SIGNATURE_HASH, "Transfer"),
+        ],
+        EventEmitter::DebtToken => vec![
+            (ScaledTokenEvents::Mint::SIGNATURE_HASH, "Mint"),
+            (ScaledTokenEvents::Burn::SIGNATURE_HASH, "Burn"),
+        ],
+    }
+}
+
+/// Name of the indexed event a log is, `None` when its emitter is unknown or the event is
+/// not one money_market_events stores for that emitter
+pub fn indexed_event_name(
+    log: &Log,
+    emitters: &HashMap<Address, EventEmitter>,
+) -> Option<&'static str> {
+    let topic = log.topic0()?;
+    let emitter = emitters.get(&log.address())?;
+    indexed_event_signatures(*emitter)
+        .into_iter()
+        .find(|(signature, _)| signature == topic)
+        .map(|(_, name)| name)
+}
+
+// Waits the configured delay before an RPC request
+async fn throttle(settings: &RpcSettings) {
+    if settings.request_delay_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(settings.request_delay_ms)).await;
+    }
+}
+
+/// Binary-searches the last block whose timestamp is at or before `timestamp`
+pub async fn find_block_at_time(timestamp: u64) -> Result<CachedBlock, Box<dyn std::error::Error>> {
+    let latest = get_block(get_last_block().await?).await?;
+    if timestamp >= latest.timestamp {
+        return Ok(latest);
+    }
+    let first = get_block(0).await?;
+    if timestamp < first.timestamp {
+        return Err(format!(
+            "Time {} is before the first block (timestamp {})",
+            timestamp, first.timestamp
+        )
+        .into());
+    }
+
+    // invariant: block `low` is at or before `timestamp`, block `high` is after it
+    let (mut low, mut high) = (first.number, latest.number);
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        if get_block(middle).await?.timestamp <= timestamp {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    get_block(low).await
+}
+
+async fn fetch_block(
+    provider: &impl Provider,
+    block_number: u64,
+) -> Result<CachedBlock, Box<dyn std::error::Error>> {
     match provider
         .get_block_by_number(BlockNumberOrTag::Number(block_number))
         .await
@@ -126,7 +634,11 @@ pub async fn get_block_timestamp(block_number: u64) -> Result<u64, Box<dyn std::
         Ok(block) => match block {
             Some(b) => {
                 let header = b.into_header();
-                Ok(header.timestamp)
+                Ok(CachedBlock {
+                    number: header.number,
+                    timestamp: header.timestamp,
+                    hash: header.hash,
+                })
             }
             None => Err("Block not found".into()),
         },
@@ -134,14 +646,121 @@ pub async fn get_block_timestamp(block_number: u64) -> Result<u64, Box<dyn std::
     }
 }
 
+// Block headers loaded from the cache file, shared by every command of the process.
+// New entries are appended to the file so later runs don't fetch them again, once they
+// are BLOCK_CACHE_CONFIRMATIONS blocks below the head: a reorg could still replace the
+// hash of a more recent block.
+struct BlockCache {
+    path: String,
+    blocks: HashMap<u64, CachedBlock>,
+    // highest block known to exist, a lower bound of the chain head
+    head: u64,
+    // blocks not written to the file yet because they are too close to the head
+    unconfirmed: BTreeSet<u64>,
+}
+
+impl BlockCache {
+    fn load(path: String) -> Self {
+        let blocks = match fs::read_to_string(&path) {
+            Ok(content) => content
+                .lines()
+                .filter_map(CachedBlock::from_line)
+                .map(|block| (block.number, block))
+                .collect(),
+            Err(_) => HashMap::new(),
+        };
+        BlockCache {
+            path,
+            blocks,
+            head: 0,
+            unconfirmed: BTreeSet::new(),
+        }
+    }
+
+    fn insert(&mut self, blocks: &[CachedBlock]) {
+        for block in blocks {
+            self.head = self.head.max(block.number);
+            if self.blocks.insert(block.number, *block).is_none() {
+                self.unconfirmed.insert(block.number);
+            }
+        }
+        self.persist_confirmed();
+    }
+
+    fn set_head(&mut self, head: u64) {
+        self.head = self.head.max(head);
+        self.persist_confirmed();
+    }
+
+    // Appends the blocks that are now deep enough below the head to the cache file
+    fn persist_confirmed(&mut self) {
+        if self.head < BLOCK_CACHE_CONFIRMATIONS {
+            return;
+        }
+        let recent = self
+            .unconfirmed
+            .split_off(&(self.head - BLOCK_CACHE_CONFIRMATIONS + 1));
+        let confirmed = std::mem::replace(&mut self.unconfirmed, recent);
+
+        let mut lines = String::new();
+        for block in confirmed
+            .iter()
+            .filter_map(|number| self.blocks.get(number))
+        {
+            lines.push_str(&block.to_line());
+            lines.push('\n');
+        }
+        if lines.is_empty() {
+            return;
+        }
+        let result = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()));
+        if let Err(e) = result {
+            eprintln!("Error writing block cache {}: {}", self.path, e);
+        }
+    }
+}
+
+static BLOCK_CACHE: LazyLock<Mutex<BlockCache>> =
+    LazyLock::new(|| Mutex::new(BlockCache::load(block_cache_path())));
+
+fn lock_block_cache() -> MutexGuard<'static, BlockCache> {
+    BLOCK_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
 pub async fn get_reserve_data(
     asset_address: &str,
+) -> Result<ReserveDataLegacy, Box<dyn std::error::Error>> {
+    reserve_data(asset_address, BlockId::latest()).await
+}
+
+/// `getReserveData` at the end of `block_number`, needs an archive RPC for old blocks
+pub async fn get_reserve_data_at_block(
+    asset_address: &str,
+    block_number: u64,
+) -> Result<ReserveDataLegacy, Box<dyn std::error::Error>> {
+    reserve_data(asset_address, BlockId::number(block_number)).await
+}
+
+async fn reserve_data(
+    asset_address: &str,
+    block: BlockId,
 ) -> Result<ReserveDataLegacy, Box<dyn std::error::Error>> {
     let provider = get_provider().await?;
     let asset_address = asset_address.parse::<Address>()?;
 
     let contract = Pool::new(POOL_ADDRESS.parse::<Address>()?, provider);
-    match contract.getReserveData(asset_address).call().await {
+    match contract
+        .getReserveData(asset_address)
+        .call()
+        .block(block)
+        .await
+    {
         Ok(reserve_data) => {
             // Validate that the reserve data is not empty/default
             if reserve_data.liquidityIndex == 0
@@ -156,6 +775,178 @@ pub async fn get_reserve_data(
     }
 }
 
+/// Parameters of the interest rate strategy of a reserve, in ray. Reads the shared v3.1
+/// strategy and falls back to the getters of a per-reserve v3.0 strategy.
+pub async fn get_rate_strategy(
+    strategy_address: Address,
+    reserve_address: &str,
+) -> Result<RateStrategyParams, Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    let reserve_address = reserve_address.parse::<Address>()?;
+    let to_ray = |value: U256| -> Result<RayU256, Box<dyn std::error::Error>> {
+        Ok(RayU256::from(u128::try_from(value)?))
+    };
+
+    let v2 = RATE_STRATEGY_V2::new(strategy_address, &provider);
+    if let Ok(data) = v2.getInterestRateData(reserve_address).call().await {
+        return Ok(RateStrategyParams {
+            optimal_usage_ratio: to_ray(data.optimalUsageRatio)?,
+            base_variable_borrow_rate: to_ray(data.baseVariableBorrowRate)?,
+            variable_rate_slope1: to_ray(data.variableRateSlope1)?,
+            variable_rate_slope2: to_ray(data.variableRateSlope2)?,
+        });
+    }
+
+    let v1 = RATE_STRATEGY_V1::new(strategy_address, &provider);
+    Ok(RateStrategyParams {
+        optimal_usage_ratio: to_ray(v1.OPTIMAL_USAGE_RATIO().call().await?)?,
+        base_variable_borrow_rate: to_ray(v1.getBaseVariableBorrowRate().call().await?)?,
+        variable_rate_slope1: to_ray(v1.getVariableRateSlope1().call().await?)?,
+        variable_rate_slope2: to_ray(v1.getVariableRateSlope2().call().await?)?,
+    })
+}
+
+/// `getUserAccountData` of a user
+pub async fn get_user_account_data(
+    user_address: &str,
+) -> Result<AccountData, Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    let user_address = user_address.parse::<Address>()?;
+    let to_u256 = |value: U256| RayU256::from_big_endian(&value.to_be_bytes::<32>());
+
+    let contract = Pool::new(POOL_ADDRESS.parse::<Address>()?, provider);
+    let data = contract.getUserAccountData(user_address).call().await?;
+    let (_, base_currency_unit) = get_base_currency().await?;
+    Ok(AccountData {
+        total_collateral_base: to_u256(data.totalCollateralBase),
+        total_debt_base: to_u256(data.totalDebtBase),
+        available_borrows_base: to_u256(data.availableBorrowsBase),
+        current_liquidation_threshold: to_u256(data.currentLiquidationThreshold),
+        ltv: to_u256(data.ltv),
+        health_factor: to_u256(data.healthFactor),
+        base_currency_unit,
+    })
+}
+
+/// The user configuration bitmap: for reserve id `i`, bit `2i` is set when the user borrows
+/// it and bit `2i + 1` when it is used as collateral
+pub async fn get_user_configuration(
+    user_address: &str,
+) -> Result<RayU256, Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    let user_address = user_address.parse::<Address>()?;
+
+    let contract = Pool::new(POOL_ADDRESS.parse::<Address>()?, provider);
+    let configuration = contract.getUserConfiguration(user_address).call().await?;
+    Ok(RayU256::from_big_endian(
+        &configuration.data.to_be_bytes::<32>(),
+    ))
+}
+
+/// E-mode category of a user, 0 when the user isn't in e-mode
+pub async fn get_user_emode(user_address: &str) -> Result<u64, Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    let user_address = user_address.parse::<Address>()?;
+
+    let contract = Pool::new(POOL_ADDRESS.parse::<Address>()?, provider);
+    let category = contract.getUserEMode(user_address).call().await?;
+    Ok(u64::try_from(category)?)
+}
+
+/// Current liquidity and variable borrow indexes of a reserve, interest accrued since the
+/// last update included
+pub async fn get_normalized_indexes(
+    reserve_address: &str,
+) -> Result<(RayU256, RayU256), Box<dyn std::error::Error>> {
+    let provider = get_provider().await?;
+    let reserve_address = reserve_address.parse::<Address>()?;
+    let to_u256 = |value: U256| RayU256::from_big_endian(&value.to_be_bytes::<32>());
+
+    let contract = Pool::new(POOL_ADDRESS.parse::<Address>()?, provider);
+    let income = contract
+        .getReserveNormalizedIncome(reserve_address)
+        .call()
+        .await?;
+    let debt = contract
+        .getReserveNormalizedVariableDebt(reserve_address)
+        .call()
+        .await?;
+    Ok((to_u256(income), to_u256(debt)))
+}
+
+/// The Aave oracle: `ORACLE_ADDRESS` when set, the one registered in the pool's addresses
+/// provider otherwise
+pub async fn get_price_oracle() -> Result<Address, Box<dyn std::error::Error>> {
+    if let Some(oracle) = oracle_address() {
+        return Ok(oracle.parse::<Address>()?);
+    }
+    let provider = get_provider().await?;
+    let pool = Pool::new(POOL_ADDRESS.parse::<Address>()?, &provider);
+    let addresses_provider = pool.ADDRESSES_PROVIDER().call().await?;
+    Ok(POOL_ADDRESSES_PROVIDER::new(addresses_provider, &provider)
+        .getPriceOracle()
+        .call()
+        .await?)
+}
+
+/// Base currency of the oracle, the zero address for USD, and its unit (1e8 for USD). Prices
+/// and account data amounts are expressed in it. Read once per run.
+pub async fn get_base_currency() -> Result<(Address, RayU256), Box<dyn std::error::Error>> {
+    static BASE_CURRENCY: tokio::sync::OnceCell<(Address, RayU256)> =
+        tokio::sync::OnceCell::const_new();
+    let base_currency = BASE_CURRENCY
+        .get_or_try_init(|| async {
+            let oracle = get_price_oracle().await?;
+            let provider = get_provider().await?;
+            let contract = PRICE_ORACLE::new(oracle, &provider);
+            let currency = contract.BASE_CURRENCY().call().await?;
+            let unit = contract.BASE_CURRENCY_UNIT().call().await?;
+            Ok::<_, Box<dyn std::error::Error>>((
+                currency,
+                RayU256::from_big_endian(&unit.to_be_bytes::<32>()),
+            ))
+        })
+        .await?;
+    Ok(*base_currency)
+}
+
+/// Oracle prices of the assets in the base currency, in the order of `asset_addresses`
+pub async fn get_asset_prices(
+    asset_addresses: &[String],
+) -> Result<Vec<RayU256>, Box<dyn std::error::Error>> {
+    asset_prices(asset_addresses, BlockId::latest()).await
+}
+
+/// Oracle prices of the assets at the end of `block_number`, needs an archive RPC for old blocks
+pub async fn get_asset_prices_at_block(
+    asset_addresses: &[String],
+    block_number: u64,
+) -> Result<Vec<RayU256>, Box<dyn std::error::Error>> {
+    asset_prices(asset_addresses, BlockId::number(block_number)).await
+}
+
+async fn asset_prices(
+    asset_addresses: &[String],
+    block: BlockId,
+) -> Result<Vec<RayU256>, Box<dyn std::error::Error>> {
+    let oracle = get_price_oracle().await?;
+    let provider = get_provider().await?;
+    let assets = asset_addresses
+        .iter()
+        .map(|address| address.parse::<Address>())
+        .collect::<Result<Vec<Address>, _>>()?;
+
+    let prices = PRICE_ORACLE::new(oracle, &provider)
+        .getAssetsPrices(assets)
+        .call()
+        .block(block)
+        .await?;
+    Ok(prices
+        .iter()
+        .map(|price| RayU256::from_big_endian(&price.to_be_bytes::<32>()))
+        .collect())
+}
+
 pub async fn get_atoken_liquidity_index(
     reserve_address: &str,
 ) -> Result<u128, Box<dyn std::error::Error>> {
@@ -169,3 +960,73 @@ pub async fn get_variable_borrow_index(
     let reserve_data = get_reserve_data(reserve_address).await?;
     Ok(reserve_data.variableBorrowIndex)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(address: Address, topic: B256) -> Log {
+        Log {
+            inner: alloy::primitives::Log::new_unchecked(address, vec![topic], Default::default()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn debt_token_transfers_are_not_indexed_events() {
+        let a_token = Address::with_last_byte(1);
+        let debt_token = Address::with_last_byte(2);
+        let emitters = HashMap::from([
+            (a_token, EventEmitter::AToken),
+            (debt_token, EventEmitter::DebtToken),
+        ]);
+        let transfer = ScaledTokenEvents::Transfer::SIGNATURE_HASH;
+
+        assert_eq!(
+            indexed_event_name(&log(a_token, transfer), &emitters),
+            Some("Transfer")
+        );
+        assert_eq!(
+            indexed_event_name(&log(debt_token, transfer), &emitters),
+            None
+        );
+        assert_eq!(
+            indexed_event_name(
+                &log(debt_token, ScaledTokenEvents::Mint::SIGNATURE_HASH),
+                &emitters
+            ),
+            Some("Mint")
+        );
+        assert_eq!(
+            indexed_event_name(&log(Address::with_last_byte(3), transfer), &emitters),
+            None
+        );
+    }
+
+    #[test]
+    fn block_cache_persists_confirmed_blocks_only() {
+        let path = std::env::temp_dir().join(format!("block_cache_test_{}", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let _ = fs::remove_file(&path);
+
+        let mut cache = BlockCache::load(path.clone());
+        let blocks: Vec<CachedBlock> = (0..=100)
+            .map(|number| CachedBlock {
+                number,
+                timestamp: 1_000 + number,
+                hash: B256::with_last_byte(number as u8),
+            })
+            .collect();
+        cache.insert(&blocks);
+        assert_eq!(cache.blocks.len(), 101);
+        assert_eq!(BlockCache::load(path.clone()).blocks.len(), 37);
+
+        // the head moved on, the blocks that became deep enough are written
+        cache.set_head(110);
+        let reloaded = BlockCache::load(path.clone());
+        assert_eq!(reloaded.blocks.len(), 47);
+        assert!(!reloaded.blocks.contains_key(&47));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,6 +1,14 @@
-use crate::constants::{RAY, HALF_RAY};
-use crate::structs::{Flag, FlagType};
+use crate::constants::{
+    RAY, HALF_RAY, WAD, SECONDS_PER_YEAR, PERCENTAGE_FACTOR, HALF_PERCENTAGE_FACTOR,
+};
+use crate::structs::{
+    AddressForm, Flag, FlagType, RateStrategyParams, RecomputedRates, ReserveConfiguration,
+    ReserveDebt, ScaledDelta,
+};
+use alloy::primitives::Address;
+use mongodb::bson::DateTime;
 use primitive_types::U256;
+use std::str::FromStr;
 
 // Returns an optional value for flags that may or may not carry a value (e.g., ValidateTimestamps)
 pub fn extract_optional_value_from_flags(flags: &[Flag], flag_type: FlagType) -> Option<String> {
@@ -17,6 +25,32 @@ pub fn extract_optional_value_from_flags(flags: &[Flag], flag_type: FlagType) ->
         (Flag::GetTokenEvents(value), FlagType::GetTokenEvents) => Some(value.clone()),
         (Flag::GetUserEvents(value), FlagType::GetUserEvents) => Some(value.clone()),
         (Flag::ValidateTimestamps(value_opt), FlagType::ValidateTimestamps) => value_opt.clone(),
+        (Flag::TimestampCoverage(value_opt), FlagType::TimestampCoverage) => value_opt.clone(),
+        (Flag::BucketSize(value), FlagType::BucketSize) => Some(value.clone()),
+        (Flag::TimestampBackfill(value), FlagType::TimestampBackfill) => Some(value.clone()),
+        (Flag::PatchFormat(value), FlagType::PatchFormat) => Some(value.clone()),
+        (Flag::MaxDeviation(value), FlagType::MaxDeviation) => Some(value.clone()),
+        (Flag::AtTime(value), FlagType::AtTime) => Some(value.clone()),
+        (Flag::Since(value), FlagType::Since) => Some(value.clone()),
+        (Flag::IndexingLatency(value_opt), FlagType::IndexingLatency) => value_opt.clone(),
+        (Flag::VerifyEvents(value_opt), FlagType::VerifyEvents) => value_opt.clone(),
+        (Flag::ReorgCheck(value_opt), FlagType::ReorgCheck) => value_opt.clone(),
+        (Flag::CheckUserLists(value_opt), FlagType::CheckUserLists) => value_opt.clone(),
+        (Flag::FindUntrackedHolders(value_opt), FlagType::FindUntrackedHolders) => value_opt.clone(),
+        (Flag::IndexHistory(value), FlagType::IndexHistory) => Some(value.clone()),
+        (Flag::Csv(value), FlagType::Csv) => Some(value.clone()),
+        (Flag::ValidateRates(value_opt), FlagType::ValidateRates) => value_opt.clone(),
+        (Flag::ValidateReserveConfig(value_opt), FlagType::ValidateReserveConfig) => {
+            value_opt.clone()
+        }
+        (Flag::StableDebt(value_opt), FlagType::StableDebt) => value_opt.clone(),
+        (Flag::ReserveHealth(value_opt), FlagType::ReserveHealth) => value_opt.clone(),
+        (Flag::ValidateAccountData(value_opt), FlagType::ValidateAccountData) => value_opt.clone(),
+        (Flag::HealthFactorBelow(value), FlagType::HealthFactorBelow) => Some(value.clone()),
+        (Flag::GetTx(value), FlagType::GetTx) => Some(value.clone()),
+        (Flag::UserTimeline(value), FlagType::UserTimeline) => Some(value.clone()),
+        (Flag::Format(value), FlagType::Format) => Some(value.clone()),
+        (Flag::BisectUser(value), FlagType::BisectUser) => Some(value.clone()),
         _ => None,
     })
 }
@@ -36,12 +70,102 @@ pub fn extract_value_from_flags_or_exit(
     }
 }
 
+/// Parses a hex address regardless of its casing and returns the lowercase form.
+/// Returns None when the input is not a valid 20-byte address.
+pub fn normalize_address(input: &str) -> Option<String> {
+    Address::from_str(input.trim())
+        .ok()
+        .map(|address| format!("{:#x}", address))
+}
+
+/// Parses a hex address regardless of its casing and returns the EIP-55 checksum form.
+/// Returns None when the input is not a valid 20-byte address.
+pub fn checksum_address(input: &str) -> Option<String> {
+    Address::from_str(input.trim())
+        .ok()
+        .map(|address| address.to_checksum(None))
+}
+
+/// Classifies how an address is stored: lowercase and EIP-55 checksum are the
+/// two canonical forms, any other mixed casing is non-canonical.
+pub fn classify_address(stored: &str) -> AddressForm {
+    match checksum_address(stored) {
+        None => AddressForm::Invalid,
+        Some(_) if stored.starts_with("0x") && stored[2..] == stored[2..].to_lowercase() => {
+            AddressForm::Lowercase
+        }
+        Some(checksum) if checksum == stored => AddressForm::Checksum,
+        Some(_) => AddressForm::NonCanonical,
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MathError {
     DivisionByZero,
     Overflow,
 }
 
+/// Parses a point in time into unix seconds. Accepts unix seconds, a date
+/// (`2026-10-01`, midnight UTC) or an RFC 3339 date-time, where the seconds may be
+/// omitted (`2026-10-01T00:00Z`).
+pub fn parse_time_argument(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    if let Ok(seconds) = input.parse::<u64>() {
+        return Ok(seconds);
+    }
+
+    let mut normalized = if input.len() == 10 {
+        format!("{}T00:00:00Z", input)
+    } else {
+        input.to_string()
+    };
+    // add the seconds when only hours and minutes are given
+    if let Some(t) = normalized.find('T') {
+        let time = &normalized[t + 1..];
+        let end = time.find(['Z', 'z', '+', '-']).unwrap_or(time.len());
+        if time[..end].matches(':').count() == 1 {
+            let seconds_at = t + 1 + end;
+            normalized.insert_str(seconds_at, ":00");
+        }
+    }
+
+    match DateTime::parse_rfc3339_str(&normalized) {
+        Ok(date) if date.timestamp_millis() >= 0 => Ok((date.timestamp_millis() / 1000) as u64),
+        _ => Err(format!(
+            "Invalid time '{}', expected unix seconds, YYYY-MM-DD or an RFC 3339 date-time",
+            input
+        )),
+    }
+}
+
+/// Parses a duration such as `90s`, `30m`, `12h`, `7d` or `2w` into seconds
+pub fn parse_duration(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let invalid = || format!("Invalid duration '{}', expected e.g. 30m, 12h or 7d", input);
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = input.split_at(split);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => return Err(invalid()),
+    };
+    amount.checked_mul(multiplier).ok_or_else(invalid)
+}
+
+/// Resolves a `--since` argument, either a duration back from `now` or a point in time
+pub fn parse_since_argument(input: &str, now: u64) -> Result<u64, String> {
+    match parse_duration(input) {
+        Ok(duration) => Ok(now.saturating_sub(duration)),
+        Err(_) => parse_time_argument(input),
+    }
+}
+
 /// Multiplies two ray-scaled numbers with half-up rounding:
 /// result = (a * b + HALF_RAY) / RAY
 pub fn ray_mul(a: U256, b: U256) -> Result<U256, MathError> {
@@ -67,6 +191,279 @@ pub fn ray_div(a: U256, b: U256) -> Result<U256, MathError> {
     with_round.checked_div(b).ok_or(MathError::Overflow)
 }
 
+/// Formats an integer token amount with the token's decimals, without trailing zeros
+/// (`1500000` with 6 decimals is `1.5`)
+pub fn format_units(amount: U256, decimals: u8) -> String {
+    let digits = amount.to_string();
+    let decimals = decimals as usize;
+    if decimals == 0 {
+        return digits;
+    }
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (integer, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        integer.to_string()
+    } else {
+        format!("{}.{}", integer, fraction)
+    }
+}
+
+/// Decimals of a power of ten unit, such as the oracle's `BASE_CURRENCY_UNIT` (1e8 has 8)
+pub fn unit_decimals(unit: U256) -> u8 {
+    (unit.to_string().len() - 1) as u8
+}
+
+/// Scaled balance change behind a Mint / Burn event of an aToken or debt token.
+/// Mints emit `value = amount + balanceIncrease` and burns `value = amount - balanceIncrease`,
+/// except burns smaller than the accrued interest, emitted as a Mint with
+/// `value = balanceIncrease - amount`. Returns the delta and the underlying amount.
+pub fn scaled_balance_delta(
+    is_mint: bool,
+    value: U256,
+    balance_increase: U256,
+    index: U256,
+) -> Result<(ScaledDelta, U256), MathError> {
+    if !is_mint {
+        let amount = value
+            .checked_add(balance_increase)
+            .ok_or(MathError::Overflow)?;
+        return Ok((ScaledDelta::Decrease(ray_div(amount, index)?), amount));
+    }
+    if value >= balance_increase {
+        let amount = value - balance_increase;
+        Ok((ScaledDelta::Increase(ray_div(amount, index)?), amount))
+    } else {
+        let amount = balance_increase - value;
+        Ok((ScaledDelta::Decrease(ray_div(amount, index)?), amount))
+    }
+}
+
+/// Aave's `calculateLinearInterest`: RAY + rate * elapsed / SECONDS_PER_YEAR, the growth of
+/// the liquidity index over `elapsed` seconds
+pub fn linear_interest(rate: U256, elapsed: u64) -> Result<U256, MathError> {
+    let interest = rate
+        .checked_mul(U256::from(elapsed))
+        .ok_or(MathError::Overflow)?
+        / U256::from(SECONDS_PER_YEAR);
+    interest
+        .checked_add(U256::from(RAY))
+        .ok_or(MathError::Overflow)
+}
+
+/// Aave's `calculateCompoundedInterest`, the third-order binomial approximation of
+/// (1 + rate / SECONDS_PER_YEAR) ^ elapsed used for the variable borrow index
+pub fn compounded_interest(rate: U256, elapsed: u64) -> Result<U256, MathError> {
+    if elapsed == 0 {
+        return Ok(U256::from(RAY));
+    }
+    let exp = U256::from(elapsed);
+    let exp_minus_one = U256::from(elapsed - 1);
+    let exp_minus_two = U256::from(elapsed.saturating_sub(2));
+    let year = U256::from(SECONDS_PER_YEAR);
+
+    let base_power_two = ray_mul(rate, rate)? / (year * year);
+    let base_power_three = ray_mul(base_power_two, rate)? / year;
+    let mul = |a: U256, b: U256| a.checked_mul(b).ok_or(MathError::Overflow);
+    let second_term = mul(mul(exp, exp_minus_one)?, base_power_two)? / 2;
+    let third_term = mul(
+        mul(mul(exp, exp_minus_one)?, exp_minus_two)?,
+        base_power_three,
+    )? / 6;
+
+    U256::from(RAY)
+        .checked_add(mul(rate, exp)? / year)
+        .and_then(|sum| sum.checked_add(second_term))
+        .and_then(|sum| sum.checked_add(third_term))
+        .ok_or(MathError::Overflow)
+}
+
+/// Aave's `percentMul`: value * percentage / 10000 with half-up rounding, the percentage
+/// in basis points
+pub fn percent_mul(value: U256, percentage: u128) -> Result<U256, MathError> {
+    value
+        .checked_mul(U256::from(percentage))
+        .and_then(|product| product.checked_add(U256::from(HALF_PERCENTAGE_FACTOR)))
+        .map(|product| product / U256::from(PERCENTAGE_FACTOR))
+        .ok_or(MathError::Overflow)
+}
+
+// `width` bits of the reserve configuration bitmap starting at bit `offset`, at most 64
+fn configuration_bits(configuration: U256, offset: usize, width: usize) -> u64 {
+    let value = (configuration >> offset).low_u64();
+    if width >= 64 {
+        value
+    } else {
+        value & ((1u64 << width) - 1)
+    }
+}
+
+/// Reserve factor in basis points, bits 64-79 of the reserve configuration bitmap
+pub fn reserve_factor(configuration: U256) -> u16 {
+    configuration_bits(configuration, 64, 16) as u16
+}
+
+/// Decodes Aave v3's `ReserveConfigurationMap` bitmap
+pub fn decode_reserve_configuration(configuration: U256) -> ReserveConfiguration {
+    let flag = |bit: usize| configuration_bits(configuration, bit, 1) == 1;
+    ReserveConfiguration {
+        ltv: configuration_bits(configuration, 0, 16) as u16,
+        liquidation_threshold: configuration_bits(configuration, 16, 16) as u16,
+        liquidation_bonus: configuration_bits(configuration, 32, 16) as u16,
+        decimals: configuration_bits(configuration, 48, 8) as u8,
+        active: flag(56),
+        frozen: flag(57),
+        borrowing_enabled: flag(58),
+        stable_borrowing_enabled: flag(59),
+        paused: flag(60),
+        borrowable_in_isolation: flag(61),
+        siloed_borrowing: flag(62),
+        flash_loan_enabled: flag(63),
+        reserve_factor: reserve_factor(configuration),
+        borrow_cap: configuration_bits(configuration, 80, 36),
+        supply_cap: configuration_bits(configuration, 116, 36),
+        liquidation_protocol_fee: configuration_bits(configuration, 152, 16) as u16,
+        emode_category: configuration_bits(configuration, 168, 8) as u8,
+        unbacked_mint_cap: configuration_bits(configuration, 176, 36),
+        debt_ceiling: configuration_bits(configuration, 212, 40),
+    }
+}
+
+/// Rates Aave's default interest rate strategy sets for a reserve with the given debt,
+/// available liquidity and unbacked amount, all in underlying units. The liquidity rate is
+/// earned on the overall borrow rate, the variable rate and the average stable rate weighted
+/// by their debt.
+pub fn calculate_interest_rates(
+    strategy: &RateStrategyParams,
+    debt: &ReserveDebt,
+    available_liquidity: U256,
+    unbacked: U256,
+    reserve_factor: u16,
+) -> Result<RecomputedRates, MathError> {
+    let total_debt = debt
+        .variable
+        .checked_add(debt.stable)
+        .ok_or(MathError::Overflow)?;
+    let (borrow_usage_ratio, supply_usage_ratio) = if total_debt.is_zero() {
+        (U256::zero(), U256::zero())
+    } else {
+        let liquidity_plus_debt = available_liquidity
+            .checked_add(total_debt)
+            .ok_or(MathError::Overflow)?;
+        let with_unbacked = liquidity_plus_debt
+            .checked_add(unbacked)
+            .ok_or(MathError::Overflow)?;
+        (
+            ray_div(total_debt, liquidity_plus_debt)?,
+            ray_div(total_debt, with_unbacked)?,
+        )
+    };
+
+    let optimal = strategy.optimal_usage_ratio;
+    let slopes = if borrow_usage_ratio > optimal {
+        let excess = ray_div(borrow_usage_ratio - optimal, U256::from(RAY) - optimal)?;
+        strategy.variable_rate_slope1 + ray_mul(strategy.variable_rate_slope2, excess)?
+    } else {
+        ray_div(
+            ray_mul(strategy.variable_rate_slope1, borrow_usage_ratio)?,
+            optimal,
+        )?
+    };
+    let variable_borrow_rate = strategy
+        .base_variable_borrow_rate
+        .checked_add(slopes)
+        .ok_or(MathError::Overflow)?;
+    let liquidity_rate = percent_mul(
+        ray_mul(
+            overall_borrow_rate(debt, total_debt, variable_borrow_rate)?,
+            supply_usage_ratio,
+        )?,
+        PERCENTAGE_FACTOR - reserve_factor as u128,
+    )?;
+
+    Ok(RecomputedRates {
+        borrow_usage_ratio,
+        supply_usage_ratio,
+        liquidity_rate,
+        variable_borrow_rate,
+    })
+}
+
+// Variable and average stable rates weighted by their debt, as `_getOverallBorrowRate`
+fn overall_borrow_rate(
+    debt: &ReserveDebt,
+    total_debt: U256,
+    variable_borrow_rate: U256,
+) -> Result<U256, MathError> {
+    if total_debt.is_zero() {
+        return Ok(U256::zero());
+    }
+    let wad_to_ray = |amount: U256| {
+        amount
+            .checked_mul(U256::from(RAY / WAD))
+            .ok_or(MathError::Overflow)
+    };
+    let weighted_variable_rate = ray_mul(wad_to_ray(debt.variable)?, variable_borrow_rate)?;
+    let weighted_stable_rate = ray_mul(wad_to_ray(debt.stable)?, debt.average_stable_rate)?;
+    ray_div(
+        weighted_variable_rate
+            .checked_add(weighted_stable_rate)
+            .ok_or(MathError::Overflow)?,
+        wad_to_ray(total_debt)?,
+    )
+}
+
+/// Whether a user borrows a reserve and uses it as collateral, read from the user
+/// configuration bitmap at the reserve's id
+pub fn user_reserve_flags(user_configuration: U256, reserve_id: u16) -> (bool, bool) {
+    let bits = (user_configuration >> (2 * reserve_id as usize)).low_u64();
+    (bits & 1 == 1, bits & 2 == 2)
+}
+
+/// Aave's health factor, in wad: collateral times the average liquidation threshold (basis
+/// points) over the debt, `U256::MAX` without debt
+pub fn health_factor(
+    collateral_base: U256,
+    debt_base: U256,
+    liquidation_threshold: U256,
+) -> Result<U256, MathError> {
+    if debt_base.is_zero() {
+        return Ok(U256::MAX);
+    }
+    let adjusted = percent_mul(collateral_base, liquidation_threshold.low_u128())?;
+    adjusted
+        .checked_mul(U256::from(WAD))
+        .and_then(|scaled| scaled.checked_add(debt_base / 2))
+        .map(|scaled| scaled / debt_base)
+        .ok_or(MathError::Overflow)
+}
+
+/// Difference between two values relative to `reference`, in basis points. Saturates when
+/// the reference is zero and the values differ.
+pub fn relative_difference_bps(value: U256, reference: U256) -> u128 {
+    let difference = if value > reference {
+        value - reference
+    } else {
+        reference - value
+    };
+    if difference.is_zero() {
+        return 0;
+    }
+    if reference.is_zero() {
+        return u128::MAX;
+    }
+    difference
+        .checked_mul(U256::from(PERCENTAGE_FACTOR))
+        .map(|scaled| scaled / reference)
+        .filter(|bps| *bps <= U256::from(u128::MAX))
+        .map_or(u128::MAX, |bps| bps.as_u128())
+}
+
+/// A ray value as a percentage (0.05e27 is 5.0)
+pub fn ray_to_percent(value: U256) -> f64 {
+    value.to_string().parse::<f64>().unwrap_or(f64::NAN) / 1e25
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -104,6 +501,45 @@ mod tests {
         assert_eq!(ray_div(a, ray(1)).unwrap(), ray(3) / U256::from(2u8));
     }
 
+    #[test]
+    fn normalize_address_lowercases_checksummed_input() {
+        assert_eq!(
+            normalize_address("0x6d7b6956589c17B2755193a67BF2d4B68827E58A").unwrap(),
+            "0x6d7b6956589c17b2755193a67bf2d4b68827e58a"
+        );
+    }
+
+    #[test]
+    fn checksum_address_round_trips_lowercase_input() {
+        assert_eq!(
+            checksum_address("0x6d7b6956589c17b2755193a67bf2d4b68827e58a").unwrap(),
+            "0x6d7b6956589c17B2755193a67BF2d4B68827E58A"
+        );
+    }
+
+    #[test]
+    fn normalize_address_rejects_symbols() {
+        assert!(normalize_address("USDC").is_none());
+        assert!(normalize_address("0x1234").is_none());
+    }
+
+    #[test]
+    fn classify_address_forms() {
+        assert_eq!(
+            classify_address("0x6d7b6956589c17b2755193a67bf2d4b68827e58a"),
+            AddressForm::Lowercase
+        );
+        assert_eq!(
+            classify_address("0x6d7b6956589c17B2755193a67BF2d4B68827E58A"),
+            AddressForm::Checksum
+        );
+        assert_eq!(
+            classify_address("0x6D7B6956589C17B2755193A67BF2D4B68827E58A"),
+            AddressForm::NonCanonical
+        );
+        assert_eq!(classify_address("not-an-address"), AddressForm::Invalid);
+    }
+
     #[test]
     fn division_by_zero() {
         assert!(matches!(
@@ -111,4 +547,225 @@ mod tests {
             Err(MathError::DivisionByZero)
         ));
     }
+
+    #[test]
+    fn parses_time_arguments() {
+        assert_eq!(parse_time_argument("1759276800"), Ok(1_759_276_800));
+        assert_eq!(parse_time_argument("2025-10-01"), Ok(1_759_276_800));
+        assert_eq!(parse_time_argument("2025-10-01T00:00Z"), Ok(1_759_276_800));
+        assert_eq!(
+            parse_time_argument("2025-10-01T02:00:30+02:00"),
+            Ok(1_759_276_830)
+        );
+        assert!(parse_time_argument("yesterday").is_err());
+    }
+
+    #[test]
+    fn parses_durations_and_since() {
+        assert_eq!(parse_duration("90s"), Ok(90));
+        assert_eq!(parse_duration("12h"), Ok(43_200));
+        assert_eq!(parse_duration("7d"), Ok(604_800));
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("d").is_err());
+        assert_eq!(parse_since_argument("1d", 100_000), Ok(13_600));
+        assert_eq!(parse_since_argument("2025-10-01", 0), Ok(1_759_276_800));
+    }
+
+    #[test]
+    fn scaled_delta_of_mints_and_burns() {
+        let index = ray(2);
+        let supply = scaled_balance_delta(true, ray(12), ray(2), index).unwrap();
+        assert_eq!(supply, (ScaledDelta::Increase(ray(5)), ray(10)));
+
+        let withdraw = scaled_balance_delta(false, ray(8), ray(2), index).unwrap();
+        assert_eq!(withdraw, (ScaledDelta::Decrease(ray(5)), ray(10)));
+
+        // a burn smaller than the accrued interest is emitted as a Mint
+        let small_burn = scaled_balance_delta(true, ray(1), ray(3), index).unwrap();
+        assert_eq!(small_burn, (ScaledDelta::Decrease(ray(1)), ray(2)));
+
+        assert!(matches!(
+            scaled_balance_delta(true, ray(1), ray(0), U256::zero()),
+            Err(MathError::DivisionByZero)
+        ));
+    }
+
+    #[test]
+    fn format_units_with_decimals() {
+        assert_eq!(format_units(U256::from(1_500_000u64), 6), "1.5");
+        assert_eq!(format_units(U256::from(42u64), 6), "0.000042");
+        assert_eq!(format_units(U256::from(3_000_000u64), 6), "3");
+        assert_eq!(format_units(U256::zero(), 18), "0");
+        assert_eq!(format_units(U256::from(7u64), 0), "7");
+        assert_eq!(unit_decimals(U256::exp10(8)), 8);
+        assert_eq!(unit_decimals(U256::one()), 0);
+    }
+
+    #[test]
+    fn interest_over_a_year() {
+        let ten_percent = U256::from(RAY / 10);
+        assert_eq!(
+            linear_interest(ten_percent, SECONDS_PER_YEAR).unwrap(),
+            U256::from(RAY + RAY / 10)
+        );
+
+        // close to e^0.1 = 1.10517...
+        let compounded = compounded_interest(ten_percent, SECONDS_PER_YEAR).unwrap();
+        assert!(compounded > U256::from(RAY) * 11051 / 10000);
+        assert!(compounded < U256::from(RAY) * 11052 / 10000);
+
+        assert_eq!(
+            compounded_interest(ten_percent, 0).unwrap(),
+            U256::from(RAY)
+        );
+        assert_eq!(
+            compounded_interest(ten_percent, 1).unwrap(),
+            linear_interest(ten_percent, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn interest_rates_from_utilization() {
+        let strategy = RateStrategyParams {
+            optimal_usage_ratio: U256::from(RAY) * 80 / 100,
+            base_variable_borrow_rate: U256::zero(),
+            variable_rate_slope1: U256::from(RAY) * 4 / 100,
+            variable_rate_slope2: U256::from(RAY) * 60 / 100,
+        };
+
+        // 40% utilization, half of slope1
+        let rates = calculate_interest_rates(
+            &strategy,
+            &variable_debt(400),
+            U256::from(600u64),
+            U256::zero(),
+            1_000,
+        )
+        .unwrap();
+        assert_eq!(rates.borrow_usage_ratio, U256::from(RAY) * 40 / 100);
+        assert_eq!(rates.variable_borrow_rate, U256::from(RAY) * 2 / 100);
+        // 2% * 40% * (1 - 10% reserve factor)
+        assert_eq!(rates.liquidity_rate, U256::from(RAY) * 72 / 10_000);
+
+        // 90% utilization, half of the excess over the 80% optimal
+        let rates = calculate_interest_rates(
+            &strategy,
+            &variable_debt(900),
+            U256::from(100u64),
+            U256::zero(),
+            1_000,
+        )
+        .unwrap();
+        assert_eq!(rates.variable_borrow_rate, U256::from(RAY) * 34 / 100);
+
+        let idle = calculate_interest_rates(
+            &strategy,
+            &ReserveDebt::default(),
+            U256::from(1u64),
+            U256::zero(),
+            0,
+        )
+        .unwrap();
+        assert!(idle.liquidity_rate.is_zero());
+        assert!(idle.variable_borrow_rate.is_zero());
+
+        // 40% utilization, a quarter of it stable debt at 10%: the liquidity rate is earned on
+        // the overall rate, 2% * 75% + 10% * 25% = 4%
+        let debt = ReserveDebt {
+            variable: U256::from(300u64),
+            stable: U256::from(100u64),
+            average_stable_rate: U256::from(RAY) / 10,
+        };
+        let rates =
+            calculate_interest_rates(&strategy, &debt, U256::from(600u64), U256::zero(), 1_000)
+                .unwrap();
+        assert_eq!(rates.variable_borrow_rate, U256::from(RAY) * 2 / 100);
+        // 4% * 40% * (1 - 10% reserve factor)
+        assert_eq!(rates.liquidity_rate, U256::from(RAY) * 144 / 10_000);
+    }
+
+    fn variable_debt(amount: u64) -> ReserveDebt {
+        ReserveDebt {
+            variable: U256::from(amount),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reserve_factor_bits() {
+        let configuration = (U256::from(1_500u64) << 64) | U256::from(0xFFFF_FFFF_FFFF_FFFFu64);
+        assert_eq!(reserve_factor(configuration), 1_500);
+        assert!((ray_to_percent(U256::from(RAY) * 5 / 100) - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn decode_configuration_bitmap() {
+        let configuration = U256::from(8_000u64)
+            | (U256::from(8_250u64) << 16)
+            | (U256::from(10_500u64) << 32)
+            | (U256::from(6u64) << 48)
+            | (U256::one() << 56)
+            | (U256::one() << 58)
+            | (U256::one() << 63)
+            | (U256::from(1_000u64) << 64)
+            | (U256::from(2_000_000u64) << 80)
+            | (U256::from(3_000_000u64) << 116)
+            | (U256::from(1_000u64) << 152)
+            | (U256::from(1u64) << 168)
+            | (U256::from(500_000_000u64) << 212);
+        let decoded = decode_reserve_configuration(configuration);
+
+        assert_eq!(decoded.ltv, 8_000);
+        assert_eq!(decoded.liquidation_threshold, 8_250);
+        assert_eq!(decoded.liquidation_bonus, 10_500);
+        assert_eq!(decoded.decimals, 6);
+        assert!(decoded.active && decoded.borrowing_enabled && decoded.flash_loan_enabled);
+        assert!(!decoded.frozen && !decoded.paused && !decoded.stable_borrowing_enabled);
+        assert_eq!(decoded.reserve_factor, 1_000);
+        assert_eq!(decoded.borrow_cap, 2_000_000);
+        assert_eq!(decoded.supply_cap, 3_000_000);
+        assert_eq!(decoded.liquidation_protocol_fee, 1_000);
+        assert_eq!(decoded.emode_category, 1);
+        assert_eq!(decoded.unbacked_mint_cap, 0);
+        assert_eq!(decoded.debt_ceiling, 500_000_000);
+    }
+
+    #[test]
+    fn user_configuration_and_health_factor() {
+        // reserve 0 borrowed, reserve 1 used as collateral
+        let configuration = U256::from(0b1001u64);
+        assert_eq!(user_reserve_flags(configuration, 0), (true, false));
+        assert_eq!(user_reserve_flags(configuration, 1), (false, true));
+        assert_eq!(user_reserve_flags(configuration, 2), (false, false));
+
+        // 1000 of collateral at 80% against 400 of debt
+        let factor = health_factor(
+            U256::from(1_000u64),
+            U256::from(400u64),
+            U256::from(8_000u64),
+        )
+        .unwrap();
+        assert_eq!(factor, U256::from(WAD) * 2);
+        assert_eq!(
+            health_factor(U256::from(1_000u64), U256::zero(), U256::from(8_000u64)).unwrap(),
+            U256::MAX
+        );
+    }
+
+    #[test]
+    fn relative_difference_in_basis_points() {
+        assert_eq!(
+            relative_difference_bps(U256::from(101u64), U256::from(100u64)),
+            100
+        );
+        assert_eq!(
+            relative_difference_bps(U256::from(99u64), U256::from(100u64)),
+            100
+        );
+        assert_eq!(relative_difference_bps(U256::zero(), U256::zero()), 0);
+        assert_eq!(
+            relative_difference_bps(U256::one(), U256::zero()),
+            u128::MAX
+        );
+    }
 }
@@ -1,43 +1,108 @@
 use crate::db::{
     find_all_reserves,
     find_reserve_for_token,
-    get_orderbook,
-    find_all_users,
-    get_solver_volume,
-    find_docs_with_non_null_timestamp,
+    stream_orderbook,
+    count_docs_with_non_null_timestamp,
+    count_timestamp_coverage,
+    find_timestamped_collections,
+    get_collections,
+    timestamp_coverage_by_block,
+    timestamp_coverage_by_day,
+    stream_block_timestamp_presence,
+    stream_timestamp_and_block_from_solver_volume,
+    find_indexed_collections,
+    stream_indexed_documents,
+    stream_money_market_events,
+    sample_money_market_events,
+    find_event_keys_in_range,
+    stream_docs_with_non_null_timestamp,
+    sample_docs_with_non_null_timestamp,
+    stream_user_addresses,
     //
-    find_all_user_addresses,
     find_all_reserve_addresses,
-    find_user_events,
-    find_token_events,
+    stream_user_events,
+    stream_token_events,
+    find_distinct_string_values,
+    find_duplicate_event_keys,
+    find_chain_id_mismatches,
+    find_transactions_in_several_blocks,
+    stream_event_ordering,
+    find_latest_event_block,
+    find_transaction_events,
+    get_user_position,
+    stream_user_positions,
+    find_reserve_data_updates,
+    find_stable_borrows,
 };
 use crate::evm::{
-    get_last_block, get_balance_of, get_block_timestamp, get_atoken_liquidity_index,
-    get_variable_borrow_index,
+    cached_block, find_block_at_time, get_block, prefetch_blocks, get_chain_id, get_receipt_logs,
+    get_block_hashes, get_decimals, get_scaled_balance_of, get_scaled_balance_of_at_block,
+    get_logs_in_range, indexed_event_signatures, indexed_event_name, POOL_ADDRESS,
+    ScaledTokenEvents, get_last_block, get_balance_of, get_block_timestamp,
+    get_atoken_liquidity_index, get_variable_borrow_index, get_reserve_data, get_rate_strategy,
+    get_total_supply, get_reserve_data_at_block, get_scaled_total_supply_at_block,
+    get_treasury_address, get_user_account_data, get_user_configuration, get_user_emode,
+    get_base_currency, get_deployment_block, get_stable_debt_supply_and_rate,
+    get_normalized_indexes, get_asset_prices, get_asset_prices_at_block,
+};
+use crate::config::{configured_chain_id, get_rpc_settings, treasury_address, usd_tolerance};
+use crate::helpers::{
+    build_user_timeline, compare_and_report_diff, find_user_scaled_position, resolve_reserve,
+    timeline_checkpoints,
 };
-use crate::helpers::{compare_and_report_diff, find_user_scaled_position};
 use crate::validators::{
     validate_user_supply_amount, validate_user_borrow_amount, validate_token_supply_amount,
     validate_token_borrow_amount, validate_user_all_positions, validate_user_all_positions_scaled,
     validate_reserve, validate_scaled_reserve, validate_user_scaled_borrow_amount,
     validate_user_scaled_supply_amount, validate_token_scaled_borrow_amount,
-    validate_token_scaled_supply_amount,
+    validate_token_scaled_supply_amount, verify_event_log, check_event_math,
+    check_transaction_events, reserve_reconciliations, reconcile_user_positions,
+    add_position_holders, check_user_list, transfer_recipients, tracked_reserves,
+    find_untracked_holders, check_index_history, check_reserve_configuration, stable_borrowers,
+    check_reserve_health, recompute_account_totals,
+};
+use crate::functions::{
+    extract_value_from_flags_or_exit, extract_optional_value_from_flags, classify_address,
+    normalize_address, parse_time_argument, parse_since_argument, format_units,
+    calculate_interest_rates, ray_to_percent, relative_difference_bps, ray_mul, unit_decimals,
+};
+use crate::structs::{
+    ReserveTokenField, Flag, FlagType, Collections, AddressForm, AddressFieldReport, EventEmitter,
+    CoverageBucket, MissingTimestampRanges, PatchFormat, TimestampPatch, BlockRange, CachedBlock,
+    LatencyReport, LatencySample, LatencyStats, EventVerificationStatus, EventOrderingRecord,
+    EventOrderingScan, TxInclusionStatus, EventMathStatus, OutputFormat, TimelineEntry,
+    ReserveBalances, BalanceComparison, ScaledDelta, IndexHistoryEntry, IndexStatus, ReserveHealth,
+    AccountData, PricedReserve, PriceBook, ReserveDebt, ReserveReconciliation,
+};
+use crate::models::{
+    ReserveTokenDocument, SolverVolumeDocument, MoneyMarketEventDocument, UserPositionDocument,
 };
-use crate::functions::{extract_value_from_flags_or_exit, extract_optional_value_from_flags};
-use crate::structs::{ReserveTokenField, Flag, FlagType};
-use crate::models::{ReserveTokenDocument, SolverVolumeDocument, MoneyMarketEventDocument};
-use crate::constants::HELP_MESSAGE;
+use crate::constants::{
+    HELP_MESSAGE, DEFAULT_COVERAGE_BUCKET_SIZE, DEFAULT_MAX_TIMESTAMP_DEVIATION_SECONDS,
+    BLOCK_PREFETCH_BATCH_SIZE, LATENCY_WORST_OFFENDERS, DEFAULT_REORG_CHECK_BLOCKS,
+    RATE_RECOMPUTE_TOLERANCE_BPS, DEFAULT_RESERVE_ACTIVITY_WINDOW, ACCOUNT_DATA_TOLERANCE_BPS,
+};
+use alloy::primitives::{Address, B256};
+use alloy::sol_types::SolEvent;
 use futures::future::join_all;
+use futures::stream::{self, BoxStream, StreamExt};
+use mongodb::Cursor;
+use mongodb::bson::{doc, Bson, DateTime};
+use primitive_types::U256;
 use tokio::task;
-use rand::seq::index::sample;
 use std::cmp::min;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub async fn handle_help() {
     println!("{}", HELP_MESSAGE);
 }
 
 pub async fn handle_orderbook() {
-    let book = match get_orderbook().await {
+    let mut book = match stream_orderbook().await {
         Ok(book) => book,
         Err(e) => {
             eprintln!("Error fetching orderbook: {}", e);
@@ -45,51 +110,179 @@ pub async fn handle_orderbook() {
         }
     };
 
-    if book.is_empty() {
-        println!("Orderbook is empty.");
-    } else {
-        for order in book {
-            println!("{:?}", order);
+    let mut order_count = 0;
+    while let Some(order_result) = book.next().await {
+        match order_result {
+            Ok(order) => {
+                order_count += 1;
+                println!("{:?}", order);
+            }
+            Err(e) => {
+                eprintln!("Error reading orderbook: {}", e);
+                std::process::exit(1);
+            }
         }
     }
+
+    if order_count == 0 {
+        println!("Orderbook is empty.");
+    }
+}
+
+pub async fn handle_timestamp_coverage(flags: Vec<Flag>) {
+    let bucket_size = match extract_optional_value_from_flags(&flags, FlagType::BucketSize) {
+        Some(value) => match value.parse::<u64>() {
+            Ok(size) if size > 0 => size,
+            _ => {
+                eprintln!(
+                    "Invalid --bucket-size '{}', expected a positive number of blocks",
+                    value
+                );
+                std::process::exit(1);
+            }
+        },
+        None => DEFAULT_COVERAGE_BUCKET_SIZE,
+    };
+
+    let collection_names =
+        match extract_optional_value_from_flags(&flags, FlagType::TimestampCoverage) {
+            Some(collection_name) => {
+                if !get_collections().await.contains(&collection_name) {
+                    eprintln!("Collection '{}' not found in the database", collection_name);
+                    std::process::exit(1);
+                }
+                vec![collection_name]
+            }
+            None => match find_timestamped_collections().await {
+                Ok(names) => names,
+                Err(e) => {
+                    eprintln!("Error finding collections with a timestamp field: {}", e);
+                    std::process::exit(1);
+                }
+            },
+        };
+
+    if collection_names.is_empty() {
+        println!("No collections with a timestamp field found in the database.");
+        return;
+    }
+
+    for collection_name in collection_names {
+        report_timestamp_coverage(&collection_name, bucket_size).await;
+    }
 }
 
-pub async fn handle_timestamp_coverage() {
-    let all_docs = match get_solver_volume().await {
-        Ok(docs) => docs,
+async fn report_timestamp_coverage(collection_name: &str, bucket_size: u64) {
+    println!("\n=== Timestamp coverage: {} ===", collection_name);
+
+    let (total_count, non_null_count) = match count_timestamp_coverage(collection_name).await {
+        Ok(counts) => counts,
         Err(e) => {
-            eprintln!("Error fetching solver volume: {}", e);
+            eprintln!("Error counting documents in {}: {}", collection_name, e);
             std::process::exit(1);
         }
     };
 
-    let non_null_docs = match find_docs_with_non_null_timestamp().await {
-        Ok(docs) => docs,
+    if total_count == 0 {
+        println!("No documents found in the collection.");
+        return;
+    }
+    println!("Total documents: {}", total_count);
+    println!("Documents with non-null timestamp: {}", non_null_count);
+    println!(
+        "Coverage percentage: {:.2}%",
+        (non_null_count as f64 / total_count as f64) * 100.0
+    );
+
+    match timestamp_coverage_by_block(collection_name, bucket_size).await {
+        Ok(buckets) => print_coverage_buckets(
+            &format!("By block range ({} blocks)", bucket_size),
+            &buckets,
+        ),
+        Err(e) => eprintln!("Error aggregating coverage by block range: {}", e),
+    }
+
+    match timestamp_coverage_by_day(collection_name).await {
+        Ok(buckets) => print_coverage_buckets("By day indexed (createdAt)", &buckets),
+        Err(e) => eprintln!("Error aggregating coverage by day: {}", e),
+    }
+
+    let mut cursor = match stream_block_timestamp_presence(collection_name).await {
+        Ok(cursor) => cursor,
         Err(e) => {
-            eprintln!("Error fetching documents with non-null timestamp: {}", e);
-            std::process::exit(1);
+            eprintln!(
+                "Error streaming block numbers from {}: {}",
+                collection_name, e
+            );
+            return;
         }
     };
 
-    if all_docs.is_empty() {
-        println!("No documents found in the database.");
-    } else {
-        println!("Total documents in the database: {}", all_docs.len());
+    let mut missing = MissingTimestampRanges::new();
+    let mut last_timestamped_block: Option<u64> = None;
+    let mut last_block: Option<u64> = None;
+    while let Some(result) = cursor.next().await {
+        match result {
+            Ok(presence) => {
+                if presence.hasTimestamp {
+                    last_timestamped_block = Some(presence.blockNumber);
+                }
+                last_block = Some(presence.blockNumber);
+                missing.push(presence.blockNumber, presence.hasTimestamp);
+            }
+            Err(e) => eprintln!("Error reading document: {}", e),
+        }
     }
 
-    if non_null_docs.is_empty() {
-        println!("Coverage: 100% (no documents with null timestamp)");
-    } else {
-        println!("Documents with non-null timestamp: {}", non_null_docs.len());
+    if missing.ranges.is_empty() {
+        println!("\nNo block ranges with missing timestamps.");
+        return;
     }
 
-    let coverage = if all_docs.is_empty() {
-        100.0
-    } else {
-        (non_null_docs.len() as f64 / all_docs.len() as f64) * 100.0
-    };
+    println!("\nBlock ranges with missing timestamps:");
+    for range in &missing.ranges {
+        println!(
+            "  {} - {} ({} documents)",
+            range.from_block, range.to_block, range.documents
+        );
+    }
+
+    if missing.is_open() {
+        match last_timestamped_block {
+            Some(block) => println!(
+                "\nTimestamps stop after block {}: every document up to the latest indexed block {} is missing one, the backfill appears to have stopped.",
+                block,
+                last_block.unwrap_or(block)
+            ),
+            None => println!(
+                "\nNo document in this collection has a timestamp, the backfill never ran."
+            ),
+        }
+    } else if let Some(block) = last_timestamped_block {
+        println!(
+            "\nThe latest indexed documents have timestamps (last at block {}).",
+            block
+        );
+    }
+}
 
-    println!("Coverage percentage: {:.2}%", coverage);
+fn print_coverage_buckets(title: &str, buckets: &[CoverageBucket]) {
+    println!("\n{}:", title);
+    for bucket in buckets {
+        let marker = if bucket.with_timestamp < bucket.total {
+            "  <- missing"
+        } else {
+            ""
+        };
+        println!(
+            "  {:<25} {:>8}/{:<8} {:>7.2}%{}",
+            bucket.label,
+            bucket.with_timestamp,
+            bucket.total,
+            bucket.coverage(),
+            marker
+        );
+    }
 }
 
 pub async fn handle_all_tokens() {
@@ -153,66 +346,70 @@ pub async fn handle_validate_timestamp(flags: Vec<Flag>) {
     // Optional numeric argument: if present, validate only that many entries; otherwise, validate all
     let maybe_count_str = extract_optional_value_from_flags(&flags, FlagType::ValidateTimestamps);
 
-    // create count amount of random indexes that are within the range of all_docs
-    let all_docs = match find_docs_with_non_null_timestamp().await {
-        Ok(docs) => docs,
-        Err(e) => {
-            eprintln!("Error fetching documents with non-null timestamp: {}", e);
-            std::process::exit(1);
-        }
-    };
-
-    let docs_to_validate = match maybe_count_str {
-        None => {
-            // Validate all timestamp entries
-            println!(
-                "Validating all timestamp entries ({} found)...",
-                all_docs.len()
-            );
-            all_docs
-        }
-        Some(count_str) => {
-            let count: usize = match count_str.parse() {
-                Ok(n) if n > 0 => n,
-                _ => {
-                    eprintln!(
-                        "Error: --validate-timestamps expects a positive integer (1..=100) when provided an argument."
-                    );
-                    std::process::exit(1);
+    let docs_to_validate: BoxStream<'static, Result<SolverVolumeDocument, String>> =
+        match maybe_count_str {
+            None => {
+                // Validate all timestamp entries
+                let total = count_docs_with_non_null_timestamp()
+                    .await
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error counting documents with non-null timestamp: {}", e);
+                        std::process::exit(1);
+                    });
+                println!("Validating all timestamp entries ({} found)...", total);
+                match stream_docs_with_non_null_timestamp().await {
+                    Ok(cursor) => cursor
+                        .map(|doc_result| doc_result.map_err(|e| e.to_string()))
+                        .boxed(),
+                    Err(e) => {
+                        eprintln!("Error fetching documents with non-null timestamp: {}", e);
+                        std::process::exit(1);
+                    }
                 }
-            };
-            // Cap to a maximum of 100 entries
-            let count = min(count, 100);
-            let to_validate = min(count, all_docs.len());
-            println!("Validating {} timestamp entries...", to_validate);
-            let indexes = sample(&mut rand::rng(), all_docs.len(), to_validate);
-            let mut selected_docs = Vec::new();
-            for idx in indexes.iter() {
-                selected_docs.push(all_docs[idx].clone());
             }
-            selected_docs
-        }
-    };
-
-    // Process documents in parallel using tokio tasks
-    let tasks: Vec<_> = docs_to_validate
-        .into_iter()
-        .map(|doc| task::spawn(async move { handle_compare_timestamp(doc).await }))
-        .collect();
+            Some(count_str) => {
+                let count: usize = match count_str.parse() {
+                    Ok(n) if n > 0 => n,
+                    _ => {
+                        eprintln!(
+                            "Error: --validate-timestamps expects a positive integer (1..=100) when provided an argument."
+                        );
+                        std::process::exit(1);
+                    }
+                };
+                // Cap to a maximum of 100 entries and let the database pick them
+                let count = min(count, 100);
+                let selected_docs = match sample_docs_with_non_null_timestamp(count).await {
+                    Ok(docs) => docs,
+                    Err(e) => {
+                        eprintln!("Error sampling documents with non-null timestamp: {}", e);
+                        std::process::exit(1);
+                    }
+                };
+                println!("Validating {} timestamp entries...", selected_docs.len());
+                stream::iter(selected_docs.into_iter().map(Ok)).boxed()
+            }
+        };
 
-    // Wait for all tasks to complete and collect results
-    let results = join_all(tasks).await;
+    // Process documents concurrently as they are read from the cursor
+    let results: Vec<Result<u64, String>> = docs_to_validate
+        .map(|doc_result| async move {
+            match doc_result {
+                Ok(doc) => handle_compare_timestamp(doc).await,
+                Err(e) => Err(format!("Error reading document: {}", e)),
+            }
+        })
+        .buffer_unordered(get_rpc_settings().max_concurrent_requests)
+        .collect()
+        .await;
 
     // Process results and collect diffs
     let mut all_diffs: Vec<u64> = Vec::new();
     for result in results {
         match result {
-            Ok(Ok(diff)) => all_diffs.push(diff),
-            Ok(Err(e)) => {
-                eprintln!("Error processing document: {}", e);
-            }
+            Ok(diff) => all_diffs.push(diff),
             Err(e) => {
-                eprintln!("Task join error: {}", e);
+                eprintln!("Error processing document: {}", e);
             }
         }
     }
@@ -235,928 +432,4180 @@ pub async fn handle_validate_timestamp(flags: Vec<Flag>) {
     }
 }
 
-pub async fn handle_balance_of(flags: Vec<Flag>) {
-    let error_message =
-        "Error: --balance-of requires both a token address and a user address to be specified.";
-
-    let token_type_passed = flags
-        .iter()
-        .find_map(|f| match f {
-            Flag::ReserveToken(_) => Some(FlagType::ReserveToken),
-            Flag::AToken(_) => Some(FlagType::AToken),
-            Flag::DebtToken(_) => Some(FlagType::DebtToken),
-            _ => None,
-        })
-        .unwrap_or_else(|| {
-            eprintln!("{}", error_message);
+pub async fn handle_timestamp_backfill(flags: Vec<Flag>) {
+    let output_path = extract_value_from_flags_or_exit(
+        flags.clone(),
+        FlagType::TimestampBackfill,
+        "Error: --timestamp-backfill requires an output file path.",
+    );
+    let format = match extract_optional_value_from_flags(&flags, FlagType::PatchFormat) {
+        Some(value) => value.parse::<PatchFormat>().unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
             std::process::exit(1);
-        });
-    let token_passed =
-        extract_value_from_flags_or_exit(flags.clone(), token_type_passed, error_message);
+        }),
+        None => PatchFormat::JsonLines,
+    };
+    let max_deviation = match extract_optional_value_from_flags(&flags, FlagType::MaxDeviation) {
+        Some(value) => value.parse::<u64>().unwrap_or_else(|_| {
+            eprintln!(
+                "Error: --max-deviation expects a number of seconds, got '{}'",
+                value
+            );
+            std::process::exit(1);
+        }),
+        None => DEFAULT_MAX_TIMESTAMP_DEVIATION_SECONDS,
+    };
 
-    let user_address =
-        extract_value_from_flags_or_exit(flags.clone(), FlagType::BalanceOf, error_message);
+    // Never overwrite an existing patch, it may be under review
+    let file = match File::create_new(&output_path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Error creating patch file {}: {}", output_path, e);
+            std::process::exit(1);
+        }
+    };
+    let mut writer = BufWriter::new(file);
 
-    match get_balance_of(&token_passed, &user_address).await {
-        Ok(balance) => println!(
-            "Balance of {} for token {}: {}",
-            user_address, token_passed, balance
-        ),
+    let cursor = match stream_timestamp_and_block_from_solver_volume().await {
+        Ok(cursor) => cursor,
         Err(e) => {
-            eprintln!("Error fetching balance: {}", e);
+            eprintln!("Error fetching solver volume documents: {}", e);
             std::process::exit(1);
         }
-    }
-}
+    };
 
-pub async fn handle_user_position(flags: Vec<Flag>) {
-    let error_message = "Error: --user-position requires a user address to be specified.";
-    let user_address =
-        extract_value_from_flags_or_exit(flags.clone(), FlagType::UserPosition, error_message);
+    let collection_name = Collections::new().solver_volume;
+    let mut blocks_resolved: usize = 0;
+    let mut batches = cursor.chunks(BLOCK_PREFETCH_BATCH_SIZE);
+    let mut scanned = 0;
+    let mut null_patched = 0;
+    let mut deviating_patched = 0;
+    let mut unresolved = 0;
 
-    let token_address_tuple = flags
-        .iter()
-        .find_map(|f| match f {
-            Flag::ReserveToken(address) => Some((address.clone(), ReserveTokenField::Reserve)),
-            Flag::AToken(address) => Some((address.clone(), ReserveTokenField::AToken)),
-            Flag::DebtToken(address) => {
-                Some((address.clone(), ReserveTokenField::VariableDebtToken))
-            }
-            _ => None,
-        })
-        .unwrap_or_else(|| {
-            eprintln!("{}", error_message);
-            std::process::exit(1);
-        });
-    let (token_address, field) = token_address_tuple;
-    let reserve_data: ReserveTokenDocument = find_reserve_for_token(&token_address, field)
-        .await
-        .unwrap_or_else(|_| {
-            eprintln!(
-                "Error: No reserve data found for token address {}",
-                token_address
-            );
-            std::process::exit(1);
-        })
-        .unwrap_or_else(|| {
-            eprintln!(
-                "Error: No reserve data found for token address {}",
-                token_address
-            );
-            std::process::exit(1);
-        });
+    while let Some(batch) = batches.next().await {
+        let docs: Vec<_> = batch
+            .into_iter()
+            .filter_map(|doc_result| match doc_result {
+                Ok(doc) => Some(doc),
+                Err(e) => {
+                    eprintln!("Error reading document: {}", e);
+                    None
+                }
+            })
+            .collect();
+        scanned += docs.len();
 
-    match find_user_scaled_position(&user_address, &reserve_data.reserveAddress).await {
-        Ok(position) => {
-            println!(
-                "User position for {} on reserve {}: {:?}",
-                user_address, token_address, position
-            );
+        let mut block_numbers: Vec<u64> = docs.iter().map(|doc| doc.blockNumber).collect();
+        block_numbers.dedup();
+        blocks_resolved += block_numbers.len();
+        match prefetch_blocks(&block_numbers).await {
+            Ok(failed) => {
+                for (block_number, e) in failed {
+                    eprintln!("Error fetching timestamp for block {}: {}", block_number, e);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error connecting to the RPC: {}", e);
+                std::process::exit(1);
+            }
         }
-        Err(e) => {
-            eprintln!("Error fetching user position: {}", e);
-            std::process::exit(1);
+
+        for doc in docs {
+            let Some(block_timestamp) = cached_block(doc.blockNumber).map(|block| block.timestamp)
+            else {
+                unresolved += 1;
+                continue;
+            };
+            let Some(patch) = TimestampPatch::propose(
+                doc.id,
+                doc.blockNumber,
+                doc.timestamp,
+                block_timestamp,
+                max_deviation,
+            ) else {
+                continue;
+            };
+            if patch.current.is_some() {
+                deviating_patched += 1;
+            } else {
+                null_patched += 1;
+            }
+            if let Err(e) = writeln!(writer, "{}", patch.to_line(format, collection_name)) {
+                eprintln!("Error writing patch file {}: {}", output_path, e);
+                std::process::exit(1);
+            }
         }
+        println!("Scanned {} documents...", scanned);
+    }
+
+    if let Err(e) = writer.flush() {
+        eprintln!("Error writing patch file {}: {}", output_path, e);
+        std::process::exit(1);
+    }
+
+    println!("\n=== Timestamp backfill proposal ===");
+    println!("Documents scanned: {}", scanned);
+    println!("Blocks checked: {}", blocks_resolved);
+    println!("Null timestamps patched: {}", null_patched);
+    println!(
+        "Deviating timestamps patched (> {}s): {}",
+        max_deviation, deviating_patched
+    );
+    if unresolved > 0 {
+        println!("Skipped (block timestamp unavailable): {}", unresolved);
     }
+    println!(
+        "Patch written to {} ({} entries). The database was not modified.",
+        output_path,
+        null_patched + deviating_patched
+    );
 }
 
-pub async fn handle_token(flags: Vec<Flag>) {
-    let token_address_tuple = flags
-        .iter()
-        .find_map(|f| match f {
-            Flag::ReserveToken(address) => Some((address.clone(), ReserveTokenField::Reserve)),
-            Flag::AToken(address) => Some((address.clone(), ReserveTokenField::AToken)),
-            Flag::DebtToken(address) => {
-                Some((address.clone(), ReserveTokenField::VariableDebtToken))
+pub async fn handle_indexing_latency(flags: Vec<Flag>) {
+    let block_range = resolve_block_range_or_exit(&flags).await;
+
+    let collection_names =
+        match extract_optional_value_from_flags(&flags, FlagType::IndexingLatency) {
+            Some(collection_name) => {
+                if !get_collections().await.contains(&collection_name) {
+                    eprintln!("Collection '{}' not found in the database", collection_name);
+                    std::process::exit(1);
+                }
+                vec![collection_name]
             }
-            _ => None,
-        })
-        .unwrap_or_else(|| {
-            eprintln!("Error: --reserve-token, --a-token or --debt-token is required.");
-            std::process::exit(1);
-        });
-    let (token_address, field) = token_address_tuple;
+            None => match find_indexed_collections().await {
+                Ok(names) => names,
+                Err(e) => {
+                    eprintln!(
+                        "Error finding collections with blockNumber and createdAt: {}",
+                        e
+                    );
+                    std::process::exit(1);
+                }
+            },
+        };
 
-    match find_reserve_for_token(&token_address, field).await {
-        Ok(token_data) => println!("Reserve data for token {}: {:?}", token_address, token_data),
-        Err(e) => {
-            eprintln!("Error fetching reserve token data: {}", e);
-            std::process::exit(1);
-        }
+    if collection_names.is_empty() {
+        println!("No collections with blockNumber and createdAt found in the database.");
+        return;
+    }
+
+    for collection_name in collection_names {
+        report_indexing_latency(&collection_name, &block_range).await;
     }
 }
 
-pub async fn handle_validate_user_supply(flags: Vec<Flag>) {
-    let user_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ValidateUserSupply,
-        "Error: --validate-user-supply requires a user address to be specified.",
-    );
+async fn report_indexing_latency(collection_name: &str, block_range: &BlockRange) {
+    println!("\n=== Indexing latency: {} ===", collection_name);
 
-    let reserve_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ReserveToken,
-        "Error: --validate-user-supply requires --reserve-token to be specified.",
-    );
+    let cursor = match stream_indexed_documents(collection_name, block_range).await {
+        Ok(cursor) => cursor,
+        Err(e) => {
+            eprintln!("Error fetching documents from {}: {}", collection_name, e);
+            return;
+        }
+    };
 
-    match validate_user_supply_amount(&user_address, &reserve_address).await {
-        Ok(result) => {
-            println!("User Supply Validation Results:");
-            println!("  Database Amount: {}", result.database_amount);
-            println!("  On-Chain Amount: {}", result.on_chain_amount);
-            println!("  Difference: {}", result.difference);
-            println!("  Percentage: {:.4}%", result.percentage);
+    // one report for the whole collection and one per event type
+    let mut overall = LatencyReport::new(LATENCY_WORST_OFFENDERS);
+    let mut by_event_type: BTreeMap<String, LatencyReport> = BTreeMap::new();
+    let mut unresolved = 0;
+    let mut batches = cursor.chunks(BLOCK_PREFETCH_BATCH_SIZE);
 
-            let report = compare_and_report_diff(
-                result.database_amount,
-                result.on_chain_amount,
-                &format!(
-                    "user {} supply for reserve {}",
-                    user_address, reserve_address
-                ),
-            );
-            println!("  Status: {}", report);
+    while let Some(batch) = batches.next().await {
+        let docs: Vec<_> = batch
+            .into_iter()
+            .filter_map(|doc_result| match doc_result {
+                Ok(doc) => Some(doc),
+                Err(e) => {
+                    eprintln!("Error reading document: {}", e);
+                    None
+                }
+            })
+            .collect();
+
+        let mut block_numbers: Vec<u64> = docs.iter().map(|doc| doc.blockNumber).collect();
+        block_numbers.dedup();
+        match prefetch_blocks(&block_numbers).await {
+            Ok(failed) => {
+                for (block_number, e) in failed {
+                    eprintln!("Error fetching timestamp for block {}: {}", block_number, e);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error connecting to the RPC: {}", e);
+                std::process::exit(1);
+            }
         }
-        Err(e) => {
-            eprintln!("Error validating user supply: {}", e);
-            std::process::exit(1);
+
+        for doc in docs {
+            let Some(block) = cached_block(doc.blockNumber) else {
+                unresolved += 1;
+                continue;
+            };
+            let sample = LatencySample {
+                id: doc.id.to_hex(),
+                block_number: doc.blockNumber,
+                latency: doc.createdAt.timestamp_millis() / 1000 - block.timestamp as i64,
+            };
+            let day = doc
+                .createdAt
+                .try_to_rfc3339_string()
+                .map(|date| date[..10].to_string())
+                .unwrap_or_else(|_| "unknown".to_string());
+            if let Some(event_type) = doc.eventType {
+                by_event_type
+                    .entry(event_type)
+                    .or_insert_with(|| LatencyReport::new(LATENCY_WORST_OFFENDERS))
+                    .push(sample.clone(), day.clone());
+            }
+            overall.push(sample, day);
         }
     }
-}
-pub async fn handle_validate_user_scaled_supply(flags: Vec<Flag>) {
-    let user_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ValidateUserSupply,
-        "Error: --validate-user-supply <USER_ADDRESS> --scaled requires a user address to be specified.",
-    );
-
-    let reserve_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ReserveToken,
-        "Error: --validate-user-supply <USER_ADDRESS> ==scaled requires --reserve-token to be specified.",
-    );
 
-    match validate_user_scaled_supply_amount(&user_address, &reserve_address).await {
-        Ok(result) => {
-            println!("User Scaled Supply Validation Results:");
-            println!("  Database Amount: {}", result.database_amount);
-            println!("  On-Chain Amount: {}", result.on_chain_amount);
-            println!("  Difference: {}", result.difference);
-            println!("  Percentage: {:.4}%", result.percentage);
+    let Some(overall_stats) = overall.stats() else {
+        println!(
+            "No documents with blockNumber and createdAt found ({}).",
+            block_range
+        );
+        return;
+    };
 
-            let report = compare_and_report_diff(
-                result.database_amount,
-                result.on_chain_amount,
-                &format!(
-                    "user {} scaled supply for reserve {}",
-                    user_address, reserve_address
-                ),
-            );
-            println!("  Status: {}", report);
-        }
-        Err(e) => {
-            eprintln!("Error validating user scaled supply: {}", e);
-            std::process::exit(1);
+    println!("Latency in seconds (createdAt - block timestamp):");
+    print_latency_stats("all documents", &overall_stats);
+    for (event_type, report) in &by_event_type {
+        if let Some(stats) = report.stats() {
+            print_latency_stats(event_type, &stats);
         }
     }
-}
 
-pub async fn handle_validate_user_borrow(flags: Vec<Flag>) {
-    let user_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ValidateUserBorrow,
-        "Error: --validate-user-borrow requires a user address to be specified.",
-    );
+    println!("\nBy day indexed (createdAt):");
+    for (day, stats) in overall.daily_stats() {
+        let marker = if stats.p95 > overall_stats.p99 {
+            "  <- spike"
+        } else {
+            ""
+        };
+        println!(
+            "  {}  count {:>8}  p50 {:>8}  p95 {:>8}  max {:>8}{}",
+            day, stats.count, stats.p50, stats.p95, stats.max, marker
+        );
+    }
 
-    let reserve_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ReserveToken,
-        "Error: --validate-user-borrow requires --reserve-token to be specified.",
+    println!("\nWorst offenders:");
+    for sample in overall.worst_offenders() {
+        println!(
+            "  {:>8}s  block {}  _id {}",
+            sample.latency, sample.block_number, sample.id
+        );
+    }
+
+    if unresolved > 0 {
+        println!("\nSkipped (block timestamp unavailable): {}", unresolved);
+    }
+}
+
+fn print_latency_stats(label: &str, stats: &LatencyStats) {
+    println!(
+        "  {:<30} count {:>8}  p50 {:>8}  p95 {:>8}  p99 {:>8}  max {:>8}",
+        label, stats.count, stats.p50, stats.p95, stats.p99, stats.max
     );
+}
 
-    match validate_user_borrow_amount(&user_address, &reserve_address).await {
-        Ok(result) => {
-            println!("User Borrow Validation Results:");
-            println!("  Database Amount: {}", result.database_amount);
-            println!("  On-Chain Amount: {}", result.on_chain_amount);
-            println!("  Difference: {}", result.difference);
-            println!("  Percentage: {:.4}%", result.percentage);
+pub async fn handle_verify_events(flags: Vec<Flag>) {
+    let block_range = resolve_block_range_or_exit(&flags).await;
 
-            let report = compare_and_report_diff(
-                result.database_amount,
-                result.on_chain_amount,
-                &format!(
-                    "user {} borrow for reserve {}",
-                    user_address, reserve_address
-                ),
-            );
-            println!("  Status: {}", report);
-        }
+    let chain_id = match get_chain_id().await {
+        Ok(chain_id) => chain_id,
         Err(e) => {
-            eprintln!("Error validating user borrow: {}", e);
+            eprintln!("Error fetching chain id: {}", e);
             std::process::exit(1);
         }
-    }
-}
+    };
 
-pub async fn handle_validate_user_scaled_borrow(flags: Vec<Flag>) {
-    let user_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ValidateUserBorrow,
-        "Error: --validate-user-borrow <USER_ADDRESS> --scaled requires a user address to be specified.",
-    );
+    let events: BoxStream<'static, Result<MoneyMarketEventDocument, String>> =
+        match extract_optional_value_from_flags(&flags, FlagType::VerifyEvents) {
+            None => {
+                println!("Verifying all money market events ({})...", block_range);
+                match stream_money_market_events(&block_range).await {
+                    Ok(cursor) => cursor
+                        .map(|doc_result| doc_result.map_err(|e| e.to_string()))
+                        .boxed(),
+                    Err(e) => {
+                        eprintln!("Error fetching money market events: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            Some(count_str) => {
+                let count: usize = match count_str.parse() {
+                    Ok(n) if n > 0 => n,
+                    _ => {
+                        eprintln!(
+                            "Error: --verify-events expects a positive integer when provided an argument."
+                        );
+                        std::process::exit(1);
+                    }
+                };
+                let sampled = match sample_money_market_events(count, &block_range).await {
+                    Ok(docs) => docs,
+                    Err(e) => {
+                        eprintln!("Error sampling money market events: {}", e);
+                        std::process::exit(1);
+                    }
+                };
+                println!(
+                    "Verifying {} sampled money market events ({})...",
+                    sampled.len(),
+                    block_range
+                );
+                stream::iter(sampled.into_iter().map(Ok)).boxed()
+            }
+        };
 
-    let reserve_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ReserveToken,
-        "Error: --validate-user-borrow <USER_ADDRESS> --scaled requires --reserve-token to be specified.",
-    );
+    let mut batches = events.chunks(BLOCK_PREFETCH_BATCH_SIZE);
+    let mut verified = 0;
+    let mut missing_receipts = 0;
+    let mut missing_logs = 0;
+    let mut mismatched = 0;
+    let mut errors = 0;
 
-    match validate_user_scaled_borrow_amount(&user_address, &reserve_address).await {
-        Ok(result) => {
-            println!("User Scaled Borrow Validation Results:");
-            println!("  Database Amount: {}", result.database_amount);
-            println!("  On-Chain Amount: {}", result.on_chain_amount);
-            println!("  Difference: {}", result.difference);
-            println!("  Percentage: {:.4}%", result.percentage);
+    while let Some(batch) = batches.next().await {
+        let docs: Vec<MoneyMarketEventDocument> = batch
+            .into_iter()
+            .filter_map(|doc_result| match doc_result {
+                Ok(doc) => Some(doc),
+                Err(e) => {
+                    eprintln!("Error reading event: {}", e);
+                    errors += 1;
+                    None
+                }
+            })
+            .collect();
 
-            let report = compare_and_report_diff(
-                result.database_amount,
-                result.on_chain_amount,
-                &format!(
-                    "user {} scaled borrow for reserve {}",
-                    user_address, reserve_address
-                ),
-            );
-            println!("  Status: {}", report);
-        }
-        Err(e) => {
-            eprintln!("Error validating user scaled borrow: {}", e);
-            std::process::exit(1);
+        let tx_hashes: Vec<String> = docs.iter().map(|doc| doc.tx_hash().to_string()).collect();
+        let receipts = match get_receipt_logs(&tx_hashes).await {
+            Ok(receipts) => receipts,
+            Err(e) => {
+                eprintln!("Error connecting to the RPC: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+        for doc in docs {
+            let logs = match receipts.get(doc.tx_hash()) {
+                Some(Ok(logs)) => logs.as_deref(),
+                Some(Err(e)) => {
+                    eprintln!(
+                        "⚠️ {} {}: error fetching receipt for tx {}: {}",
+                        doc.id(),
+                        doc.event_type(),
+                        doc.tx_hash(),
+                        e
+                    );
+                    errors += 1;
+                    continue;
+                }
+                None => None,
+            };
+            let verification = verify_event_log(&doc, logs, chain_id);
+            match verification.status {
+                EventVerificationStatus::Verified => {
+                    verified += 1;
+                    continue;
+                }
+                EventVerificationStatus::MissingReceipt => missing_receipts += 1,
+                EventVerificationStatus::MissingLog => missing_logs += 1,
+                EventVerificationStatus::Mismatch(_) => mismatched += 1,
+            }
+            println!("❌ {}", verification);
         }
     }
-}
 
-pub async fn handle_validate_token_supply(flags: Vec<Flag>) {
-    let reserve_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ReserveToken,
-        "Error: --validate-token-supply requires --reserve-token to be specified.",
+    println!(
+        "\n📊 Summary: {} verified, {} with mismatched fields, {} missing logs, {} missing receipts, {} errors",
+        verified, mismatched, missing_logs, missing_receipts, errors
     );
+}
 
-    match validate_token_supply_amount(&reserve_address).await {
-        Ok(result) => {
-            println!("Token Supply Validation Results:");
-            println!("  Database Amount: {}", result.database_amount);
-            println!("  On-Chain Amount: {}", result.on_chain_amount);
-            println!("  Difference: {}", result.difference);
-            println!("  Percentage: {:.4}%", result.percentage);
+pub async fn handle_validate_event_math(flags: Vec<Flag>) {
+    let block_range = resolve_block_range_or_exit(&flags).await;
 
-            let report = compare_and_report_diff(
-                result.database_amount,
-                result.on_chain_amount,
-                &format!("total aToken supply for reserve {}", reserve_address),
-            );
-            println!("  Status: {}", report);
+    let reserves = match find_all_reserves().await {
+        Ok(reserves) => reserves,
+        Err(e) => {
+            eprintln!("Error fetching reserve tokens: {}", e);
+            std::process::exit(1);
         }
+    };
+    // aToken / debt token address -> reserve address
+    let mut a_token_reserves: HashMap<String, String> = HashMap::new();
+    let mut debt_token_reserves: HashMap<String, String> = HashMap::new();
+    for reserve in &reserves {
+        a_token_reserves.insert(
+            reserve.aTokenAddress.to_lowercase(),
+            reserve.reserveAddress.clone(),
+        );
+        debt_token_reserves.insert(
+            reserve.variableDebtTokenAddress.to_lowercase(),
+            reserve.reserveAddress.clone(),
+        );
+    }
+
+    let mut cursor = match stream_money_market_events(&block_range).await {
+        Ok(cursor) => cursor,
         Err(e) => {
-            eprintln!("Error validating token supply: {}", e);
+            eprintln!("Error fetching money market events: {}", e);
             std::process::exit(1);
         }
+    };
+    println!("Checking mint and burn events ({})...", block_range);
+
+    let mut consistent = 0;
+    let mut without_reserve_update = 0;
+    let mut inconsistent = 0;
+    let mut check_transaction = |tx_events: &[MoneyMarketEventDocument]| {
+        for event in tx_events {
+            let token = event.token().to_lowercase();
+            let reserve = match event {
+                MoneyMarketEventDocument::DebtTokenMint(_)
+                | MoneyMarketEventDocument::DebtTokenBurn(_) => debt_token_reserves.get(&token),
+                _ => a_token_reserves.get(&token),
+            };
+            let Some(check) = check_event_math(event, reserve.map(|r| r.as_str()), tx_events)
+            else {
+                continue;
+            };
+            match check.status {
+                EventMathStatus::Consistent => consistent += 1,
+                EventMathStatus::NoReserveUpdate => without_reserve_update += 1,
+                EventMathStatus::Inconsistent(_) => {
+                    inconsistent += 1;
+                    println!("❌ {}", check);
+                }
+            }
+        }
+    };
+
+    // events are sorted by block and log index, so the events of a transaction are adjacent
+    let mut tx_events: Vec<MoneyMarketEventDocument> = vec![];
+    while let Some(doc_result) = cursor.next().await {
+        let event = match doc_result {
+            Ok(event) => event,
+            Err(e) => {
+                eprintln!("Error reading money market event: {}", e);
+                continue;
+            }
+        };
+        if let Some(last) = tx_events.last()
+            && !last.tx_hash().eq_ignore_ascii_case(event.tx_hash())
+        {
+            check_transaction(&tx_events);
+            tx_events.clear();
+        }
+        tx_events.push(event);
     }
-}
+    check_transaction(&tx_events);
 
-pub async fn handle_validate_token_scaled_supply(flags: Vec<Flag>) {
-    let reserve_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ReserveToken,
-        "Error: --validate-token-supply --scaled requires --reserve-token to be specified.",
+    println!(
+        "\n📊 Summary: {} consistent, {} consistent without a ReserveDataUpdated in the transaction, {} inconsistent",
+        consistent, without_reserve_update, inconsistent
     );
+}
 
-    match validate_token_scaled_supply_amount(&reserve_address).await {
-        Ok(result) => {
-            println!("Token Scaled Supply Validation Results:");
-            println!("  Database Amount: {}", result.database_amount);
-            println!("  On-Chain Amount: {}", result.on_chain_amount);
-            println!("  Difference: {}", result.difference);
-            println!("  Percentage: {:.4}%", result.percentage);
+pub async fn handle_reorg_check(flags: Vec<Flag>) {
+    let blocks = match extract_optional_value_from_flags(&flags, FlagType::ReorgCheck) {
+        None => DEFAULT_REORG_CHECK_BLOCKS,
+        Some(blocks_str) => match blocks_str.parse::<u64>() {
+            Ok(n) if n > 0 => n,
+            _ => {
+                eprintln!("Error: --reorg-check expects a positive number of blocks.");
+                std::process::exit(1);
+            }
+        },
+    };
 
-            let report = compare_and_report_diff(
-                result.database_amount,
-                result.on_chain_amount,
-                &format!("total aToken scaled supply for reserve {}", reserve_address),
-            );
-            println!("  Status: {}", report);
+    let latest_block = match find_latest_event_block().await {
+        Ok(Some(block)) => block,
+        Ok(None) => {
+            println!("No money market events indexed, nothing to check");
+            return;
         }
         Err(e) => {
-            eprintln!("Error validating scaled token supply: {}", e);
+            eprintln!("Error fetching the latest indexed block: {}", e);
             std::process::exit(1);
         }
-    }
-}
-pub async fn handle_validate_token_borrow(flags: Vec<Flag>) {
-    let reserve_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ReserveToken,
-        "Error: --validate-token-borrow requires --reserve-token to be specified.",
-    );
-
-    match validate_token_borrow_amount(&reserve_address).await {
-        Ok(result) => {
-            println!("Token Borrow Validation Results:");
-            println!("  Database Amount: {}", result.database_amount);
-            println!("  On-Chain Amount: {}", result.on_chain_amount);
-            println!("  Difference: {}", result.difference);
-            println!("  Percentage: {:.4}%", result.percentage);
+    };
+    let block_range = BlockRange {
+        from: Some(latest_block.saturating_sub(blocks - 1)),
+        to: Some(latest_block),
+    };
 
-            let report = compare_and_report_diff(
-                result.database_amount,
-                result.on_chain_amount,
-                &format!("total debt token supply for reserve {}", reserve_address),
-            );
-            println!("  Status: {}", report);
-        }
+    let reserves = match find_all_reserves().await {
+        Ok(reserves) => reserves,
         Err(e) => {
-            eprintln!("Error validating token borrow: {}", e);
+            eprintln!("Error fetching reserve tokens: {}", e);
             std::process::exit(1);
         }
+    };
+    // reserve, aToken and debt token address -> reserve symbol
+    let mut symbols: HashMap<String, String> = HashMap::new();
+    for reserve in &reserves {
+        for token in [
+            &reserve.reserveAddress,
+            &reserve.aTokenAddress,
+            &reserve.variableDebtTokenAddress,
+        ] {
+            symbols.insert(token.to_lowercase(), reserve.symbol.clone());
+        }
     }
-}
 
-pub async fn handle_validate_token_scaled_borrow(flags: Vec<Flag>) {
-    let reserve_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ReserveToken,
-        "Error: --validate-token-borrow --scaled requires --reserve-token to be specified.",
+    let chain_id = match get_chain_id().await {
+        Ok(chain_id) => chain_id,
+        Err(e) => {
+            eprintln!("Error fetching chain id: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let cursor = match stream_money_market_events(&block_range).await {
+        Ok(cursor) => cursor,
+        Err(e) => {
+            eprintln!("Error fetching money market events: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!(
+        "Checking the money market events of the last {} indexed blocks ({})...",
+        blocks, block_range
     );
 
-    match validate_token_scaled_borrow_amount(&reserve_address).await {
-        Ok(result) => {
-            println!("Token Scaled Borrow Validation Results:");
-            println!("  Database Amount: {}", result.database_amount);
-            println!("  On-Chain Amount: {}", result.on_chain_amount);
-            println!("  Difference: {}", result.difference);
-            println!("  Percentage: {:.4}%", result.percentage);
+    let mut batches = cursor.chunks(BLOCK_PREFETCH_BATCH_SIZE);
+    let mut canonical = 0;
+    let mut errors = 0;
+    let mut affected_users: BTreeSet<String> = BTreeSet::new();
+    let mut affected_reserves: BTreeSet<String> = BTreeSet::new();
+    let mut affected_events = 0;
 
-            let report = compare_and_report_diff(
+    while let Some(batch) = batches.next().await {
+        let events: Vec<MoneyMarketEventDocument> = batch
+            .into_iter()
+            .filter_map(|doc_result| match doc_result {
+                Ok(doc) => Some(doc),
+                Err(e) => {
+                    eprintln!("Error reading money market event: {}", e);
+                    errors += 1;
+                    None
+                }
+            })
+            .collect();
+
+        let tx_hashes: Vec<String> = events.iter().map(|e| e.tx_hash().to_string()).collect();
+        let receipts = match get_receipt_logs(&tx_hashes).await {
+            Ok(receipts) => receipts,
+            Err(e) => {
+                eprintln!("Error fetching transaction receipts: {}", e);
+                std::process::exit(1);
+            }
+        };
+        // the headers now at the stored heights, not the cached ones: a block replaced at
+        // the same height keeps its number
+        let block_numbers: Vec<u64> = events.iter().map(|e| e.block_number()).collect();
+        let block_hashes = match get_block_hashes(&block_numbers).await {
+            Ok(block_hashes) => block_hashes,
+            Err(e) => {
+                eprintln!("Error fetching block headers: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+        for event in &events {
+            let logs = match receipts.get(event.tx_hash()) {
+                Some(Ok(logs)) => logs.as_deref(),
+                Some(Err(e)) => {
+                    errors += 1;
+                    eprintln!("Error fetching receipt of {}: {}", event.tx_hash(), e);
+                    continue;
+                }
+                None => None,
+            };
+            let canonical_hash = match block_hashes.get(&event.block_number()) {
+                Some(Ok(hash)) => *hash,
+                Some(Err(e)) => {
+                    errors += 1;
+                    eprintln!("Error fetching block {}: {}", event.block_number(), e);
+                    continue;
+                }
+                None => {
+                    errors += 1;
+                    continue;
+                }
+            };
+            let inclusion = logs
+                .unwrap_or_default()
+                .iter()
+                .find_map(|log| log.block_number.zip(log.block_hash));
+            let log_status = verify_event_log(event, logs, chain_id).status;
+            let status = TxInclusionStatus::classify(
+                event.block_number(),
+                canonical_hash,
+                inclusion,
+                log_status,
+            );
+            if status == TxInclusionStatus::Canonical {
+                canonical += 1;
+                continue;
+            }
+
+            affected_events += 1;
+            println!(
+                "❌ {} {} (tx {}, stored block {}, log {}): {}",
+                event.id().to_hex(),
+                event.event_type(),
+                event.tx_hash(),
+                event.block_number(),
+                event.log_index(),
+                status
+            );
+            for user in event.affected_users() {
+                affected_users.insert(user.to_lowercase());
+            }
+            let token = event.token().to_lowercase();
+            affected_reserves.insert(symbols.get(&token).cloned().unwrap_or(token));
+        }
+    }
+
+    if !affected_users.is_empty() {
+        println!("\n👤 Affected users:");
+        for user in &affected_users {
+            println!("  {}", user);
+        }
+    }
+    if !affected_reserves.is_empty() {
+        println!("\n🏦 Affected reserves:");
+        for reserve in &affected_reserves {
+            println!("  {}", reserve);
+        }
+    }
+
+    println!(
+        "\n📊 Summary: {} canonical, {} affected by a reorg, {} errors ({} users, {} reserves)",
+        canonical,
+        affected_events,
+        errors,
+        affected_users.len(),
+        affected_reserves.len()
+    );
+}
+
+pub async fn handle_find_missing_events(flags: Vec<Flag>) {
+    let mut block_range = resolve_block_range_or_exit(&flags).await;
+    let from_block = block_range.from.unwrap_or_default();
+    let to_block = match block_range.to {
+        Some(to_block) => to_block,
+        None => match get_last_block().await {
+            Ok(block) => block,
+            Err(e) => {
+                eprintln!("Error fetching last block: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    block_range.to = Some(to_block);
+
+    let reserves = match find_all_reserves().await {
+        Ok(reserves) => reserves,
+        Err(e) => {
+            eprintln!("Error fetching reserve tokens: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut emitters: HashMap<Address, EventEmitter> = HashMap::from([(
+        POOL_ADDRESS.parse().expect("valid pool address"),
+        EventEmitter::Pool,
+    )]);
+    for reserve in &reserves {
+        for (token, emitter) in [
+            (&reserve.aTokenAddress, EventEmitter::AToken),
+            (&reserve.variableDebtTokenAddress, EventEmitter::DebtToken),
+        ] {
+            match token.parse::<Address>() {
+                Ok(address) => {
+                    emitters.insert(address, emitter);
+                }
+                Err(_) => eprintln!(
+                    "Skipping invalid token address {} ({})",
+                    token, reserve.symbol
+                ),
+            }
+        }
+    }
+    let addresses: Vec<Address> = emitters.keys().copied().collect();
+    // the filter matches any of the topics on any address, logs an emitter doesn't index are
+    // dropped by indexed_event_name
+    let topics: Vec<B256> = [
+        EventEmitter::Pool,
+        EventEmitter::AToken,
+        EventEmitter::DebtToken,
+    ]
+    .into_iter()
+    .flat_map(indexed_event_signatures)
+    .map(|(topic, _)| topic)
+    .collect::<BTreeSet<_>>()
+    .into_iter()
+    .collect();
+
+    println!(
+        "Scanning {} for logs of the pool and {} reserve tokens...",
+        block_range,
+        addresses.len() - 1
+    );
+
+    // compare chain and database one window at a time to keep memory bounded
+    let settings = get_rpc_settings();
+    let window = settings.logs_block_range * settings.max_concurrent_requests as u64;
+    let mut scanned_logs = 0;
+    let mut missing = 0;
+    let mut start = from_block;
+    while start <= to_block {
+        let end = min(start.saturating_add(window - 1), to_block);
+
+        let logs = match get_logs_in_range(&addresses, &topics, start, end).await {
+            Ok(logs) => logs,
+            Err(e) => {
+                eprintln!("Error fetching logs for blocks {} - {}: {}", start, end, e);
+                std::process::exit(1);
+            }
+        };
+        let stored = match find_event_keys_in_range(&BlockRange {
+            from: Some(start),
+            to: Some(end),
+        })
+        .await
+        {
+            Ok(keys) => keys,
+            Err(e) => {
+                eprintln!(
+                    "Error fetching stored events for blocks {} - {}: {}",
+                    start, end, e
+                );
+                std::process::exit(1);
+            }
+        };
+
+        for log in logs {
+            let Some(event_name) = indexed_event_name(&log, &emitters) else {
+                continue;
+            };
+            scanned_logs += 1;
+            let tx_hash = log
+                .transaction_hash
+                .map(|hash| hash.to_string())
+                .unwrap_or_default();
+            let log_index = log.log_index.unwrap_or_default() as i64;
+            if stored.contains(&(tx_hash.to_lowercase(), log_index)) {
+                continue;
+            }
+            missing += 1;
+            println!(
+                "❌ Missing {} from {:#x} at block {} (tx {}, log {})",
+                event_name,
+                log.address(),
+                log.block_number.unwrap_or_default(),
+                tx_hash,
+                log_index
+            );
+        }
+        println!("Scanned blocks {} - {}...", start, end);
+
+        start = end + 1;
+    }
+
+    println!(
+        "\n📊 Summary: {} logs scanned, {} without a money_market_events document",
+        scanned_logs, missing
+    );
+}
+
+pub async fn handle_balance_of(flags: Vec<Flag>) {
+    let error_message =
+        "Error: --balance-of requires both a token address and a user address to be specified.";
+
+    let token_type_passed = flags
+        .iter()
+        .find_map(|f| match f {
+            Flag::ReserveToken(_) => Some(FlagType::ReserveToken),
+            Flag::AToken(_) => Some(FlagType::AToken),
+            Flag::DebtToken(_) => Some(FlagType::DebtToken),
+            _ => None,
+        })
+        .unwrap_or_else(|| {
+            eprintln!("{}", error_message);
+            std::process::exit(1);
+        });
+    let token_passed =
+        extract_value_from_flags_or_exit(flags.clone(), token_type_passed.clone(), error_message);
+
+    let user_address =
+        extract_value_from_flags_or_exit(flags.clone(), FlagType::BalanceOf, error_message);
+
+    let (reserve, _) = resolve_reserve_or_exit(&token_passed).await;
+    let token_address = match token_type_passed {
+        FlagType::AToken => reserve.aTokenAddress,
+        FlagType::DebtToken => reserve.variableDebtTokenAddress,
+        _ => reserve.reserveAddress,
+    };
+
+    match get_balance_of(&token_address, &user_address).await {
+        Ok(balance) => println!(
+            "Balance of {} for token {} ({}): {}",
+            user_address, token_address, reserve.symbol, balance
+        ),
+        Err(e) => {
+            eprintln!("Error fetching balance: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub async fn handle_user_position(flags: Vec<Flag>) {
+    let error_message = "Error: --user-position requires a user address to be specified.";
+    let user_address =
+        extract_value_from_flags_or_exit(flags.clone(), FlagType::UserPosition, error_message);
+
+    let token_address = flags
+        .iter()
+        .find_map(|f| match f {
+            Flag::ReserveToken(token) | Flag::AToken(token) | Flag::DebtToken(token) => {
+                Some(token.clone())
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| {
+            eprintln!("{}", error_message);
+            std::process::exit(1);
+        });
+    let (reserve_data, _) = resolve_reserve_or_exit(&token_address).await;
+
+    match find_user_scaled_position(&user_address, &reserve_data.reserveAddress).await {
+        Ok(position) => {
+            println!(
+                "User position for {} on reserve {}: {:?}",
+                user_address, token_address, position
+            );
+        }
+        Err(e) => {
+            eprintln!("Error fetching user position: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    match get_user_account_data(&user_address).await {
+        Ok(account_data) => println!("On-chain account data: {}", account_data),
+        Err(e) => eprintln!("Error fetching the on-chain account data: {}", e),
+    }
+}
+
+pub async fn handle_token(flags: Vec<Flag>) {
+    let token_address = flags
+        .iter()
+        .find_map(|f| match f {
+            Flag::ReserveToken(token) | Flag::AToken(token) | Flag::DebtToken(token) => {
+                Some(token.clone())
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| {
+            eprintln!("Error: --reserve-token, --a-token or --debt-token is required.");
+            std::process::exit(1);
+        });
+
+    let (token_data, _) = resolve_reserve_or_exit(&token_address).await;
+    println!("Reserve data for token {}: {:?}", token_address, token_data);
+
+    match get_reserve_data(&token_data.reserveAddress).await {
+        Ok(reserve_data) => println!(
+            "\nOn-chain configuration of {}:\n{}",
+            token_data.symbol,
+            reserve_data.decoded_configuration()
+        ),
+        Err(e) => eprintln!("Error fetching the on-chain reserve configuration: {}", e),
+    }
+}
+
+pub async fn handle_validate_user_supply(flags: Vec<Flag>) {
+    let user_address = extract_value_from_flags_or_exit(
+        flags.clone(),
+        FlagType::ValidateUserSupply,
+        "Error: --validate-user-supply requires a user address to be specified.",
+    );
+
+    let reserve_address = extract_reserve_address_or_exit(
+        &flags,
+        "Error: --validate-user-supply requires --reserve-token to be specified.",
+    )
+    .await;
+
+    match validate_user_supply_amount(&user_address, &reserve_address).await {
+        Ok(result) => {
+            println!("User Supply Validation Results:");
+            println!("  Database Amount: {}", result.database_amount);
+            println!("  On-Chain Amount: {}", result.on_chain_amount);
+            println!("  Difference: {}", result.difference);
+            println!("  Percentage: {:.4}%", result.percentage);
+            print_usd_difference(&reserve_address, result.difference).await;
+
+            let report = compare_and_report_diff(
                 result.database_amount,
                 result.on_chain_amount,
                 &format!(
-                    "total debt token scaled supply for reserve {}",
-                    reserve_address
+                    "user {} supply for reserve {}",
+                    user_address, reserve_address
+                ),
+            );
+            println!("  Status: {}", report);
+        }
+        Err(e) => {
+            eprintln!("Error validating user supply: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+pub async fn handle_validate_user_scaled_supply(flags: Vec<Flag>) {
+    let user_address = extract_value_from_flags_or_exit(
+        flags.clone(),
+        FlagType::ValidateUserSupply,
+        "Error: --validate-user-supply <USER_ADDRESS> --scaled requires a user address to be specified.",
+    );
+
+    let reserve_address = extract_reserve_address_or_exit(
+        &flags,
+        "Error: --validate-user-supply <USER_ADDRESS> ==scaled requires --reserve-token to be specified.",
+    )
+    .await;
+
+    match validate_user_scaled_supply_amount(&user_address, &reserve_address).await {
+        Ok(result) => {
+            println!("User Scaled Supply Validation Results:");
+            println!("  Database Amount: {}", result.database_amount);
+            println!("  On-Chain Amount: {}", result.on_chain_amount);
+            println!("  Difference: {}", result.difference);
+            println!("  Percentage: {:.4}%", result.percentage);
+
+            let report = compare_and_report_diff(
+                result.database_amount,
+                result.on_chain_amount,
+                &format!(
+                    "user {} scaled supply for reserve {}",
+                    user_address, reserve_address
+                ),
+            );
+            println!("  Status: {}", report);
+        }
+        Err(e) => {
+            eprintln!("Error validating user scaled supply: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub async fn handle_validate_user_borrow(flags: Vec<Flag>) {
+    let user_address = extract_value_from_flags_or_exit(
+        flags.clone(),
+        FlagType::ValidateUserBorrow,
+        "Error: --validate-user-borrow requires a user address to be specified.",
+    );
+
+    let reserve_address = extract_reserve_address_or_exit(
+        &flags,
+        "Error: --validate-user-borrow requires --reserve-token to be specified.",
+    )
+    .await;
+
+    match validate_user_borrow_amount(&user_address, &reserve_address).await {
+        Ok(result) => {
+            println!("User Borrow Validation Results:");
+            println!("  Database Amount: {}", result.database_amount);
+            println!("  On-Chain Amount: {}", result.on_chain_amount);
+            println!("  Difference: {}", result.difference);
+            println!("  Percentage: {:.4}%", result.percentage);
+            print_usd_difference(&reserve_address, result.difference).await;
+
+            let report = compare_and_report_diff(
+                result.database_amount,
+                result.on_chain_amount,
+                &format!(
+                    "user {} borrow for reserve {}",
+                    user_address, reserve_address
                 ),
             );
             println!("  Status: {}", report);
         }
         Err(e) => {
-            eprintln!("Error validating token scaled borrow: {}", e);
+            eprintln!("Error validating user borrow: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub async fn handle_validate_user_scaled_borrow(flags: Vec<Flag>) {
+    let user_address = extract_value_from_flags_or_exit(
+        flags.clone(),
+        FlagType::ValidateUserBorrow,
+        "Error: --validate-user-borrow <USER_ADDRESS> --scaled requires a user address to be specified.",
+    );
+
+    let reserve_address = extract_reserve_address_or_exit(
+        &flags,
+        "Error: --validate-user-borrow <USER_ADDRESS> --scaled requires --reserve-token to be specified.",
+    )
+    .await;
+
+    match validate_user_scaled_borrow_amount(&user_address, &reserve_address).await {
+        Ok(result) => {
+            println!("User Scaled Borrow Validation Results:");
+            println!("  Database Amount: {}", result.database_amount);
+            println!("  On-Chain Amount: {}", result.on_chain_amount);
+            println!("  Difference: {}", result.difference);
+            println!("  Percentage: {:.4}%", result.percentage);
+
+            let report = compare_and_report_diff(
+                result.database_amount,
+                result.on_chain_amount,
+                &format!(
+                    "user {} scaled borrow for reserve {}",
+                    user_address, reserve_address
+                ),
+            );
+            println!("  Status: {}", report);
+        }
+        Err(e) => {
+            eprintln!("Error validating user scaled borrow: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub async fn handle_validate_token_supply(flags: Vec<Flag>) {
+    let reserve_address = extract_reserve_address_or_exit(
+        &flags,
+        "Error: --validate-token-supply requires --reserve-token to be specified.",
+    )
+    .await;
+
+    match validate_token_supply_amount(&reserve_address).await {
+        Ok(result) => {
+            println!("Token Supply Validation Results:");
+            println!("  Database Amount: {}", result.database_amount);
+            println!("  On-Chain Amount: {}", result.on_chain_amount);
+            println!("  Difference: {}", result.difference);
+            println!("  Percentage: {:.4}%", result.percentage);
+            print_usd_difference(&reserve_address, result.difference).await;
+
+            let report = compare_and_report_diff(
+                result.database_amount,
+                result.on_chain_amount,
+                &format!("total aToken supply for reserve {}", reserve_address),
+            );
+            println!("  Status: {}", report);
+        }
+        Err(e) => {
+            eprintln!("Error validating token supply: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub async fn handle_validate_token_scaled_supply(flags: Vec<Flag>) {
+    let reserve_address = extract_reserve_address_or_exit(
+        &flags,
+        "Error: --validate-token-supply --scaled requires --reserve-token to be specified.",
+    )
+    .await;
+
+    match validate_token_scaled_supply_amount(&reserve_address).await {
+        Ok(result) => {
+            println!("Token Scaled Supply Validation Results:");
+            println!("  Database Amount: {}", result.database_amount);
+            println!("  On-Chain Amount: {}", result.on_chain_amount);
+            println!("  Difference: {}", result.difference);
+            println!("  Percentage: {:.4}%", result.percentage);
+
+            let report = compare_and_report_diff(
+                result.database_amount,
+                result.on_chain_amount,
+                &format!("total aToken scaled supply for reserve {}", reserve_address),
+            );
+            println!("  Status: {}", report);
+        }
+        Err(e) => {
+            eprintln!("Error validating scaled token supply: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+pub async fn handle_validate_token_borrow(flags: Vec<Flag>) {
+    let reserve_address = extract_reserve_address_or_exit(
+        &flags,
+        "Error: --validate-token-borrow requires --reserve-token to be specified.",
+    )
+    .await;
+
+    match validate_token_borrow_amount(&reserve_address).await {
+        Ok(result) => {
+            println!("Token Borrow Validation Results:");
+            println!("  Database Amount: {}", result.database_amount);
+            println!("  On-Chain Amount: {}", result.on_chain_amount);
+            println!("  Difference: {}", result.difference);
+            println!("  Percentage: {:.4}%", result.percentage);
+            print_usd_difference(&reserve_address, result.difference).await;
+
+            let report = compare_and_report_diff(
+                result.database_amount,
+                result.on_chain_amount,
+                &format!("total debt token supply for reserve {}", reserve_address),
+            );
+            println!("  Status: {}", report);
+        }
+        Err(e) => {
+            eprintln!("Error validating token borrow: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub async fn handle_validate_token_scaled_borrow(flags: Vec<Flag>) {
+    let reserve_address = extract_reserve_address_or_exit(
+        &flags,
+        "Error: --validate-token-borrow --scaled requires --reserve-token to be specified.",
+    )
+    .await;
+
+    match validate_token_scaled_borrow_amount(&reserve_address).await {
+        Ok(result) => {
+            println!("Token Scaled Borrow Validation Results:");
+            println!("  Database Amount: {}", result.database_amount);
+            println!("  On-Chain Amount: {}", result.on_chain_amount);
+            println!("  Difference: {}", result.difference);
+            println!("  Percentage: {:.4}%", result.percentage);
+
+            let report = compare_and_report_diff(
+                result.database_amount,
+                result.on_chain_amount,
+                &format!(
+                    "total debt token scaled supply for reserve {}",
+                    reserve_address
+                ),
+            );
+            println!("  Status: {}", report);
+        }
+        Err(e) => {
+            eprintln!("Error validating token scaled borrow: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub async fn handle_validate_token_all() {
+    handle_validate_token_all_generic(false).await;
+}
+
+pub async fn handle_validate_token_all_scaled() {
+    handle_validate_token_all_generic(true).await;
+}
+
+pub async fn handle_validate_token_all_generic(scaled: bool) {
+    println!("Validating all reserves in parallel...");
+
+    // Get all reserves first
+    let reserves = match find_all_reserves().await {
+        Ok(reserves) => reserves,
+        Err(e) => {
+            eprintln!("Error fetching reserve tokens: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // scaled amounts aren't token amounts, only real balances are priced
+    let prices = if scaled {
+        PriceBook::default()
+    } else {
+        let addresses: Vec<String> = reserves
+            .iter()
+            .map(|reserve| reserve.reserveAddress.clone())
+            .collect();
+        price_book_or_warn(&addresses).await
+    };
+    let tolerance = usd_tolerance();
+
+    // Create tasks for parallel validation
+    let tasks: Vec<_> = reserves
+        .into_iter()
+        .map(|reserve| {
+            let reserve_address = reserve.reserveAddress.clone();
+            task::spawn(async move {
+                if scaled {
+                    match validate_scaled_reserve(&reserve_address).await {
+                        Ok(result) => Ok(result),
+                        Err(e) => Err(format!("Failed to validate {}: {}", reserve_address, e)),
+                    }
+                } else {
+                    match validate_reserve(&reserve_address).await {
+                        Ok(result) => Ok(result),
+                        Err(e) => Err(format!("Failed to validate {}: {}", reserve_address, e)),
+                    }
+                }
+            })
+        })
+        .collect();
+
+    // Wait for all tasks to complete
+    let results = join_all(tasks).await;
+
+    let mut success_count = 0;
+    let mut error_count = 0;
+    let mut usd_mismatch = (0.0, 0.0);
+    let mut above_tolerance = 0;
+
+    for result in results {
+        match result {
+            Ok(Ok(validation_result)) => {
+                success_count += 1;
+                if let Some(error) = &validation_result.error {
+                    error_count += 1;
+                    println!(
+                        "❌ Reserve {}: ERROR - {}",
+                        validation_result.reserve_address, error
+                    );
+                } else {
+                    println!(
+                        "✅ Reserve {} validated successfully",
+                        validation_result.reserve_address
+                    );
+                    println!(
+                        "  Supply - DB: {}\n  On-Chain:    {}\n  Diff: {}, %: {:.6}%",
+                        validation_result.supply.database_amount,
+                        validation_result.supply.on_chain_amount,
+                        validation_result.supply.difference,
+                        validation_result.supply.percentage
+                    );
+                    println!(
+                        "  Borrow - DB: {}\n  On-Chain:    {}\n  Diff: {}, %: {:.6}%",
+                        validation_result.borrow.database_amount,
+                        validation_result.borrow.on_chain_amount,
+                        validation_result.borrow.difference,
+                        validation_result.borrow.percentage
+                    );
+                    let reserve = &validation_result.reserve_address;
+                    if let (Some(supply_usd), Some(borrow_usd)) = (
+                        prices.usd_value(reserve, validation_result.supply.difference),
+                        prices.usd_value(reserve, validation_result.borrow.difference),
+                    ) {
+                        println!(
+                            "  USD Diff - supply: ${:.2}, borrow: ${:.2}",
+                            supply_usd, borrow_usd
+                        );
+                        usd_mismatch.0 += supply_usd;
+                        usd_mismatch.1 += borrow_usd;
+                        if supply_usd > tolerance || borrow_usd > tolerance {
+                            above_tolerance += 1;
+                        }
+                    }
+                }
+            }
+            Ok(Err(e)) => {
+                error_count += 1;
+                println!("❌ Validation failed: {}", e);
+            }
+            Err(e) => {
+                error_count += 1;
+                println!("❌ Task failed: {}", e);
+            }
+        }
+    }
+
+    println!(
+        "\n📊 Summary: {} successful, {} errors",
+        success_count, error_count
+    );
+    if !prices.is_empty() {
+        println!(
+            "💵 USD mismatch: supply ${:.2}, borrow ${:.2}, {} reserves above USD_TOLERANCE (${:.2})",
+            usd_mismatch.0, usd_mismatch.1, above_tolerance, tolerance
+        );
+    }
+}
+
+pub async fn handle_validate_users_all() {
+    handle_validate_users_all_generic(false).await;
+}
+
+pub async fn handle_validate_users_all_scaled() {
+    handle_validate_users_all_generic(true).await;
+}
+
+pub async fn handle_validate_users_all_generic(scaled: bool) {
+    println!("Validating all users in parallel...");
+    let prices = Arc::new(if scaled {
+        PriceBook::default()
+    } else {
+        all_reserve_prices().await
+    });
+
+    // Stream user addresses and spawn one validation task per user
+    let mut users = match stream_user_addresses().await {
+        Ok(users) => users,
+        Err(e) => {
+            eprintln!("Error fetching users: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut tasks = Vec::new();
+    while let Some(user_result) = users.next().await {
+        let user_address = match user_result {
+            Ok(user) => user.userAddress,
+            Err(e) => {
+                eprintln!("Error reading user: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let prices = Arc::clone(&prices);
+        tasks.push(task::spawn(async move {
+            if scaled {
+                handle_user_validation_scaled(&user_address, false).await;
+                0.0
+            } else {
+                handle_user_validation(&user_address, false, &prices).await
+            }
+        }));
+    }
+
+    // Wait for all tasks to complete
+    let results = join_all(tasks).await;
+
+    let mut success_count = 0;
+    let mut error_count = 0;
+    let mut usd_mismatch = 0.0;
+
+    for result in results {
+        match result {
+            Ok(user_usd_mismatch) => {
+                success_count += 1;
+                usd_mismatch += user_usd_mismatch;
+            }
+            Err(e) => {
+                error_count += 1;
+                println!("❌ Task failed: {}", e);
+            }
+        }
+    }
+
+    println!(
+        "\n📊 Summary: {} successful users, {} errors",
+        success_count, error_count
+    );
+    if !prices.is_empty() {
+        println!("💵 USD mismatch over all positions: ${:.2}", usd_mismatch);
+    }
+}
+
+pub async fn handle_validate_user_all(flags: Vec<Flag>) {
+    let user_address = extract_value_from_flags_or_exit(
+        flags.clone(),
+        FlagType::ValidateUserAll,
+        "Error: --validate-user-all requires a user address to be specified.",
+    );
+
+    println!("Validating all positions for user {}...", user_address);
+    let prices = all_reserve_prices().await;
+    let usd_mismatch = handle_user_validation(&user_address, true, &prices).await;
+    if !prices.is_empty() {
+        println!("💵 USD mismatch: ${:.2}", usd_mismatch);
+    }
+}
+pub async fn handle_validate_user_all_scaled(flags: Vec<Flag>) {
+    let user_address = extract_value_from_flags_or_exit(
+        flags.clone(),
+        FlagType::ValidateUserAll,
+        "Error: --validate-user-all requires a user address to be specified.",
+    );
+
+    println!("Validating all positions for user {}...", user_address);
+    handle_user_validation_scaled(&user_address, true).await;
+}
+
+// Returns the USD value of the user's supply and borrow differences
+async fn handle_user_validation(
+    user_address: &str,
+    exit_on_error: bool,
+    prices: &PriceBook,
+) -> f64 {
+    handle_user_validation_generic(user_address, exit_on_error, false, prices).await
+}
+
+async fn handle_user_validation_scaled(user_address: &str, exit_on_error: bool) {
+    handle_user_validation_generic(user_address, exit_on_error, true, &PriceBook::default()).await;
+}
+
+async fn handle_user_validation_generic(
+    user_address: &str,
+    exit_on_error: bool,
+    scaled: bool,
+    prices: &PriceBook,
+) -> f64 {
+    let result = if scaled {
+        match validate_user_all_positions_scaled(user_address).await {
+            Ok(result_inner) => result_inner,
+            Err(e) => {
+                eprintln!("Error validating user {}: {}", user_address, e);
+                if exit_on_error {
+                    std::process::exit(1);
+                }
+                return 0.0;
+            }
+        }
+    } else {
+        match validate_user_all_positions(user_address).await {
+            Ok(result_inner) => result_inner,
+            Err(e) => {
+                eprintln!("Error validating user {}: {}", user_address, e);
+                if exit_on_error {
+                    std::process::exit(1);
+                }
+                return 0.0;
+            }
+        }
+    };
+    let mut usd_mismatch = 0.0;
+    println!(
+        "✅ User {}: {} positions validated",
+        result.user_address,
+        result.positions.len()
+    );
+    for position in &result.positions {
+        if let Some(error) = &position.error {
+            println!(
+                "  ❌ Reserve {}: ERROR - {}",
+                position.reserve_address, error
+            );
+        } else {
+            println!("  📊 Reserve {}:", position.reserve_address);
+            println!(
+                "  Supply - DB: {}\n  On-Chain:    {}\n  Diff: {}, %: {:.6}%",
+                position.supply.database_amount,
+                position.supply.on_chain_amount,
+                position.supply.difference,
+                position.supply.percentage
+            );
+            println!(
+                "  Supply - DB: {}\n  On-Chain:    {}\n  Diff: {}, %: {:.6}%",
+                position.borrow.database_amount,
+                position.borrow.on_chain_amount,
+                position.borrow.difference,
+                position.borrow.percentage
+            );
+            if let (Some(supply_usd), Some(borrow_usd)) = (
+                prices.usd_value(&position.reserve_address, position.supply.difference),
+                prices.usd_value(&position.reserve_address, position.borrow.difference),
+            ) {
+                println!(
+                    "  USD Diff - supply: ${:.2}, borrow: ${:.2}",
+                    supply_usd, borrow_usd
+                );
+                usd_mismatch += supply_usd + borrow_usd;
+            }
+        }
+    }
+    usd_mismatch
+}
+
+pub async fn handle_validate_all() {
+    println!("Validating everything...");
+
+    // Validate all reserves
+    println!("\n🔍 Validating all reserves...");
+    handle_validate_token_all().await;
+    // Validate all users
+    println!("\n🔍 Validating all users...");
+    handle_validate_users_all().await;
+
+    println!("\n🎉 Complete validation finished!");
+}
+
+pub async fn handle_validate_all_scaled() {
+    println!("Validating everything...");
+
+    // Validate all reserves
+    println!("\n🔍 Validating all reserves...");
+    handle_validate_token_all_scaled().await;
+    // Validate all users
+    println!("\n🔍 Validating all users...");
+    handle_validate_users_all_scaled().await;
+
+    println!("\n🎉 Complete validation finished!");
+}
+
+// New handlers for the additional CLI features
+
+pub async fn handle_get_all_users() {
+    let mut users = match stream_user_addresses().await {
+        Ok(users) => users,
+        Err(e) => {
+            eprintln!("Error fetching users: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut user_count = 0;
+    while let Some(user_result) = users.next().await {
+        match user_result {
+            Ok(user) => {
+                if user_count == 0 {
+                    println!("All user addresses:");
+                }
+                user_count += 1;
+                println!("{}", user.userAddress);
+            }
+            Err(e) => {
+                eprintln!("Error reading user: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if user_count == 0 {
+        println!("No users found.");
+    } else {
+        println!("Total users: {}", user_count);
+    }
+}
+
+pub async fn handle_get_all_reserves() {
+    let reserves = match find_all_reserves().await {
+        Ok(reserves) => reserves,
+        Err(e) => {
+            eprintln!("Error fetching reserves: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if reserves.is_empty() {
+        println!("No reserves found.");
+    } else {
+        println!("All reserve tokens:");
+        for reserve in &reserves {
+            println!(
+                "Address: {}, Symbol: {}",
+                reserve.reserveAddress, reserve.symbol
+            );
+        }
+        println!("Total reserves: {}", reserves.len());
+    }
+}
+
+pub async fn handle_get_all_a_tokens() {
+    let reserves = match find_all_reserves().await {
+        Ok(reserves) => reserves,
+        Err(e) => {
+            eprintln!("Error fetching reserves: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if reserves.is_empty() {
+        println!("No aTokens found.");
+    } else {
+        println!("All aToken addresses:");
+        for reserve in &reserves {
+            println!(
+                "Address: {}, Symbol: {}",
+                reserve.aTokenAddress, reserve.symbol
+            );
+        }
+        println!("Total aTokens: {}", reserves.len());
+    }
+}
+
+pub async fn handle_get_all_debt_tokens() {
+    let reserves = match find_all_reserves().await {
+        Ok(reserves) => reserves,
+        Err(e) => {
+            eprintln!("Error fetching reserves: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if reserves.is_empty() {
+        println!("No debt tokens found.");
+    } else {
+        println!("All debt token addresses:");
+        for reserve in &reserves {
+            println!(
+                "Address: {}, Symbol: {}",
+                reserve.variableDebtTokenAddress, reserve.symbol
+            );
+        }
+        println!("Total debt tokens: {}", reserves.len());
+    }
+}
+
+pub async fn handle_get_token_events(flags: Vec<Flag>) {
+    let token_address = extract_value_from_flags_or_exit(
+        flags.clone(),
+        FlagType::GetTokenEvents,
+        "Error: --get-token-events requires a token address to be specified.",
+    );
+
+    let (reserve, field) = resolve_reserve_or_exit(&token_address).await;
+    let token_address = match field {
+        ReserveTokenField::AToken => reserve.aTokenAddress,
+        ReserveTokenField::VariableDebtToken => reserve.variableDebtTokenAddress,
+        ReserveTokenField::Reserve => reserve.reserveAddress,
+    };
+
+    let block_range = resolve_block_range_or_exit(&flags).await;
+    let events = match stream_token_events(&token_address, &block_range).await {
+        Ok(events) => events,
+        Err(e) => {
+            eprintln!("Error fetching token events: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let grouped = flags.iter().any(|f| matches!(f, Flag::Grouped));
+    if handle_money_market_event_output(events, grouped).await == 0 {
+        println!(
+            "No events found for token: {} ({})",
+            token_address, block_range
+        );
+    }
+}
+
+pub async fn handle_get_user_events(flags: Vec<Flag>) {
+    let user_address = extract_value_from_flags_or_exit(
+        flags.clone(),
+        FlagType::GetUserEvents,
+        "Error: --get-user-events requires a user address to be specified.",
+    );
+
+    let block_range = resolve_block_range_or_exit(&flags).await;
+    let events = match stream_user_events(&user_address, &block_range).await {
+        Ok(events) => events,
+        Err(e) => {
+            eprintln!("Error fetching user events: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let grouped = flags.iter().any(|f| matches!(f, Flag::Grouped));
+    if handle_money_market_event_output(events, grouped).await == 0 {
+        println!(
+            "No events found for user: {} ({})",
+            user_address, block_range
+        );
+    }
+}
+
+pub async fn handle_user_timeline(flags: Vec<Flag>) {
+    let user_address = extract_value_from_flags_or_exit(
+        flags.clone(),
+        FlagType::UserTimeline,
+        "Error: --user-timeline requires a user address to be specified.",
+    );
+    let format = match extract_optional_value_from_flags(&flags, FlagType::Format) {
+        Some(value) => value.parse::<OutputFormat>().unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }),
+        None => OutputFormat::Text,
+    };
+    let block_range = resolve_block_range_or_exit(&flags).await;
+
+    let mut cursor = match stream_user_events(&user_address, &block_range).await {
+        Ok(cursor) => cursor,
+        Err(e) => {
+            eprintln!("Error fetching user events: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut events: Vec<MoneyMarketEventDocument> = vec![];
+    while let Some(doc_result) = cursor.next().await {
+        match doc_result {
+            Ok(event) => events.push(event),
+            Err(e) => {
+                eprintln!("Error reading event: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    events.sort_by_key(|event| (event.block_number(), event.log_index()));
+
+    let reserves = match find_all_reserves().await {
+        Ok(reserves) => reserves,
+        Err(e) => {
+            eprintln!("Error fetching reserve tokens: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut token_reserves: HashMap<String, String> = HashMap::new();
+    let mut reserves_by_address: HashMap<String, &ReserveTokenDocument> = HashMap::new();
+    for reserve in &reserves {
+        let reserve_address = reserve.reserveAddress.to_lowercase();
+        token_reserves.insert(
+            reserve.aTokenAddress.to_lowercase(),
+            reserve_address.clone(),
+        );
+        token_reserves.insert(
+            reserve.variableDebtTokenAddress.to_lowercase(),
+            reserve_address.clone(),
+        );
+        reserves_by_address.insert(reserve_address, reserve);
+    }
+
+    let (timeline, balances) = build_user_timeline(&user_address, &events, &token_reserves);
+
+    // decimals of the underlying, amounts are printed in raw units when they can't be fetched
+    let decimal_results = join_all(
+        balances
+            .keys()
+            .map(|reserve| async move { (reserve.clone(), get_decimals(reserve).await) }),
+    )
+    .await;
+    let mut decimals: HashMap<String, u8> = HashMap::new();
+    for (reserve, result) in decimal_results {
+        match result {
+            Ok(value) => {
+                decimals.insert(reserve, value);
+            }
+            Err(e) => eprintln!(
+                "Warning: could not fetch decimals of {}, printing raw amounts: {}",
+                reserve, e
+            ),
+        }
+    }
+    let symbol = |reserve: &str| {
+        reserves_by_address
+            .get(reserve)
+            .map(|r| r.symbol.clone())
+            .unwrap_or_else(|| reserve.to_string())
+    };
+    let amount = |reserve: &str, value: Option<U256>| match value {
+        Some(value) => format_units(value, decimals.get(reserve).copied().unwrap_or(0)),
+        None => "-".to_string(),
+    };
+
+    // final balances compared with user_positions and the chain, only meaningful when the
+    // timeline starts at the first event
+    let mut comparisons: Vec<BalanceComparison> = vec![];
+    if block_range.is_unbounded() {
+        let positions = get_user_position(&user_address)
+            .await
+            .map(|position| position.positions)
+            .unwrap_or_default();
+        let mut compared: BTreeMap<String, ReserveBalances> = balances.clone();
+        for position in &positions {
+            compared
+                .entry(position.reserveAddress.to_lowercase())
+                .or_default();
+        }
+        for (reserve, reserve_balances) in &compared {
+            let position = positions
+                .iter()
+                .find(|p| p.reserveAddress.eq_ignore_ascii_case(reserve));
+            let Some(reserve_doc) = reserves_by_address.get(reserve) else {
+                continue;
+            };
+            for (kind, timeline_scaled, token) in [
+                (
+                    "supply",
+                    reserve_balances.scaled_supply,
+                    &reserve_doc.aTokenAddress,
+                ),
+                (
+                    "debt",
+                    reserve_balances.scaled_debt,
+                    &reserve_doc.variableDebtTokenAddress,
+                ),
+            ] {
+                let stored = position.and_then(|p| {
+                    let value = if kind == "supply" {
+                        &p.aTokenBalance
+                    } else {
+                        &p.variableDebtTokenBalance
+                    };
+                    U256::from_dec_str(&value.to_string()).ok()
+                });
+                let on_chain = get_scaled_balance_of(token, &user_address)
+                    .await
+                    .map(U256::from)
+                    .map_err(|e| e.to_string());
+                comparisons.push(BalanceComparison {
+                    reserve: reserve.clone(),
+                    balance: kind,
+                    timeline_scaled,
+                    stored,
+                    on_chain,
+                });
+            }
+        }
+    }
+
+    if format == OutputFormat::Json {
+        let entries: Vec<Bson> = timeline
+            .iter()
+            .map(|entry| {
+                let optional = |value: Option<U256>| match value {
+                    Some(value) => Bson::String(value.to_string()),
+                    None => Bson::Null,
+                };
+                Bson::Document(doc! {
+                    "blockNumber": entry.block_number as i64,
+                    "logIndex": entry.log_index,
+                    "txHash": &entry.tx_hash,
+                    "action": &entry.action,
+                    "reserve": &entry.reserve,
+                    "symbol": symbol(&entry.reserve),
+                    "amount": optional(entry.amount),
+                    "scaledSupply": entry.balances.scaled_supply.to_string(),
+                    "scaledDebt": entry.balances.scaled_debt.to_string(),
+                    "supply": optional(entry.supply),
+                    "debt": optional(entry.debt),
+                })
+            })
+            .collect();
+        let comparison: Vec<Bson> = comparisons
+            .iter()
+            .map(|comparison| {
+                Bson::Document(doc! {
+                    "reserve": &comparison.reserve,
+                    "symbol": symbol(&comparison.reserve),
+                    "balance": comparison.balance,
+                    "timelineScaled": comparison.timeline_scaled.to_string(),
+                    "userPositionsScaled": comparison
+                        .stored
+                        .map(|v| Bson::String(v.to_string()))
+                        .unwrap_or(Bson::Null),
+                    "onChainScaled": match &comparison.on_chain {
+                        Ok(value) => Bson::String(value.to_string()),
+                        Err(e) => Bson::String(format!("error: {}", e)),
+                    },
+                })
+            })
+            .collect();
+        let output = doc! {
+            "user": &user_address,
+            "range": block_range.to_string(),
+            "events": entries,
+            "comparison": comparison,
+        };
+        println!("{}", Bson::Document(output).into_relaxed_extjson());
+        return;
+    }
+
+    println!(
+        "Timeline of {} ({}), {} events:",
+        user_address,
+        block_range,
+        timeline.len()
+    );
+    for entry in &timeline {
+        print_timeline_entry(entry, &symbol(&entry.reserve), |value| {
+            amount(&entry.reserve, value)
+        });
+    }
+
+    if !block_range.is_unbounded() {
+        println!(
+            "\nBalances above only count the events in the range, they are not compared with user_positions or the chain."
+        );
+        return;
+    }
+    println!("\n📋 Final scaled balances (timeline / user_positions / on-chain):");
+    for comparison in &comparisons {
+        let reserve = comparison.reserve.as_str();
+        println!(
+            "  {} {} {}: {} / {} / {}",
+            if comparison.matches() { "✅" } else { "❌" },
+            symbol(reserve),
+            comparison.balance,
+            amount(reserve, Some(comparison.timeline_scaled)),
+            amount(reserve, comparison.stored),
+            match &comparison.on_chain {
+                Ok(value) => amount(reserve, Some(*value)),
+                Err(e) => format!("error: {}", e),
+            }
+        );
+    }
+}
+
+pub async fn handle_bisect_user(flags: Vec<Flag>) {
+    let user_address = extract_value_from_flags_or_exit(
+        flags.clone(),
+        FlagType::BisectUser,
+        "Error: --bisect-user requires a user address to be specified.",
+    );
+    let input = extract_value_from_flags_or_exit(
+        flags,
+        FlagType::ReserveToken,
+        "Error: --bisect-user requires --reserve to be specified.",
+    );
+    let (reserve, _) = resolve_reserve_or_exit(&input).await;
+    let reserve_address = reserve.reserveAddress.to_lowercase();
+    let a_token = reserve.aTokenAddress.to_lowercase();
+    let debt_token = reserve.variableDebtTokenAddress.to_lowercase();
+
+    let mut cursor = match stream_user_events(&user_address, &BlockRange::default()).await {
+        Ok(cursor) => cursor,
+        Err(e) => {
+            eprintln!("Error fetching user events: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut events: Vec<MoneyMarketEventDocument> = vec![];
+    while let Some(doc_result) = cursor.next().await {
+        match doc_result {
+            Ok(event) => {
+                let token = event.token().to_lowercase();
+                if token == reserve_address || token == a_token || token == debt_token {
+                    events.push(event);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error reading event: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    events.sort_by_key(|event| (event.block_number(), event.log_index()));
+
+    let token_reserves = HashMap::from([
+        (a_token.clone(), reserve_address.clone()),
+        (debt_token.clone(), reserve_address.clone()),
+    ]);
+    let (timeline, _) = build_user_timeline(&user_address, &events, &token_reserves);
+    let checkpoints = timeline_checkpoints(&timeline, &reserve_address);
+    let Some(last) = checkpoints.last() else {
+        println!(
+            "No events found for user {} in reserve {}",
+            user_address, reserve.symbol
+        );
+        return;
+    };
+    println!(
+        "Replaying {} events of {} in {} over {} blocks ({} - {})",
+        timeline.len(),
+        user_address,
+        reserve.symbol,
+        checkpoints.len(),
+        checkpoints[0].block_number,
+        last.block_number
+    );
+
+    let chain_balances = |block_number: u64| {
+        scaled_balances_at_block(&a_token, &debt_token, &user_address, block_number)
+    };
+    let diverges = |replayed: &ReserveBalances, chain: &ReserveBalances| {
+        replayed.scaled_supply != chain.scaled_supply || replayed.scaled_debt != chain.scaled_debt
+    };
+
+    let last_chain = chain_balances(last.block_number).await;
+    if !diverges(&last.balances, &last_chain) {
+        let latest_block = match get_last_block().await {
+            Ok(block) => block,
+            Err(e) => {
+                eprintln!("Error fetching latest block: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let latest_chain = chain_balances(latest_block).await;
+        if diverges(&last.balances, &latest_chain) {
+            println!(
+                "❌ Replayed balances match the chain at block {}, the user's last event, but not at the latest block {}",
+                last.block_number, latest_block
+            );
+            print_balance_pair(&last.balances, &latest_chain);
+            println!(
+                "Events of the user between blocks {} and {} are missing from the database.",
+                last.block_number + 1,
+                latest_block
+            );
+        } else {
+            println!(
+                "✅ Replayed balances match the chain at block {} and at the latest block {}",
+                last.block_number, latest_block
+            );
+        }
+        return;
+    }
+
+    // first checkpoint whose replayed balances differ from the chain, assuming a divergence
+    // persists once it appeared
+    let (mut low, mut high) = (0, checkpoints.len() - 1);
+    let mut first_chain = last_chain;
+    while low < high {
+        let middle = (low + high) / 2;
+        let chain = chain_balances(checkpoints[middle].block_number).await;
+        if diverges(&checkpoints[middle].balances, &chain) {
+            high = middle;
+            first_chain = chain;
+        } else {
+            low = middle + 1;
+        }
+    }
+    let culprit = checkpoints[low];
+
+    println!(
+        "\n❌ First divergence at block {} (checkpoint {} of {})",
+        culprit.block_number,
+        low + 1,
+        checkpoints.len()
+    );
+    print_balance_pair(&culprit.balances, &first_chain);
+    match low.checked_sub(1).map(|i| checkpoints[i].block_number) {
+        Some(previous) => println!(
+            "Balances still matched at block {}. If the transactions below look right, an event of the user between blocks {} and {} is missing from the database.",
+            previous,
+            previous + 1,
+            culprit.block_number
+        ),
+        None => println!("Balances already differ after the user's first event in this reserve."),
+    }
+
+    println!("\nReplayed events in block {}:", culprit.block_number);
+    let mut tx_hashes: Vec<String> = vec![];
+    for entry in timeline
+        .iter()
+        .filter(|entry| entry.block_number == culprit.block_number)
+    {
+        print_timeline_entry(entry, &reserve.symbol, |value| {
+            value.map_or_else(|| "-".to_string(), |v| v.to_string())
+        });
+        if !tx_hashes.contains(&entry.tx_hash) {
+            tx_hashes.push(entry.tx_hash.clone());
+        }
+    }
+
+    let tx_events = match find_transaction_events(&tx_hashes).await {
+        Ok(events) => events,
+        Err(e) => {
+            eprintln!("Error fetching transaction events: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let reserve_tokens = reserve_token_addresses().await;
+    for tx_hash in &tx_hashes {
+        let events: Vec<MoneyMarketEventDocument> = tx_events
+            .iter()
+            .filter(|event| event.tx_hash().eq_ignore_ascii_case(tx_hash))
+            .cloned()
+            .collect();
+        print_transaction_events(&events, &reserve_tokens);
+    }
+}
+
+// On-chain scaled supply and debt of a user at the end of a block, exits when the RPC
+// can't serve the historical state
+async fn scaled_balances_at_block(
+    a_token: &str,
+    debt_token: &str,
+    user_address: &str,
+    block_number: u64,
+) -> ReserveBalances {
+    let mut balances = ReserveBalances::default();
+    for (token, balance) in [
+        (a_token, &mut balances.scaled_supply),
+        (debt_token, &mut balances.scaled_debt),
+    ] {
+        match get_scaled_balance_of_at_block(token, user_address, block_number).await {
+            Ok(value) => *balance = U256::from(value),
+            Err(e) => {
+                eprintln!(
+                    "Error fetching scaledBalanceOf of {} at block {} (an archive RPC is required): {}",
+                    token, block_number, e
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    balances
+}
+
+fn print_balance_pair(replayed: &ReserveBalances, chain: &ReserveBalances) {
+    println!(
+        "  replayed: scaled supply {}, scaled debt {}",
+        replayed.scaled_supply, replayed.scaled_debt
+    );
+    println!(
+        "  on-chain: scaled supply {}, scaled debt {}",
+        chain.scaled_supply, chain.scaled_debt
+    );
+}
+
+fn print_timeline_entry(
+    entry: &TimelineEntry,
+    symbol: &str,
+    amount: impl Fn(Option<U256>) -> String,
+) {
+    println!(
+        "  block {} #{} {} {} {} (tx {})",
+        entry.block_number,
+        entry.log_index,
+        entry.action,
+        amount(entry.amount),
+        symbol,
+        entry.tx_hash
+    );
+    println!(
+        "      supply: {} scaled, {} real | debt: {} scaled, {} real",
+        amount(Some(entry.balances.scaled_supply)),
+        amount(entry.supply),
+        amount(Some(entry.balances.scaled_debt)),
+        amount(entry.debt)
+    );
+}
+
+// Prints events as they are read from the cursor and returns how many were printed.
+// In grouped mode every event of the matching transactions is printed, one transaction
+// at a time.
+async fn handle_money_market_event_output(
+    mut events: Cursor<MoneyMarketEventDocument>,
+    grouped: bool,
+) -> usize {
+    let mut event_count = 0;
+    // (blockNumber, txHash) of the matching events, for grouped mode
+    let mut transactions: BTreeSet<(u64, String)> = BTreeSet::new();
+    while let Some(event_result) = events.next().await {
+        let event = match event_result {
+            Ok(event) => event,
+            Err(e) => {
+                eprintln!("Error reading event: {}", e);
+                std::process::exit(1);
+            }
+        };
+        event_count += 1;
+        if grouped {
+            transactions.insert((event.block_number(), event.tx_hash().to_lowercase()));
+        } else {
+            print_money_market_event(&event);
+        }
+    }
+    if !grouped || transactions.is_empty() {
+        return event_count;
+    }
+
+    let reserve_tokens = reserve_token_addresses().await;
+    let tx_hashes: Vec<String> = transactions.into_iter().map(|(_, hash)| hash).collect();
+    let mut missing_companions = 0;
+    for batch in tx_hashes.chunks(BLOCK_PREFETCH_BATCH_SIZE) {
+        let events = match find_transaction_events(batch).await {
+            Ok(events) => events,
+            Err(e) => {
+                eprintln!("Error fetching transaction events: {}", e);
+                std::process::exit(1);
+            }
+        };
+        for tx_events in events.chunk_by(|a, b| a.tx_hash().eq_ignore_ascii_case(b.tx_hash())) {
+            missing_companions += print_transaction_events(tx_events, &reserve_tokens);
+        }
+    }
+    println!(
+        "\n📊 {} transactions, {} missing companion events",
+        tx_hashes.len(),
+        missing_companions
+    );
+    event_count
+}
+
+pub async fn handle_get_tx(flags: Vec<Flag>) {
+    let tx_hash = extract_value_from_flags_or_exit(
+        flags,
+        FlagType::GetTx,
+        "Error: --get-tx requires a transaction hash to be specified.",
+    );
+
+    let events = match find_transaction_events(std::slice::from_ref(&tx_hash)).await {
+        Ok(events) => events,
+        Err(e) => {
+            eprintln!("Error fetching transaction events: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if events.is_empty() {
+        println!("No events found for transaction: {}", tx_hash);
+        return;
+    }
+
+    let reserve_tokens = reserve_token_addresses().await;
+    print_transaction_events(&events, &reserve_tokens);
+}
+
+// Prints the events of one transaction under its action and returns how many
+// companion events are missing
+fn print_transaction_events(
+    events: &[MoneyMarketEventDocument],
+    reserve_tokens: &HashMap<String, (String, String)>,
+) -> usize {
+    let check = check_transaction_events(events, reserve_tokens);
+    println!("\n{}", check);
+    for event in events {
+        print_money_market_event(event);
+    }
+    for companion in &check.missing_companions {
+        println!("⚠️ Missing companion event: {}", companion);
+    }
+    check.missing_companions.len()
+}
+
+// Lowercase reserve address -> lowercase (aToken, variable debt token) addresses
+async fn reserve_token_addresses() -> HashMap<String, (String, String)> {
+    let reserves = match find_all_reserves().await {
+        Ok(reserves) => reserves,
+        Err(e) => {
+            eprintln!("Error fetching reserve tokens: {}", e);
+            std::process::exit(1);
+        }
+    };
+    reserves
+        .into_iter()
+        .map(|reserve| {
+            (
+                reserve.reserveAddress.to_lowercase(),
+                (
+                    reserve.aTokenAddress.to_lowercase(),
+                    reserve.variableDebtTokenAddress.to_lowercase(),
+                ),
+            )
+        })
+        .collect()
+}
+
+fn print_money_market_event(event: &MoneyMarketEventDocument) {
+    match event {
+        MoneyMarketEventDocument::ATokenBalanceTransfer(doc) => {
+            println!("AToken Balance Transfer Event:");
+            println!("  Doc: {:?}", doc);
+        }
+        MoneyMarketEventDocument::ATokenTransfer(doc) => {
+            println!("AToken Transfer Event:");
+            println!("  Doc: {:?}", doc);
+        }
+        MoneyMarketEventDocument::ATokenBurn(doc) => {
+            println!("AToken Burn Event:");
+            println!("  Doc: {:?}", doc);
+        }
+        MoneyMarketEventDocument::ATokenMint(doc) => {
+            println!("AToken Mint Event:");
+            println!("  Doc: {:?}", doc);
+        }
+        MoneyMarketEventDocument::Borrow(doc) => {
+            println!("Borrow Event:");
+            println!("  Doc: {:?}", doc);
+        }
+        MoneyMarketEventDocument::DebtTokenBurn(doc) => {
+            println!("Debt Token Burn Event:");
+            println!("  Doc: {:?}", doc);
+        }
+        MoneyMarketEventDocument::DebtTokenMint(doc) => {
+            println!("Debt Token Mint Event:");
+            println!("  Doc: {:?}", doc);
+        }
+        MoneyMarketEventDocument::Repay(doc) => {
+            println!("Repay Event:");
+            println!("  Doc: {:?}", doc);
+        }
+        MoneyMarketEventDocument::ReserveDataUpdated(doc) => {
+            println!("Reserve Data Updated Event:");
+            println!("  Doc: {:?}", doc);
+        }
+        MoneyMarketEventDocument::Supply(doc) => {
+            println!("Supply Event:");
+            println!("  Doc: {:?}", doc);
+        }
+        MoneyMarketEventDocument::Withdraw(doc) => {
+            println!("Withdraw Event:");
+            println!("  Doc: {:?}", doc);
+        }
+    }
+}
+
+async fn handle_validate_reserve_indexes_generic(reserve_address: String) {
+    let (reserve, _) = resolve_reserve_or_exit(&reserve_address).await;
+    let reserve_address = reserve.reserveAddress;
+    println!("Validating reserve indexes for: {}", reserve_address);
+    // Get database values
+    let reserve_data =
+        match find_reserve_for_token(&reserve_address, ReserveTokenField::Reserve).await {
+            Ok(Some(data)) => data,
+            Ok(None) => {
+                eprintln!("Reserve not found in database: {}", reserve_address);
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Error fetching reserve data: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+    println!("Reserve: {}", reserve_address);
+    println!("Token: {}", reserve_data.symbol);
+    // Get on-chain values
+    let on_chain_liquidity_index = match get_atoken_liquidity_index(&reserve_address).await {
+        Ok(index) => index,
+        Err(e) => {
+            eprintln!("Error fetching on-chain liquidity index: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let on_chain_variable_borrow_index = match get_variable_borrow_index(&reserve_address).await {
+        Ok(index) => index,
+        Err(e) => {
+            eprintln!("Error fetching on-chain variable borrow index: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Convert database values to u128 for comparison
+    let db_liquidity_index = reserve_data
+        .liquidityIndex
+        .to_string()
+        .parse::<u128>()
+        .unwrap_or(0);
+    let db_variable_borrow_index = reserve_data
+        .variableBorrowIndex
+        .to_string()
+        .parse::<u128>()
+        .unwrap_or(0);
+
+    println!("Liquidity Index:");
+    println!("  Database: {}", db_liquidity_index);
+    println!("  On-Chain: {}", on_chain_liquidity_index);
+    println!(
+        "  Difference: {}",
+        db_liquidity_index.abs_diff(on_chain_liquidity_index)
+    );
+
+    println!("Variable Borrow Index:");
+    println!("  Database: {}", db_variable_borrow_index);
+    println!("  On-Chain: {}", on_chain_variable_borrow_index);
+    println!(
+        "  Difference: {}",
+        db_variable_borrow_index.abs_diff(on_chain_variable_borrow_index)
+    );
+}
+
+pub async fn handle_validate_reserve_indexes(flags: Vec<Flag>) {
+    let reserve_address = extract_value_from_flags_or_exit(
+        flags.clone(),
+        FlagType::ValidateReserveIndexes,
+        "Error: --validate-reserve-indexes requires a reserve address to be specified.",
+    );
+
+    handle_validate_reserve_indexes_generic(reserve_address).await;
+}
+
+pub async fn handle_validate_all_reserve_indexes() {
+    println!("Validating indexes for all reserves...");
+
+    let reserves = find_all_reserve_addresses().await;
+
+    if reserves.is_empty() {
+        println!("No reserves found.");
+        return;
+    }
+
+    println!("Found {} reserves to validate", reserves.len());
+
+    for reserve in reserves {
+        handle_validate_reserve_indexes_generic(reserve).await;
+    }
+
+    println!("\n🎉 Reserve index validation complete!");
+}
+
+pub async fn handle_address_casing_report() {
+    let collections = Collections::new();
+    let address_fields: Vec<(&str, &str)> = vec![
+        (collections.reserve_tokens, "reserveAddress"),
+        (collections.reserve_tokens, "aTokenAddress"),
+        (collections.reserve_tokens, "variableDebtTokenAddress"),
+        (collections.reserve_tokens, "suppliers"),
+        (collections.reserve_tokens, "borrowers"),
+        (collections.user_positions, "userAddress"),
+        (collections.user_positions, "positions.reserveAddress"),
+        (collections.user_positions, "positions.aTokenAddress"),
+        (
+            collections.user_positions,
+            "positions.variableDebtTokenAddress",
+        ),
+        (collections.money_market_events, "reserve"),
+        (collections.money_market_events, "tokenAddress"),
+        (collections.money_market_events, "user"),
+        (collections.money_market_events, "onBehalfOf"),
+        (collections.money_market_events, "caller"),
+        (collections.money_market_events, "from"),
+        (collections.money_market_events, "to"),
+        (collections.money_market_events, "target"),
+        (collections.money_market_events, "repayer"),
+    ];
+
+    println!("Scanning {} address fields...", address_fields.len());
+
+    let mut reports: Vec<AddressFieldReport> = Vec::new();
+    // lowercase address -> stored form -> fields where that form was found
+    let mut stored_forms: HashMap<String, BTreeMap<String, Vec<String>>> = HashMap::new();
+
+    for (collection, field) in address_fields {
+        let values = match find_distinct_string_values(collection, field).await {
+            Ok(values) => values,
+            Err(e) => {
+                eprintln!("Error scanning {}.{}: {}", collection, field, e);
+                std::process::exit(1);
+            }
+        };
+
+        let mut report = AddressFieldReport::new(collection, field);
+        for value in values {
+            match classify_address(&value) {
+                AddressForm::Lowercase => report.lowercase += 1,
+                AddressForm::Checksum => report.checksum += 1,
+                AddressForm::NonCanonical => report.non_canonical.push(value.clone()),
+                AddressForm::Invalid => {
+                    report.invalid.push(value);
+                    continue;
+                }
+            }
+            if let Some(lowercase) = normalize_address(&value) {
+                stored_forms
+                    .entry(lowercase)
+                    .or_default()
+                    .entry(value)
+                    .or_default()
+                    .push(report.location());
+            }
+        }
+        reports.push(report);
+    }
+
+    println!("\n📋 Stored address forms per field:");
+    for report in &reports {
+        let mixed = if report.lowercase > 0 && report.checksum > 0 {
+            " ⚠️ mixed"
+        } else {
+            ""
+        };
+        println!(
+            "  {}: {} lowercase, {} checksum, {} non-canonical, {} invalid{}",
+            report.location(),
+            report.lowercase,
+            report.checksum,
+            report.non_canonical.len(),
+            report.invalid.len(),
+            mixed
+        );
+    }
+
+    let non_canonical_count: usize = reports.iter().map(|r| r.non_canonical.len()).sum();
+    let invalid_count: usize = reports.iter().map(|r| r.invalid.len()).sum();
+    if non_canonical_count + invalid_count > 0 {
+        println!("\n❌ Addresses stored in non-canonical form:");
+        for report in &reports {
+            for value in &report.non_canonical {
+                println!("  {}: {} (bad checksum casing)", report.location(), value);
+            }
+            for value in &report.invalid {
+                println!("  {}: {:?} (not an address)", report.location(), value);
+            }
+        }
+    }
+
+    let mut inconsistent: Vec<(&String, &BTreeMap<String, Vec<String>>)> = stored_forms
+        .iter()
+        .filter(|(_, forms)| forms.len() > 1)
+        .collect();
+    inconsistent.sort_by(|a, b| a.0.cmp(b.0));
+    if !inconsistent.is_empty() {
+        println!("\n⚠️ Addresses stored with different casing across collections:");
+        for (address, forms) in &inconsistent {
+            println!("  {}:", address);
+            for (form, locations) in forms.iter() {
+                println!("    {} in {}", form, locations.join(", "));
+            }
+        }
+    }
+
+    println!(
+        "\n📊 Summary: {} distinct addresses, {} non-canonical values, {} invalid values, {} addresses with inconsistent casing",
+        stored_forms.len(),
+        non_canonical_count,
+        invalid_count,
+        inconsistent.len()
+    );
+}
+
+pub async fn handle_reconcile_reserves() {
+    let reserves = match find_all_reserves().await {
+        Ok(reserves) => reserves,
+        Err(e) => {
+            eprintln!("Error fetching reserve tokens: {}", e);
             std::process::exit(1);
         }
+    };
+    let treasury = treasury_address();
+    let (mut reconciliations, mut issues) = reserve_reconciliations(&reserves, treasury.as_deref());
+    let users = for_each_user_position(|user| {
+        reconcile_user_positions(&mut reconciliations, user, &mut issues)
+    })
+    .await;
+
+    println!(
+        "Reconciling {} reserves with {} user positions (database only)",
+        reconciliations.len(),
+        users
+    );
+    match &treasury {
+        Some(treasury) => println!("Treasury: {}", treasury),
+        None => println!(
+            "TREASURY_ADDRESS is not set, a position of the treasury is counted with the users"
+        ),
+    }
+
+    let mut consistent = 0;
+    for reconciliation in &reconciliations {
+        if reconciliation.is_consistent() {
+            consistent += 1;
+        }
+        println!(
+            "\n{} {} ({})",
+            if reconciliation.is_consistent() {
+                "✅"
+            } else {
+                "❌"
+            },
+            reconciliation.symbol,
+            reconciliation.reserve
+        );
+        println!(
+            "  supply: total {} | users {} ({} suppliers) | treasury {} | residual {}",
+            reconciliation.total_supply,
+            reconciliation.users_supply,
+            reconciliation.suppliers,
+            reconciliation
+                .treasury_supply
+                .map_or_else(|| "-".to_string(), |v| v.to_string()),
+            reconciliation.supply_residual()
+        );
+        println!(
+            "  debt:   total {} | users {} ({} borrowers) | residual {}",
+            reconciliation.total_debt,
+            reconciliation.users_debt,
+            reconciliation.borrowers,
+            reconciliation.debt_residual()
+        );
+        if reconciliation.treasury_supply.is_none()
+            && matches!(reconciliation.supply_residual(), ScaledDelta::Increase(r) if !r.is_zero())
+        {
+            println!(
+                "  the treasury has no position in this reserve, a positive supply residual can be its balance"
+            );
+        }
     }
+
+    if !issues.is_empty() {
+        println!("\n⚠️ Positions not counted:");
+        for issue in &issues {
+            println!("  {}", issue);
+        }
+    }
+    println!(
+        "\n📋 {} of {} reserves match the sum of their user positions",
+        consistent,
+        reconciliations.len()
+    );
 }
 
-pub async fn handle_validate_token_all() {
-    handle_validate_token_all_generic(false).await;
+pub async fn handle_check_user_lists(flags: Vec<Flag>) {
+    let on_chain = flags.iter().any(|flag| matches!(flag, Flag::OnChain));
+    let reserves = selected_reserves_or_exit(&flags, FlagType::CheckUserLists).await;
+    let mut holders = HashMap::new();
+    for_each_user_position(|user| add_position_holders(&mut holders, user)).await;
+    let normalize =
+        |address: &String| normalize_address(address).unwrap_or_else(|| address.to_lowercase());
+
+    let mut consistent = 0;
+    for reserve in &reserves {
+        let (suppliers, borrowers) = holders
+            .remove(&reserve.reserveAddress.to_lowercase())
+            .unwrap_or_default();
+        let mut checks = vec![
+            check_user_list("suppliers", &reserve.suppliers, &suppliers),
+            check_user_list("borrowers", &reserve.borrowers, &borrowers),
+        ];
+
+        if on_chain {
+            for (list, listed, db_holders, token) in [
+                (
+                    "suppliers (on-chain)",
+                    &reserve.suppliers,
+                    &suppliers,
+                    &reserve.aTokenAddress,
+                ),
+                (
+                    "borrowers (on-chain)",
+                    &reserve.borrowers,
+                    &borrowers,
+                    &reserve.variableDebtTokenAddress,
+                ),
+            ] {
+                let candidates: BTreeSet<String> = listed
+                    .iter()
+                    .map(normalize)
+                    .chain(db_holders.iter().cloned())
+                    .collect();
+                let (holders, failed) = on_chain_holders(token, candidates).await;
+                for (user, e) in &failed {
+                    eprintln!(
+                        "Warning: could not fetch scaledBalanceOf of {} for {}, left out of the on-chain check: {}",
+                        token, user, e
+                    );
+                }
+                let listed: Vec<String> = listed
+                    .iter()
+                    .filter(|address| !failed.iter().any(|(user, _)| *user == normalize(address)))
+                    .cloned()
+                    .collect();
+                checks.push(check_user_list(list, &listed, &holders));
+            }
+        }
+
+        let is_consistent = checks.iter().all(|check| check.is_consistent());
+        if is_consistent {
+            consistent += 1;
+        }
+        println!(
+            "\n{} {} ({}): {} suppliers, {} borrowers listed",
+            if is_consistent { "✅" } else { "❌" },
+            reserve.symbol,
+            reserve.reserveAddress,
+            reserve.suppliers.len(),
+            reserve.borrowers.len()
+        );
+        for check in &checks {
+            println!("  {}", check);
+            for (label, users) in [
+                ("missing", &check.missing),
+                ("stale", &check.stale),
+                ("duplicate", &check.duplicates),
+            ] {
+                for user in users {
+                    println!("    {}: {}", label, user);
+                }
+            }
+        }
+    }
+    println!(
+        "\n📋 {} of {} reserves have consistent suppliers and borrowers lists",
+        consistent,
+        reserves.len()
+    );
 }
 
-pub async fn handle_validate_token_all_scaled() {
-    handle_validate_token_all_generic(true).await;
+pub async fn handle_find_untracked_holders(flags: Vec<Flag>) {
+    let reserves = selected_reserves_or_exit(&flags, FlagType::FindUntrackedHolders).await;
+    let block_range = resolve_block_range_or_exit(&flags).await;
+    let to_block = match get_last_block().await {
+        Ok(block) => block,
+        Err(e) => {
+            eprintln!("Error fetching last block: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut addresses: Vec<Address> = vec![];
+    for reserve in &reserves {
+        for token in [&reserve.aTokenAddress, &reserve.variableDebtTokenAddress] {
+            match token.parse::<Address>() {
+                Ok(address) => addresses.push(address),
+                Err(_) => eprintln!(
+                    "Skipping invalid token address {} ({})",
+                    token, reserve.symbol
+                ),
+            }
+        }
+    }
+    // without --since the scan starts when the first token was deployed, holders from before
+    // the first indexed event are the ones the indexer never saw
+    let from_block = match block_range.from {
+        Some(from_block) => from_block,
+        None => {
+            let mut from_block = to_block;
+            for address in &addresses {
+                // None when deployed after the earliest token found so far
+                match get_deployment_block(*address, from_block).await {
+                    Ok(Some(block)) => from_block = block,
+                    Ok(None) => {}
+                    Err(e) => {
+                        eprintln!("Error finding the deployment block of {}: {}", address, e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            from_block
+        }
+    };
+    println!(
+        "Scanning blocks {} - {} for Transfer logs of {} tokens...",
+        from_block,
+        to_block,
+        addresses.len()
+    );
+
+    // one window at a time, only the recipients are kept
+    let settings = get_rpc_settings();
+    let window = settings.logs_block_range * settings.max_concurrent_requests as u64;
+    let topics = [ScaledTokenEvents::Transfer::SIGNATURE_HASH];
+    let mut recipients: HashMap<String, BTreeSet<String>> = HashMap::new();
+    let mut start = from_block;
+    while start <= to_block {
+        let end = min(start.saturating_add(window - 1), to_block);
+        let logs = match get_logs_in_range(&addresses, &topics, start, end).await {
+            Ok(logs) => logs,
+            Err(e) => {
+                eprintln!("Error fetching logs for blocks {} - {}: {}", start, end, e);
+                std::process::exit(1);
+            }
+        };
+        for (token, token_recipients) in transfer_recipients(&logs) {
+            recipients
+                .entry(token)
+                .or_default()
+                .extend(token_recipients);
+        }
+        start = end + 1;
+    }
+
+    let mut tracked = HashMap::new();
+    for_each_user_position(|user| {
+        let (address, reserves) = tracked_reserves(user);
+        tracked.insert(address, reserves);
+    })
+    .await;
+    let treasury = treasury_address().and_then(|address| normalize_address(&address));
+
+    let mut untracked_total = 0;
+    for reserve in &reserves {
+        println!("\n{} ({})", reserve.symbol, reserve.reserveAddress);
+        for (kind, token) in [
+            ("aToken", &reserve.aTokenAddress),
+            ("debt token", &reserve.variableDebtTokenAddress),
+        ] {
+            let candidates = recipients
+                .get(&token.to_lowercase())
+                .cloned()
+                .unwrap_or_default();
+            let untracked = find_untracked_holders(&reserve.reserveAddress, &candidates, &tracked);
+            // only the untracked recipients need their current balance fetched
+            let (holders, failed) = on_chain_holders(
+                token,
+                untracked
+                    .iter()
+                    .map(|holder| holder.address.clone())
+                    .collect(),
+            )
+            .await;
+            println!(
+                "  {} {}: {} recipients, {} untracked with a non-zero balance",
+                if holders.is_empty() { "✅" } else { "❌" },
+                kind,
+                candidates.len(),
+                holders.len()
+            );
+            for holder in untracked
+                .iter()
+                .filter(|holder| holders.contains(&holder.address))
+            {
+                let is_treasury = treasury.as_deref() == Some(holder.address.as_str());
+                println!(
+                    "    {}{}",
+                    holder,
+                    if is_treasury { " [treasury]" } else { "" }
+                );
+            }
+            for (user, e) in &failed {
+                eprintln!(
+                    "Warning: could not fetch scaledBalanceOf of {} for {}: {}",
+                    token, user, e
+                );
+            }
+            untracked_total += holders.len();
+        }
+    }
+    println!(
+        "\n📋 {} on-chain holders have no position in user_positions",
+        untracked_total
+    );
 }
 
-pub async fn handle_validate_token_all_generic(scaled: bool) {
-    println!("Validating all reserves in parallel...");
+pub async fn handle_index_history(flags: Vec<Flag>) {
+    let input = extract_value_from_flags_or_exit(
+        flags.clone(),
+        FlagType::IndexHistory,
+        "Error: --index-history requires a reserve to be specified.",
+    );
+    let csv_path = extract_optional_value_from_flags(&flags, FlagType::Csv);
+    let (reserve, _) = resolve_reserve_or_exit(&input).await;
 
-    // Get all reserves first
-    let reserves = match find_all_reserves().await {
-        Ok(reserves) => reserves,
+    let events = match find_reserve_data_updates(&reserve.reserveAddress).await {
+        Ok(events) => events,
         Err(e) => {
-            eprintln!("Error fetching reserve tokens: {}", e);
+            eprintln!("Error fetching ReserveDataUpdated events: {}", e);
             std::process::exit(1);
         }
     };
+    let (Some(first), Some(latest)) = (events.first(), events.last()) else {
+        println!("No ReserveDataUpdated events found for {}", reserve.symbol);
+        return;
+    };
 
-    // Create tasks for parallel validation
-    let tasks: Vec<_> = reserves
-        .into_iter()
-        .map(|reserve| {
-            let reserve_address = reserve.reserveAddress.clone();
-            task::spawn(async move {
-                if scaled {
-                    match validate_scaled_reserve(&reserve_address).await {
-                        Ok(result) => Ok(result),
-                        Err(e) => Err(format!("Failed to validate {}: {}", reserve_address, e)),
-                    }
-                } else {
-                    match validate_reserve(&reserve_address).await {
-                        Ok(result) => Ok(result),
-                        Err(e) => Err(format!("Failed to validate {}: {}", reserve_address, e)),
-                    }
+    let mut block_numbers: Vec<u64> = events.iter().map(|e| e.common.blockNumber).collect();
+    block_numbers.dedup();
+    for batch in block_numbers.chunks(BLOCK_PREFETCH_BATCH_SIZE) {
+        match prefetch_blocks(batch).await {
+            Ok(failed) => {
+                for (block_number, e) in failed {
+                    eprintln!("Error fetching timestamp for block {}: {}", block_number, e);
                 }
-            })
-        })
+            }
+            Err(e) => {
+                eprintln!("Error connecting to the RPC: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let timestamps: HashMap<u64, u64> = block_numbers
+        .iter()
+        .filter_map(|block_number| cached_block(*block_number).map(|b| (b.number, b.timestamp)))
         .collect();
+    let history = check_index_history(&events, &timestamps);
 
-    // Wait for all tasks to complete
-    let results = join_all(tasks).await;
+    println!(
+        "Index history of {} ({}): {} ReserveDataUpdated events, blocks {} - {}",
+        reserve.symbol,
+        reserve.reserveAddress,
+        history.len(),
+        first.common.blockNumber,
+        latest.common.blockNumber
+    );
+    for entry in history.iter().filter(|entry| {
+        entry.liquidity_index_status.is_error() || entry.borrow_index_status.is_error()
+    }) {
+        println!(
+            "❌ block {} #{} (tx {}): liquidity index {} ({}), borrow index {} ({})",
+            entry.block_number,
+            entry.log_index,
+            entry.tx_hash,
+            entry.liquidity_index,
+            entry.liquidity_index_status,
+            entry.variable_borrow_index,
+            entry.borrow_index_status
+        );
+    }
 
-    let mut success_count = 0;
-    let mut error_count = 0;
+    println!("\n📋 Summary:");
+    for (name, statuses) in [
+        (
+            "liquidity index",
+            history
+                .iter()
+                .map(|entry| entry.liquidity_index_status)
+                .collect::<Vec<_>>(),
+        ),
+        (
+            "borrow index",
+            history
+                .iter()
+                .map(|entry| entry.borrow_index_status)
+                .collect::<Vec<_>>(),
+        ),
+    ] {
+        let count = |predicate: fn(&IndexStatus) -> bool| {
+            statuses.iter().filter(|status| predicate(status)).count()
+        };
+        println!(
+            "  {}: {} ok, {} unchanged, {} decreased, {} not matching the rate, {} without timestamp",
+            name,
+            count(|s| matches!(s, IndexStatus::Consistent)),
+            count(|s| matches!(s, IndexStatus::Unchanged)),
+            count(|s| matches!(s, IndexStatus::Decreased)),
+            count(|s| matches!(s, IndexStatus::Mismatch { .. })),
+            count(|s| matches!(s, IndexStatus::NoTimestamp))
+        );
+    }
 
-    for result in results {
-        match result {
-            Ok(Ok(validation_result)) => {
-                success_count += 1;
-                if let Some(error) = &validation_result.error {
-                    error_count += 1;
-                    println!(
-                        "❌ Reserve {}: ERROR - {}",
-                        validation_result.reserve_address, error
-                    );
-                } else {
-                    println!(
-                        "✅ Reserve {} validated successfully",
-                        validation_result.reserve_address
-                    );
-                    println!(
-                        "  Supply - DB: {}\n  On-Chain:    {}\n  Diff: {}, %: {:.6}%",
-                        validation_result.supply.database_amount,
-                        validation_result.supply.on_chain_amount,
-                        validation_result.supply.difference,
-                        validation_result.supply.percentage
-                    );
-                    println!(
-                        "  Borrow - DB: {}\n  On-Chain:    {}\n  Diff: {}, %: {:.6}%",
-                        validation_result.borrow.database_amount,
-                        validation_result.borrow.on_chain_amount,
-                        validation_result.borrow.difference,
-                        validation_result.borrow.percentage
-                    );
-                }
+    let differences: Vec<(&str, String, String)> = [
+        (
+            "liquidityIndex",
+            &reserve.liquidityIndex,
+            &latest.liquidityIndex,
+        ),
+        (
+            "variableBorrowIndex",
+            &reserve.variableBorrowIndex,
+            &latest.variableBorrowIndex,
+        ),
+        (
+            "liquidityRate",
+            &reserve.liquidityRate,
+            &latest.liquidityRate,
+        ),
+        (
+            "variableBorrowRate",
+            &reserve.variableBorrowRate,
+            &latest.variableBorrowRate,
+        ),
+        (
+            "stableBorrowRate",
+            &reserve.stableBorrowRate,
+            &latest.stableBorrowRate,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, stored, event)| stored.to_string() != event.to_string())
+    .map(|(field, stored, event)| (field, stored.to_string(), event.to_string()))
+    .collect();
+    if differences.is_empty() {
+        println!(
+            "  ✅ the reserve document matches the latest ReserveDataUpdated (block {})",
+            latest.common.blockNumber
+        );
+    } else {
+        println!(
+            "  ❌ the reserve document differs from the latest ReserveDataUpdated (block {}):",
+            latest.common.blockNumber
+        );
+        for (field, stored, event) in differences {
+            println!("    {}: stored {}, latest event {}", field, stored, event);
+        }
+    }
+
+    if let Some(path) = csv_path {
+        // Never overwrite an existing export
+        let file = match File::create_new(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Error creating CSV file {}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+        let mut writer = BufWriter::new(file);
+        let lines = std::iter::once(IndexHistoryEntry::CSV_HEADER.to_string())
+            .chain(history.iter().map(|entry| entry.to_csv_line()));
+        for line in lines {
+            if let Err(e) = writeln!(writer, "{}", line) {
+                eprintln!("Error writing CSV file {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+        if let Err(e) = writer.flush() {
+            eprintln!("Error writing CSV file {}: {}", path, e);
+            std::process::exit(1);
+        }
+        println!(
+            "\nIndex history written to {} ({} rows)",
+            path,
+            history.len()
+        );
+    }
+}
+
+pub async fn handle_validate_rates(flags: Vec<Flag>) {
+    let reserves = selected_reserves_or_exit(&flags, FlagType::ValidateRates).await;
+
+    let mut consistent = 0;
+    for reserve in &reserves {
+        match validate_reserve_rates(reserve).await {
+            Ok(true) => consistent += 1,
+            Ok(false) => {}
+            Err(e) => println!(
+                "❌ {} ({}): could not validate rates: {}",
+                reserve.symbol, reserve.reserveAddress, e
+            ),
+        }
+    }
+    println!(
+        "\n📋 {} of {} reserves have consistent rates",
+        consistent,
+        reserves.len()
+    );
+}
+
+// Prints the stored, on-chain and recomputed rates of a reserve and returns whether they agree
+async fn validate_reserve_rates(
+    reserve: &ReserveTokenDocument,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let parse = |value: String| -> Result<U256, Box<dyn std::error::Error>> {
+        Ok(U256::from_dec_str(&value)?)
+    };
+    let reserve_data = get_reserve_data(&reserve.reserveAddress).await?;
+    let strategy_address = reserve_data.interestRateStrategyAddress;
+    let strategy = get_rate_strategy(strategy_address, &reserve.reserveAddress).await?;
+    let variable_debt = get_total_supply(&reserve.variableDebtTokenAddress).await?;
+    // the strategy weights the liquidity rate by the average stable rate of the stable debt
+    let (stable_debt, average_stable_rate) = if reserve_data.stableDebtTokenAddress == Address::ZERO
+    {
+        (U256::zero(), U256::zero())
+    } else {
+        get_stable_debt_supply_and_rate(&format!("{:#x}", reserve_data.stableDebtTokenAddress))
+            .await?
+    };
+    let debt = ReserveDebt {
+        variable: U256::from(variable_debt),
+        stable: stable_debt,
+        average_stable_rate,
+    };
+    let available_liquidity =
+        get_balance_of(&reserve.reserveAddress, &reserve.aTokenAddress).await?;
+    let reserve_factor = reserve_data.decoded_configuration().reserve_factor;
+    let recomputed = calculate_interest_rates(
+        &strategy,
+        &debt,
+        U256::from(available_liquidity),
+        U256::from(reserve_data.unbacked),
+        reserve_factor,
+    )
+    .map_err(|e| format!("rate recomputation failed: {:?}", e))?;
+
+    println!(
+        "\n{} ({}), strategy {}",
+        reserve.symbol, reserve.reserveAddress, strategy_address
+    );
+    println!(
+        "  utilization {:.4}% (optimal {:.2}%), base rate {:.4}%, slope1 {:.4}%, slope2 {:.4}%, reserve factor {:.2}%",
+        ray_to_percent(recomputed.borrow_usage_ratio),
+        ray_to_percent(strategy.optimal_usage_ratio),
+        ray_to_percent(strategy.base_variable_borrow_rate),
+        ray_to_percent(strategy.variable_rate_slope1),
+        ray_to_percent(strategy.variable_rate_slope2),
+        reserve_factor as f64 / 100.0
+    );
+
+    let mut is_consistent = true;
+    for (name, stored, on_chain, recomputed) in [
+        (
+            "liquidityRate",
+            parse(reserve.liquidityRate.to_string())?,
+            U256::from(reserve_data.currentLiquidityRate),
+            Some(recomputed.liquidity_rate),
+        ),
+        (
+            "variableBorrowRate",
+            parse(reserve.variableBorrowRate.to_string())?,
+            U256::from(reserve_data.currentVariableBorrowRate),
+            Some(recomputed.variable_borrow_rate),
+        ),
+        (
+            "stableBorrowRate",
+            parse(reserve.stableBorrowRate.to_string())?,
+            U256::from(reserve_data.currentStableBorrowRate),
+            None,
+        ),
+    ] {
+        let stored_matches = stored == on_chain;
+        let recomputed_matches = recomputed.is_none_or(|recomputed| {
+            relative_difference_bps(recomputed, on_chain) <= RATE_RECOMPUTE_TOLERANCE_BPS
+        });
+        is_consistent &= stored_matches && recomputed_matches;
+        println!(
+            "  {} {}: db {:.4}%, on-chain {:.4}%{}",
+            if stored_matches && recomputed_matches {
+                "✅"
+            } else {
+                "❌"
+            },
+            name,
+            ray_to_percent(stored),
+            ray_to_percent(on_chain),
+            recomputed
+                .map(|recomputed| format!(", recomputed {:.4}%", ray_to_percent(recomputed)))
+                .unwrap_or_default()
+        );
+        if !stored_matches {
+            println!("     stored {} differs from on-chain {}", stored, on_chain);
+        }
+    }
+    Ok(is_consistent)
+}
+
+pub async fn handle_validate_reserve_config(flags: Vec<Flag>) {
+    let reserves = selected_reserves_or_exit(&flags, FlagType::ValidateReserveConfig).await;
+    // activity is looked for over the last day unless --since says otherwise
+    let mut flags = flags;
+    if !flags.iter().any(|flag| matches!(flag, Flag::Since(_))) {
+        flags.push(Flag::Since(DEFAULT_RESERVE_ACTIVITY_WINDOW.to_string()));
+    }
+    let block_range = resolve_block_range_or_exit(&flags).await;
+
+    let mut consistent = 0;
+    for reserve in &reserves {
+        match validate_reserve_configuration(reserve, &block_range).await {
+            Ok(true) => consistent += 1,
+            Ok(false) => {}
+            Err(e) => println!(
+                "❌ {} ({}): could not validate the configuration: {}",
+                reserve.symbol, reserve.reserveAddress, e
+            ),
+        }
+    }
+    println!(
+        "\n📋 {} of {} reserves are within their caps and status",
+        consistent,
+        reserves.len()
+    );
+}
+
+// Prints the decoded configuration of a reserve with its issues and returns whether there are none
+async fn validate_reserve_configuration(
+    reserve: &ReserveTokenDocument,
+    block_range: &BlockRange,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let configuration = get_reserve_data(&reserve.reserveAddress)
+        .await?
+        .decoded_configuration();
+    let total_supply = get_total_supply(&reserve.aTokenAddress).await?;
+    let total_debt = get_total_supply(&reserve.variableDebtTokenAddress).await?;
+
+    let mut activity: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut cursor = stream_token_events(&reserve.reserveAddress, block_range).await?;
+    while let Some(event) = cursor.next().await {
+        *activity.entry(event?.event_type()).or_default() += 1;
+    }
+
+    let issues = check_reserve_configuration(
+        &configuration,
+        U256::from(total_supply),
+        U256::from(total_debt),
+        &activity,
+    );
+    println!(
+        "\n{} {} ({}), supply {}, debt {}, {} events indexed",
+        if issues.is_empty() { "✅" } else { "❌" },
+        reserve.symbol,
+        reserve.reserveAddress,
+        format_units(U256::from(total_supply), configuration.decimals),
+        format_units(U256::from(total_debt), configuration.decimals),
+        activity.values().sum::<usize>()
+    );
+    println!("{}", configuration);
+    for issue in &issues {
+        println!("  ❌ {}", issue);
+    }
+    Ok(issues.is_empty())
+}
+
+pub async fn handle_stable_debt(flags: Vec<Flag>) {
+    let reserves = selected_reserves_or_exit(&flags, FlagType::StableDebt).await;
+    let mut holders = HashMap::new();
+    for_each_user_position(|user| add_position_holders(&mut holders, user)).await;
+
+    let mut with_stable_debt = 0;
+    for reserve in &reserves {
+        let borrows = match find_stable_borrows(&reserve.reserveAddress).await {
+            Ok(borrows) => borrows,
+            Err(e) => {
+                eprintln!(
+                    "Error fetching stable borrows of {} ({}): {}",
+                    reserve.symbol, reserve.reserveAddress, e
+                );
+                std::process::exit(1);
             }
-            Ok(Err(e)) => {
-                error_count += 1;
-                println!("❌ Validation failed: {}", e);
+        };
+        let stable_borrowers = stable_borrowers(&borrows);
+        let stable_debt_token = match get_reserve_data(&reserve.reserveAddress).await {
+            Ok(reserve_data) => reserve_data.stableDebtTokenAddress,
+            Err(e) => {
+                println!(
+                    "❌ {} ({}): could not fetch the reserve data: {}",
+                    reserve.symbol, reserve.reserveAddress, e
+                );
+                continue;
             }
+        };
+        if stable_debt_token == Address::ZERO {
+            println!(
+                "{} {} ({}): no stable debt token, {} stable borrows indexed",
+                if borrows.is_empty() { "✅" } else { "❌" },
+                reserve.symbol,
+                reserve.reserveAddress,
+                borrows.len()
+            );
+            with_stable_debt += usize::from(!borrows.is_empty());
+            continue;
+        }
+        let stable_debt_token = format!("{:#x}", stable_debt_token);
+        let total_supply = match get_total_supply(&stable_debt_token).await {
+            Ok(total_supply) => total_supply,
             Err(e) => {
-                error_count += 1;
-                println!("❌ Task failed: {}", e);
+                println!(
+                    "❌ {} ({}): could not fetch the stable debt supply: {}",
+                    reserve.symbol, reserve.reserveAddress, e
+                );
+                continue;
             }
+        };
+        if total_supply == 0 && borrows.is_empty() {
+            println!(
+                "✅ {} ({}): no stable debt (token {})",
+                reserve.symbol, reserve.reserveAddress, stable_debt_token
+            );
+            continue;
         }
-    }
+        with_stable_debt += 1;
+
+        // every address the database relates to the reserve may hold stable debt
+        let (suppliers, borrowers) = holders
+            .remove(&reserve.reserveAddress.to_lowercase())
+            .unwrap_or_default();
+        let mut candidates: BTreeSet<String> = suppliers.into_iter().chain(borrowers).collect();
+        candidates.extend(
+            reserve
+                .suppliers
+                .iter()
+                .chain(&reserve.borrowers)
+                .map(|address| {
+                    normalize_address(address).unwrap_or_else(|| address.to_lowercase())
+                }),
+        );
+        candidates.extend(stable_borrowers.keys().cloned());
+        let (holders, failed) = stable_debt_balances(&stable_debt_token, candidates).await;
 
+        println!(
+            "❌ {} ({}): {} stable debt (token {}) that the database has no field for, {} stable borrows indexed",
+            reserve.symbol,
+            reserve.reserveAddress,
+            total_supply,
+            stable_debt_token,
+            borrows.len()
+        );
+        for (user, balance) in &holders {
+            println!(
+                "  {}: {} stable debt, {} stable borrows indexed",
+                user,
+                balance,
+                stable_borrowers.get(user).copied().unwrap_or_default()
+            );
+        }
+        let found: u128 = holders.values().sum();
+        if found < total_supply {
+            println!(
+                "  {} of the stable debt is held by addresses unknown to the database",
+                total_supply - found
+            );
+        }
+        for (user, e) in failed {
+            eprintln!("  Error fetching the stable debt of {}: {}", user, e);
+        }
+    }
     println!(
-        "\n📊 Summary: {} successful, {} errors",
-        success_count, error_count
+        "\n📋 {} of {} reserves have stable debt",
+        with_stable_debt,
+        reserves.len()
     );
 }
 
-pub async fn handle_validate_users_all() {
-    handle_validate_users_all_generic(false).await;
-}
-
-pub async fn handle_validate_users_all_scaled() {
-    handle_validate_users_all_generic(true).await;
-}
-
-pub async fn handle_validate_users_all_generic(scaled: bool) {
-    println!("Validating all users in parallel...");
-
-    // Fetch all users first
-    let users = match find_all_users().await {
-        Ok(users) => users,
+pub async fn handle_reserve_health(flags: Vec<Flag>) {
+    let reserves = selected_reserves_or_exit(&flags, FlagType::ReserveHealth).await;
+    // user_positions only hold the balances after the latest indexed event, so every on-chain
+    // value is read at that block unless --at-time pins a later one
+    let latest_indexed = match find_latest_event_block().await {
+        Ok(latest_indexed) => latest_indexed,
         Err(e) => {
-            eprintln!("Error fetching users: {}", e);
+            eprintln!("Error fetching the latest indexed block: {}", e);
             std::process::exit(1);
         }
     };
-
-    // Create tasks for parallel user validation
-    let tasks: Vec<_> = users
-        .into_iter()
-        .map(|user| {
-            let user_address = user.userAddress.clone();
-            task::spawn(async move {
-                // Use handle_user_validation instead of calling validate_user_all_positions directly
-                if scaled {
-                    handle_user_validation_scaled(&user_address, false).await;
-                } else {
-                    handle_user_validation(&user_address, false).await;
-                }
-            })
-        })
-        .collect();
-
-    // Wait for all tasks to complete
-    let results = join_all(tasks).await;
-
-    let mut success_count = 0;
-    let mut error_count = 0;
-
-    for result in results {
-        match result {
-            Ok(_) => {
-                success_count += 1;
-            }
+    let block_number = match (resolve_block_range_or_exit(&flags).await.to, latest_indexed) {
+        (Some(block_number), Some(latest_indexed)) if block_number < latest_indexed => {
+            eprintln!(
+                "Error: block {} is before the latest indexed event (block {}), user_positions can't be compared with it",
+                block_number, latest_indexed
+            );
+            std::process::exit(1);
+        }
+        (Some(block_number), _) | (None, Some(block_number)) => block_number,
+        (None, None) => match get_last_block().await {
+            Ok(block_number) => block_number,
             Err(e) => {
-                error_count += 1;
-                println!("❌ Task failed: {}", e);
+                eprintln!("Error fetching last block: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    // every reserve is reconciled so that positions elsewhere aren't reported as unknown, with
+    // the treasury of each selected reserve kept apart from its users
+    let all_reserves = match find_all_reserves().await {
+        Ok(reserves) => reserves,
+        Err(e) => {
+            eprintln!("Error fetching reserve tokens: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let (mut reconciliations, _) =
+        reserve_reconciliations(&all_reserves, treasury_address().as_deref());
+    for reserve in &reserves {
+        let Some(reconciliation) = reconciliations.iter_mut().find(|reconciliation| {
+            reconciliation
+                .reserve
+                .eq_ignore_ascii_case(&reserve.reserveAddress)
+        }) else {
+            continue;
+        };
+        if reconciliation.treasury.is_none() {
+            match get_treasury_address(&reserve.aTokenAddress).await {
+                Ok(treasury) => reconciliation.treasury = normalize_address(&treasury),
+                Err(e) => eprintln!(
+                    "Error fetching the treasury of {} ({}): {}",
+                    reserve.symbol, reserve.reserveAddress, e
+                ),
             }
         }
     }
+    // positions that can't be counted show up in the supply residual
+    let mut uncounted = vec![];
+    for_each_user_position(|user| {
+        reconcile_user_positions(&mut reconciliations, user, &mut uncounted)
+    })
+    .await;
+    println!("Reserve health at block {}", block_number);
+    let reserve_addresses: Vec<String> = reserves
+        .iter()
+        .map(|reserve| reserve.reserveAddress.clone())
+        .collect();
+    // USD values use the oracle prices of the same block
+    let prices = match price_book(&reserve_addresses, Some(block_number)).await {
+        Ok(prices) => prices,
+        Err(e) => {
+            eprintln!(
+                "⚠️  Could not read oracle prices, USD values are left out: {}",
+                e
+            );
+            PriceBook::default()
+        }
+    };
 
+    let mut healthy = 0;
+    let mut usd_residual = 0.0;
+    for reserve in &reserves {
+        let reconciliation = reconciliations.iter().find(|reconciliation| {
+            reconciliation
+                .reserve
+                .eq_ignore_ascii_case(&reserve.reserveAddress)
+        });
+        match reserve_health(reserve, reconciliation, block_number).await {
+            Ok(health) => {
+                let issues = check_reserve_health(&health);
+                if issues.is_empty() {
+                    healthy += 1;
+                }
+                print_reserve_health(reserve, &health, &issues);
+                if let Some(usd) = residual_usd_value(reserve, &health, &prices) {
+                    println!("  supply residual (USD): ${:.2}", usd);
+                    usd_residual += usd;
+                }
+            }
+            Err(e) => println!(
+                "❌ {} ({}): could not read the reserve at block {}: {}",
+                reserve.symbol, reserve.reserveAddress, block_number, e
+            ),
+        }
+    }
     println!(
-        "\n📊 Summary: {} successful users, {} errors",
-        success_count, error_count
+        "\n📋 {} of {} reserves are consistent at block {}",
+        healthy,
+        reserves.len(),
+        block_number
     );
+    if !prices.is_empty() {
+        println!("💵 Supply residual over all reserves: ${:.2}", usd_residual);
+    }
 }
 
-pub async fn handle_validate_user_all(flags: Vec<Flag>) {
-    let user_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ValidateUserAll,
-        "Error: --validate-user-all requires a user address to be specified.",
-    );
-
-    println!("Validating all positions for user {}...", user_address);
-    handle_user_validation(&user_address, true).await;
+// USD value of the supply residual, in tokens at the pinned liquidity index
+fn residual_usd_value(
+    reserve: &ReserveTokenDocument,
+    health: &ReserveHealth,
+    prices: &PriceBook,
+) -> Option<f64> {
+    let scaled = match health.supply_residual() {
+        ScaledDelta::Increase(amount) | ScaledDelta::Decrease(amount) => amount,
+    };
+    let amount = ray_mul(scaled, health.liquidity_index).ok()?;
+    if amount > U256::from(u128::MAX) {
+        return None;
+    }
+    prices.usd_value(&reserve.reserveAddress, amount.as_u128())
 }
-pub async fn handle_validate_user_all_scaled(flags: Vec<Flag>) {
-    let user_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ValidateUserAll,
-        "Error: --validate-user-all requires a user address to be specified.",
-    );
 
-    println!("Validating all positions for user {}...", user_address);
-    handle_user_validation_scaled(&user_address, true).await;
-}
+// `reconciliation` holds the user_positions sums of the reserve, None when the reserve
+// document has non-integer totals
+async fn reserve_health(
+    reserve: &ReserveTokenDocument,
+    reconciliation: Option<&ReserveReconciliation>,
+    block_number: u64,
+) -> Result<ReserveHealth, Box<dyn std::error::Error>> {
+    let reconciliation = reconciliation.ok_or("the reserve document has non-integer totals")?;
+    let treasury = reconciliation
+        .treasury
+        .as_deref()
+        .ok_or("the treasury address is unknown")?;
+    let reserve_data = get_reserve_data_at_block(&reserve.reserveAddress, block_number).await?;
+    let scaled_total_supply =
+        get_scaled_total_supply_at_block(&reserve.aTokenAddress, block_number).await?;
+    let treasury_supply =
+        get_scaled_balance_of_at_block(&reserve.aTokenAddress, treasury, block_number).await?;
+    let users_supply = reconciliation.users_supply;
 
-async fn handle_user_validation(user_address: &str, exit_on_error: bool) {
-    handle_user_validation_generic(user_address, exit_on_error, false).await;
+    Ok(ReserveHealth {
+        block_number,
+        configuration: reserve_data.decoded_configuration(),
+        scaled_total_supply: U256::from(scaled_total_supply),
+        users_supply,
+        treasury_supply: U256::from(treasury_supply),
+        accrued_to_treasury: U256::from(reserve_data.accruedToTreasury),
+        liquidity_index: U256::from(reserve_data.liquidityIndex),
+        unbacked: U256::from(reserve_data.unbacked),
+        isolation_mode_total_debt: U256::from(reserve_data.isolationModeTotalDebt),
+    })
 }
 
-async fn handle_user_validation_scaled(user_address: &str, exit_on_error: bool) {
-    handle_user_validation_generic(user_address, exit_on_error, true).await;
+fn print_reserve_health(reserve: &ReserveTokenDocument, health: &ReserveHealth, issues: &[String]) {
+    println!(
+        "\n{} {} ({})",
+        if issues.is_empty() { "✅" } else { "❌" },
+        reserve.symbol,
+        reserve.reserveAddress
+    );
+    if reserve.blockNumber > health.block_number {
+        println!(
+            "  ⚠️  the reserve document was updated at block {}, after the pinned block",
+            reserve.blockNumber
+        );
+    }
+    println!(
+        "  scaled supply: total {}, users {}, treasury {}, accrued to treasury {}",
+        health.scaled_total_supply,
+        health.users_supply,
+        health.treasury_supply,
+        health.accrued_to_treasury
+    );
+    println!(
+        "  unbacked {} (mint cap {} tokens)",
+        health.unbacked, health.configuration.unbacked_mint_cap
+    );
+    if health.configuration.debt_ceiling != 0 {
+        println!(
+            "  isolation mode debt {} of {} ({:.2}% of the debt ceiling)",
+            format_units(health.isolation_mode_total_debt, 2),
+            format_units(U256::from(health.configuration.debt_ceiling), 2),
+            health.isolation_mode_total_debt.as_u128() as f64 * 100.0
+                / health.configuration.debt_ceiling as f64
+        );
+    }
+    for issue in issues {
+        println!("  ❌ {}", issue);
+    }
 }
 
-async fn handle_user_validation_generic(user_address: &str, exit_on_error: bool, scaled: bool) {
-    let result = if scaled {
-        match validate_user_all_positions_scaled(user_address).await {
-            Ok(result_inner) => result_inner,
+pub async fn handle_validate_account_data(flags: Vec<Flag>) {
+    let user_address = extract_optional_value_from_flags(&flags, FlagType::ValidateAccountData);
+    let single_user = user_address.is_some();
+    let users: BoxStream<'static, Result<UserPositionDocument, String>> = match user_address {
+        Some(user_address) => match get_user_position(&user_address).await {
+            Ok(user) => stream::iter([Ok(user)]).boxed(),
             Err(e) => {
-                eprintln!("Error validating user {}: {}", user_address, e);
-                if exit_on_error {
-                    std::process::exit(1);
-                }
-                return;
+                eprintln!("Error fetching user position: {}", e);
+                std::process::exit(1);
             }
-        }
-    } else {
-        match validate_user_all_positions(user_address).await {
-            Ok(result_inner) => result_inner,
+        },
+        None => match stream_user_positions().await {
+            Ok(cursor) => cursor
+                .map(|user_result| user_result.map_err(|e| e.to_string()))
+                .boxed(),
             Err(e) => {
-                eprintln!("Error validating user {}: {}", user_address, e);
-                if exit_on_error {
-                    std::process::exit(1);
-                }
-                return;
+                eprintln!("Error fetching user positions: {}", e);
+                std::process::exit(1);
             }
+        },
+    };
+    let reserves = match priced_reserves().await {
+        Ok(reserves) => reserves,
+        Err(e) => {
+            eprintln!("Error fetching reserve prices: {}", e);
+            std::process::exit(1);
         }
     };
-    println!(
-        "✅ User {}: {} positions validated",
-        result.user_address,
-        result.positions.len()
-    );
-    for position in &result.positions {
-        if let Some(error) = &position.error {
+    let stable_debt = match stable_debt_by_user().await {
+        Ok(stable_debt) => stable_debt,
+        Err(e) => {
+            eprintln!("Error fetching stable debt balances: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // users are checked as they are read from the cursor
+    let mut results = users
+        .map(|user_result| async move {
+            let user = user_result?;
+            let on_chain = get_user_account_data(&user.userAddress).await;
+            let configuration = get_user_configuration(&user.userAddress).await;
+            let emode = get_user_emode(&user.userAddress).await;
+            Ok::<_, String>((
+                user,
+                on_chain.map_err(|e| e.to_string()),
+                configuration.map_err(|e| e.to_string()),
+                emode.map_err(|e| e.to_string()),
+            ))
+        })
+        .buffer_unordered(get_rpc_settings().max_concurrent_requests);
+
+    let no_stable_debt = HashMap::new();
+    let mut users_read = 0;
+    let mut consistent = 0;
+    let mut compared = 0;
+    while let Some(result) = results.next().await {
+        let (user, on_chain, configuration, emode) = match result {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                eprintln!("Error reading user position: {}", e);
+                std::process::exit(1);
+            }
+        };
+        users_read += 1;
+        let (on_chain, configuration, emode) = match (on_chain, configuration, emode) {
+            (Ok(on_chain), Ok(configuration), Ok(emode)) => (on_chain, configuration, emode),
+            (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+                println!(
+                    "❌ {}: could not fetch the account data: {}",
+                    user.userAddress, e
+                );
+                continue;
+            }
+        };
+        compared += 1;
+        let user_stable_debt = stable_debt
+            .get(&user.userAddress.to_lowercase())
+            .unwrap_or(&no_stable_debt);
+        let (totals, issues) =
+            recompute_account_totals(&user, &reserves, user_stable_debt, configuration);
+        let collateral_bps =
+            relative_difference_bps(totals.collateral_base, on_chain.total_collateral_base);
+        let debt_bps = relative_difference_bps(totals.debt_base, on_chain.total_debt_base);
+        let matches = collateral_bps <= ACCOUNT_DATA_TOLERANCE_BPS
+            && debt_bps <= ACCOUNT_DATA_TOLERANCE_BPS
+            && issues.is_empty();
+        if matches {
+            consistent += 1;
+            if !single_user {
+                continue;
+            }
+        }
+        println!("{} {}", if matches { "✅" } else { "❌" }, user.userAddress);
+        println!("  on-chain: {}", on_chain);
+        let base_decimals = unit_decimals(on_chain.base_currency_unit);
+        // getUserAccountData applies the e-mode category's liquidation threshold, which isn't
+        // modelled here, so only the collateral and the debt are comparable
+        if emode != 0 {
             println!(
-                "  ❌ Reserve {}: ERROR - {}",
-                position.reserve_address, error
+                "  recomputed: collateral {}, debt {} (e-mode category {}: liquidation threshold and health factor not modelled)",
+                format_units(totals.collateral_base, base_decimals),
+                format_units(totals.debt_base, base_decimals),
+                emode
             );
         } else {
-            println!("  📊 Reserve {}:", position.reserve_address);
-            println!(
-                "  Supply - DB: {}\n  On-Chain:    {}\n  Diff: {}, %: {:.6}%",
-                position.supply.database_amount,
-                position.supply.on_chain_amount,
-                position.supply.difference,
-                position.supply.percentage
-            );
             println!(
-                "  Supply - DB: {}\n  On-Chain:    {}\n  Diff: {}, %: {:.6}%",
-                position.borrow.database_amount,
-                position.borrow.on_chain_amount,
-                position.borrow.difference,
-                position.borrow.percentage
+                "  recomputed: collateral {}, debt {}, liquidation threshold {:.2}%, health factor {:.4}",
+                format_units(totals.collateral_base, base_decimals),
+                format_units(totals.debt_base, base_decimals),
+                totals.liquidation_threshold.low_u64() as f64 / 100.0,
+                AccountData {
+                    health_factor: totals.health_factor,
+                    ..Default::default()
+                }
+                .health_factor_value()
             );
         }
+        for issue in &issues {
+            println!("  ❌ {}", issue);
+        }
     }
+    println!(
+        "\n📋 {} of {} compared users match getUserAccountData within {} bps ({} could not be fetched)",
+        consistent,
+        compared,
+        ACCOUNT_DATA_TOLERANCE_BPS,
+        users_read - compared
+    );
 }
 
-pub async fn handle_validate_all() {
-    println!("Validating everything...");
+pub async fn handle_health_factor_below(flags: Vec<Flag>) {
+    let input = extract_value_from_flags_or_exit(
+        flags.clone(),
+        FlagType::HealthFactorBelow,
+        "Error: --health-factor-below requires a health factor to be specified.",
+    );
+    let threshold = match input.parse::<f64>() {
+        Ok(threshold) if threshold > 0.0 => threshold,
+        _ => {
+            eprintln!(
+                "Error: invalid health factor '{}', expected a positive number such as 1.1",
+                input
+            );
+            std::process::exit(1);
+        }
+    };
+    let users = match stream_user_addresses().await {
+        Ok(users) => users,
+        Err(e) => {
+            eprintln!("Error fetching users: {}", e);
+            std::process::exit(1);
+        }
+    };
 
-    // Validate all reserves
-    println!("\n🔍 Validating all reserves...");
-    handle_validate_token_all().await;
-    // Validate all users
-    println!("\n🔍 Validating all users...");
-    handle_validate_users_all().await;
+    // only the users below the threshold are kept
+    let mut results = users
+        .map(|user_result| async move {
+            let user = user_result.map_err(|e| e.to_string())?;
+            let account_data = get_user_account_data(&user.userAddress)
+                .await
+                .map_err(|e| e.to_string());
+            Ok::<_, String>((user.userAddress, account_data))
+        })
+        .buffer_unordered(get_rpc_settings().max_concurrent_requests);
 
-    println!("\n🎉 Complete validation finished!");
-}
+    let mut checked = 0;
+    let mut at_risk: Vec<(String, AccountData)> = vec![];
+    while let Some(result) = results.next().await {
+        let (user, result) = match result {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                eprintln!("Error reading user: {}", e);
+                std::process::exit(1);
+            }
+        };
+        checked += 1;
+        match result {
+            Ok(account_data) if account_data.health_factor_value() < threshold => {
+                at_risk.push((user, account_data))
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Error fetching the account data of {}: {}", user, e),
+        }
+    }
+    at_risk.sort_by(|a, b| {
+        a.1.health_factor_value()
+            .total_cmp(&b.1.health_factor_value())
+    });
 
-pub async fn handle_validate_all_scaled() {
-    println!("Validating everything...");
+    for (user, account_data) in &at_risk {
+        println!(
+            "{} {}: {}",
+            if account_data.health_factor_value() < 1.0 {
+                "🔴"
+            } else {
+                "🟠"
+            },
+            user,
+            account_data
+        );
+    }
+    println!(
+        "\n📋 {} of {} users have a health factor below {}",
+        at_risk.len(),
+        checked,
+        threshold
+    );
+}
 
-    // Validate all reserves
-    println!("\n🔍 Validating all reserves...");
-    handle_validate_token_all_scaled().await;
-    // Validate all users
-    println!("\n🔍 Validating all users...");
-    handle_validate_users_all_scaled().await;
+// Oracle prices of the given reserves, at `block_number` or the latest block
+async fn price_book(
+    reserve_addresses: &[String],
+    block_number: Option<u64>,
+) -> Result<PriceBook, Box<dyn std::error::Error>> {
+    // amounts are printed in USD, which an oracle with another base currency doesn't price in
+    let (base_currency, base_currency_unit) = get_base_currency().await?;
+    if base_currency != Address::ZERO {
+        return Err(format!("the oracle base currency is {:#x}, not USD", base_currency).into());
+    }
+    let prices = match block_number {
+        Some(block_number) => get_asset_prices_at_block(reserve_addresses, block_number).await?,
+        None => get_asset_prices(reserve_addresses).await?,
+    };
+    let decimals = join_all(
+        reserve_addresses
+            .iter()
+            .map(|address| get_decimals(address)),
+    )
+    .await;
 
-    println!("\n🎉 Complete validation finished!");
+    let mut book = PriceBook::new(block_number, base_currency_unit);
+    for ((address, price), decimals) in reserve_addresses.iter().zip(prices).zip(decimals) {
+        book.insert(address, price, decimals?);
+    }
+    Ok(book)
 }
 
-// New handlers for the additional CLI features
-
-pub async fn handle_get_all_users() {
-    let users = find_all_user_addresses().await;
+// Latest prices of every reserve in the database
+async fn all_reserve_prices() -> PriceBook {
+    price_book_or_warn(&find_all_reserve_addresses().await).await
+}
 
-    if users.is_empty() {
-        println!("No users found.");
-    } else {
-        println!("All user addresses:");
-        for user in &users {
-            println!("{}", user);
+// Latest prices of the given reserves, empty when the oracle can't be read so that
+// validations still run without USD values
+async fn price_book_or_warn(reserve_addresses: &[String]) -> PriceBook {
+    match price_book(reserve_addresses, None).await {
+        Ok(book) => book,
+        Err(e) => {
+            eprintln!(
+                "⚠️  Could not read oracle prices, USD values are left out: {}",
+                e
+            );
+            PriceBook::default()
         }
-        println!("Total users: {}", users.len());
     }
 }
 
-pub async fn handle_get_all_reserves() {
-    let reserves = match find_all_reserves().await {
-        Ok(reserves) => reserves,
+async fn print_usd_difference(reserve_address: &str, difference: u128) {
+    let prices = price_book_or_warn(&[reserve_address.to_string()]).await;
+    if let Some(usd) = prices.usd_value(reserve_address, difference) {
+        println!(
+            "  Difference (USD): ${:.2}{}",
+            usd,
+            if usd > usd_tolerance() {
+                ""
+            } else {
+                " (within USD_TOLERANCE)"
+            }
+        );
+    }
+}
+
+// Streams every user_positions document into `f`, exiting on a database error, and returns
+// the number of documents
+async fn for_each_user_position(mut f: impl FnMut(&UserPositionDocument)) -> usize {
+    let mut users = match stream_user_positions().await {
+        Ok(users) => users,
         Err(e) => {
-            eprintln!("Error fetching reserves: {}", e);
+            eprintln!("Error fetching user positions: {}", e);
             std::process::exit(1);
         }
     };
-
-    if reserves.is_empty() {
-        println!("No reserves found.");
-    } else {
-        println!("All reserve tokens:");
-        for reserve in &reserves {
-            println!(
-                "Address: {}, Symbol: {}",
-                reserve.reserveAddress, reserve.symbol
-            );
+    let mut count = 0;
+    while let Some(user_result) = users.next().await {
+        match user_result {
+            Ok(user) => {
+                f(&user);
+                count += 1;
+            }
+            Err(e) => {
+                eprintln!("Error reading user position: {}", e);
+                std::process::exit(1);
+            }
         }
-        println!("Total reserves: {}", reserves.len());
     }
+    count
 }
 
-pub async fn handle_get_all_a_tokens() {
-    let reserves = match find_all_reserves().await {
-        Ok(reserves) => reserves,
-        Err(e) => {
-            eprintln!("Error fetching reserves: {}", e);
-            std::process::exit(1);
-        }
-    };
+// Pricing data of every reserve, keyed by lowercase reserve address
+async fn priced_reserves() -> Result<HashMap<String, PricedReserve>, Box<dyn std::error::Error>> {
+    let reserves = find_all_reserves().await?;
+    let addresses: Vec<String> = reserves
+        .iter()
+        .map(|reserve| reserve.reserveAddress.clone())
+        .collect();
+    let prices = get_asset_prices(&addresses).await?;
 
-    if reserves.is_empty() {
-        println!("No aTokens found.");
-    } else {
-        println!("All aToken addresses:");
-        for reserve in &reserves {
-            println!(
-                "Address: {}, Symbol: {}",
-                reserve.aTokenAddress, reserve.symbol
-            );
-        }
-        println!("Total aTokens: {}", reserves.len());
+    let mut priced = HashMap::new();
+    for (address, price) in addresses.iter().zip(prices) {
+        let reserve_data = get_reserve_data(address).await?;
+        let configuration = reserve_data.decoded_configuration();
+        let (liquidity_index, variable_borrow_index) = get_normalized_indexes(address).await?;
+        priced.insert(
+            address.to_lowercase(),
+            PricedReserve {
+                id: reserve_data.id,
+                decimals: configuration.decimals,
+                liquidation_threshold: configuration.liquidation_threshold,
+                liquidity_index,
+                variable_borrow_index,
+                price,
+            },
+        );
     }
+    Ok(priced)
 }
 
-pub async fn handle_get_all_debt_tokens() {
-    let reserves = match find_all_reserves().await {
-        Ok(reserves) => reserves,
-        Err(e) => {
-            eprintln!("Error fetching reserves: {}", e);
-            std::process::exit(1);
+// On-chain stable debt of the users with indexed stable rate borrows, keyed by lowercase user
+// address and then by lowercase reserve address
+async fn stable_debt_by_user()
+-> Result<HashMap<String, HashMap<String, U256>>, Box<dyn std::error::Error>> {
+    let mut stable_debt: HashMap<String, HashMap<String, U256>> = HashMap::new();
+    for reserve in find_all_reserves().await? {
+        let borrowers = stable_borrowers(&find_stable_borrows(&reserve.reserveAddress).await?);
+        if borrowers.is_empty() {
+            continue;
         }
-    };
-
-    if reserves.is_empty() {
-        println!("No debt tokens found.");
-    } else {
-        println!("All debt token addresses:");
-        for reserve in &reserves {
-            println!(
-                "Address: {}, Symbol: {}",
-                reserve.variableDebtTokenAddress, reserve.symbol
-            );
+        let stable_debt_token = get_reserve_data(&reserve.reserveAddress)
+            .await?
+            .stableDebtTokenAddress;
+        if stable_debt_token == Address::ZERO {
+            continue;
+        }
+        let (holders, failed) = stable_debt_balances(
+            &format!("{:#x}", stable_debt_token),
+            borrowers.into_keys().collect(),
+        )
+        .await;
+        if let Some((user, e)) = failed.first() {
+            return Err(format!(
+                "stable debt of {} in {} ({}): {}",
+                user, reserve.symbol, reserve.reserveAddress, e
+            )
+            .into());
+        }
+        for (user, balance) in holders {
+            stable_debt
+                .entry(user.to_lowercase())
+                .or_default()
+                .insert(reserve.reserveAddress.to_lowercase(), U256::from(balance));
         }
-        println!("Total debt tokens: {}", reserves.len());
     }
+    Ok(stable_debt)
 }
 
-pub async fn handle_get_token_events(flags: Vec<Flag>) {
-    let token_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::GetTokenEvents,
-        "Error: --get-token-events requires a token address to be specified.",
-    );
+// Non-zero stable debt balances of the candidates, and the candidates whose balance couldn't be
+// fetched
+async fn stable_debt_balances(
+    token: &str,
+    candidates: BTreeSet<String>,
+) -> (BTreeMap<String, u128>, Vec<(String, String)>) {
+    let results: Vec<(String, Result<u128, String>)> = stream::iter(candidates)
+        .map(|user| async move {
+            let balance = get_balance_of(token, &user)
+                .await
+                .map_err(|e| e.to_string());
+            (user, balance)
+        })
+        .buffer_unordered(get_rpc_settings().max_concurrent_requests)
+        .collect()
+        .await;
 
-    let events = match find_token_events(&token_address).await {
-        Ok(events) => events,
-        Err(e) => {
-            eprintln!("Error fetching token events: {}", e);
-            std::process::exit(1);
+    let mut holders = BTreeMap::new();
+    let mut failed = vec![];
+    for (user, result) in results {
+        match result {
+            Ok(0) => {}
+            Ok(balance) => {
+                holders.insert(user, balance);
+            }
+            Err(e) => failed.push((user, e)),
         }
-    };
-
-    if events.is_empty() {
-        println!("No events found for token: {}", token_address);
-    } else {
-        handle_money_market_event_output(events);
     }
+    (holders, failed)
 }
 
-pub async fn handle_get_user_events(flags: Vec<Flag>) {
-    let user_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::GetUserEvents,
-        "Error: --get-user-events requires a user address to be specified.",
-    );
+// Candidates with a non-zero on-chain scaled balance of `token`, and the candidates whose
+// balance couldn't be fetched
+async fn on_chain_holders(
+    token: &str,
+    candidates: BTreeSet<String>,
+) -> (BTreeSet<String>, Vec<(String, String)>) {
+    let results: Vec<(String, Result<u128, String>)> = stream::iter(candidates)
+        .map(|user| async move {
+            let balance = get_scaled_balance_of(token, &user)
+                .await
+                .map_err(|e| e.to_string());
+            (user, balance)
+        })
+        .buffer_unordered(get_rpc_settings().max_concurrent_requests)
+        .collect()
+        .await;
 
-    let events = match find_user_events(&user_address).await {
-        Ok(events) => events,
-        Err(e) => {
-            eprintln!("Error fetching user events: {}", e);
-            std::process::exit(1);
+    let mut holders = BTreeSet::new();
+    let mut failed = vec![];
+    for (user, result) in results {
+        match result {
+            Ok(0) => {}
+            Ok(_) => {
+                holders.insert(user);
+            }
+            Err(e) => failed.push((user, e)),
         }
-    };
-
-    if events.is_empty() {
-        println!("No events found for user: {}", user_address);
-    } else {
-        handle_money_market_event_output(events);
     }
+    (holders, failed)
 }
 
-fn handle_money_market_event_output(event_vector: Vec<MoneyMarketEventDocument>) {
-    for event in event_vector {
-        match event {
-            MoneyMarketEventDocument::ATokenBalanceTransfer(doc) => {
-                println!("AToken Balance Transfer Event:");
-                println!("  Doc: {:?}", doc);
-            }
-            MoneyMarketEventDocument::ATokenTransfer(doc) => {
-                println!("AToken Transfer Event:");
-                println!("  Doc: {:?}", doc);
-            }
-            MoneyMarketEventDocument::ATokenBurn(doc) => {
-                println!("AToken Burn Event:");
-                println!("  Doc: {:?}", doc);
-            }
-            MoneyMarketEventDocument::ATokenMint(doc) => {
-                println!("AToken Mint Event:");
-                println!("  Doc: {:?}", doc);
-            }
-            MoneyMarketEventDocument::Borrow(doc) => {
-                println!("Borrow Event:");
-                println!("  Doc: {:?}", doc);
-            }
-            MoneyMarketEventDocument::DebtTokenBurn(doc) => {
-                println!("Debt Token Burn Event:");
-                println!("  Doc: {:?}", doc);
-            }
-            MoneyMarketEventDocument::DebtTokenMint(doc) => {
-                println!("Debt Token Mint Event:");
-                println!("  Doc: {:?}", doc);
-            }
-            MoneyMarketEventDocument::Repay(doc) => {
-                println!("Repay Event:");
-                println!("  Doc: {:?}", doc);
+pub async fn handle_event_anomalies() {
+    let collections = Collections::new();
+    let chain_id = configured_chain_id();
+    let mut total_anomalies = 0;
+
+    for collection in [collections.money_market_events, collections.intent_events] {
+        println!("\n🔎 Scanning {}...", collection);
+
+        let duplicates = match find_duplicate_event_keys(collection).await {
+            Ok(duplicates) => duplicates,
+            Err(e) => {
+                eprintln!("Error finding duplicate events in {}: {}", collection, e);
+                std::process::exit(1);
             }
-            MoneyMarketEventDocument::ReserveDataUpdated(doc) => {
-                println!("Reserve Data Updated Event:");
-                println!("  Doc: {:?}", doc);
+        };
+        println!("  Duplicate (txHash, logIndex) pairs: {}", duplicates.len());
+        for (tx_hash, log_index, ids) in &duplicates {
+            println!("    {} #{}: {}", tx_hash, log_index, ids.join(", "));
+        }
+
+        let mismatches = match find_chain_id_mismatches(collection, chain_id).await {
+            Ok(mismatches) => mismatches,
+            Err(e) => {
+                eprintln!("Error finding chain id mismatches in {}: {}", collection, e);
+                std::process::exit(1);
             }
-            MoneyMarketEventDocument::Supply(doc) => {
-                println!("Supply Event:");
-                println!("  Doc: {:?}", doc);
+        };
+        println!(
+            "  Documents with a chainId other than {}: {}",
+            chain_id,
+            mismatches.len()
+        );
+        for (id, stored_chain_id) in &mismatches {
+            println!("    {} (chainId {})", id, stored_chain_id);
+        }
+
+        let split_transactions = match find_transactions_in_several_blocks(collection).await {
+            Ok(transactions) => transactions,
+            Err(e) => {
+                eprintln!("Error grouping transactions in {}: {}", collection, e);
+                std::process::exit(1);
             }
-            MoneyMarketEventDocument::Withdraw(doc) => {
-                println!("Withdraw Event:");
-                println!("  Doc: {:?}", doc);
+        };
+        println!(
+            "  Transactions stored in more than one block: {}",
+            split_transactions.len()
+        );
+        for (tx_hash, blocks, ids) in &split_transactions {
+            println!(
+                "    {} in blocks {}: {}",
+                tx_hash,
+                blocks.join(", "),
+                ids.join(", ")
+            );
+        }
+
+        let mut cursor = match stream_event_ordering(collection).await {
+            Ok(cursor) => cursor,
+            Err(e) => {
+                eprintln!("Error streaming {}: {}", collection, e);
+                std::process::exit(1);
             }
+        };
+        let mut scan = EventOrderingScan::new();
+        while let Some(doc_result) = cursor.next().await {
+            let doc = match doc_result {
+                Ok(doc) => doc,
+                Err(e) => {
+                    eprintln!("Error reading document from {}: {}", collection, e);
+                    continue;
+                }
+            };
+            scan.push(EventOrderingRecord {
+                id: doc.id.to_hex(),
+                tx_hash: doc.txHash,
+                block_number: doc.blockNumber,
+                log_index: doc.logIndex,
+                created_at: doc
+                    .createdAt
+                    .map(|created_at| created_at.timestamp_millis()),
+                version: doc.version.unwrap_or_default(),
+            });
+        }
+        scan.finish();
+
+        println!(
+            "  logIndex claimed by more than one transaction of a block: {}",
+            scan.log_index_conflicts.len()
+        );
+        for (block_number, log_index, ids) in &scan.log_index_conflicts {
+            println!(
+                "    block {} #{}: {}",
+                block_number,
+                log_index,
+                ids.join(", ")
+            );
+        }
+        println!(
+            "  Documents created before a document of an earlier block: {}",
+            scan.created_out_of_order.len()
+        );
+        for id in &scan.created_out_of_order {
+            println!("    {}", id);
+        }
+        println!(
+            "  Documents rewritten after ingestion (__v > 0): {}",
+            scan.rewritten.len()
+        );
+        for id in &scan.rewritten {
+            println!("    {}", id);
         }
+        // transactions also emit logs that are not indexed (e.g. underlying ERC-20
+        // transfers), so gaps are listed for review rather than counted as anomalies
+        println!(
+            "  Transactions with logIndex gaps (informational): {}",
+            scan.log_index_gaps.len()
+        );
+        for (tx_hash, missing) in &scan.log_index_gaps {
+            let missing: Vec<String> = missing.iter().map(|index| index.to_string()).collect();
+            println!("    {} missing {}", tx_hash, missing.join(", "));
+        }
+
+        total_anomalies += duplicates.len()
+            + mismatches.len()
+            + split_transactions.len()
+            + scan.log_index_conflicts.len()
+            + scan.created_out_of_order.len()
+            + scan.rewritten.len();
+    }
+
+    if total_anomalies == 0 {
+        println!("\n✅ No event anomalies found");
+    } else {
+        println!("\n❌ {} event anomalies found", total_anomalies);
     }
 }
 
-async fn handle_validate_reserve_indexes_generic(reserve_address: String) {
-    println!("Validating reserve indexes for: {}", reserve_address);
-    // Get database values
-    let reserve_data =
-        match find_reserve_for_token(&reserve_address, ReserveTokenField::Reserve).await {
-            Ok(Some(data)) => data,
-            Ok(None) => {
-                eprintln!("Reserve not found in database: {}", reserve_address);
+pub async fn handle_block_at_time(flags: Vec<Flag>) {
+    if let Some(at_time) = extract_optional_value_from_flags(&flags, FlagType::AtTime) {
+        let timestamp = parse_time_argument(&at_time).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+        let block = resolve_block_at_time_or_exit(timestamp).await;
+        println!("--at-time {}: {}", at_time, format_cached_block(&block));
+    }
+    if let Some(since) = extract_optional_value_from_flags(&flags, FlagType::Since) {
+        let range = resolve_block_range_or_exit(&[Flag::Since(since.clone())]).await;
+        let from = range.from.unwrap_or_default();
+        // the time is after the head when the first block at or after it does not exist yet
+        let latest = match get_last_block().await {
+            Ok(latest) => latest,
+            Err(e) => {
+                eprintln!("Error fetching last block: {}", e);
                 std::process::exit(1);
             }
+        };
+        if from > latest {
+            eprintln!(
+                "Error: --since {} is later than the latest block {}, no block starts the range yet",
+                since, latest
+            );
+            std::process::exit(1);
+        }
+        let block = match get_block(from).await {
+            Ok(block) => block,
             Err(e) => {
-                eprintln!("Error fetching reserve data: {}", e);
+                eprintln!("Error fetching block {}: {}", from, e);
                 std::process::exit(1);
             }
         };
+        println!("--since {}: {}", since, format_cached_block(&block));
+    }
+}
 
-    println!("Reserve: {}", reserve_address);
-    println!("Token: {}", reserve_data.symbol);
-    // Get on-chain values
-    let on_chain_liquidity_index = match get_atoken_liquidity_index(&reserve_address).await {
-        Ok(index) => index,
-        Err(e) => {
-            eprintln!("Error fetching on-chain liquidity index: {}", e);
-            std::process::exit(1);
-        }
-    };
+fn format_cached_block(block: &CachedBlock) -> String {
+    let time = DateTime::from_millis(block.timestamp as i64 * 1000)
+        .try_to_rfc3339_string()
+        .unwrap_or_default();
+    format!(
+        "block {} (timestamp {}, {}, hash {})",
+        block.number, block.timestamp, time, block.hash
+    )
+}
 
-    let on_chain_variable_borrow_index = match get_variable_borrow_index(&reserve_address).await {
-        Ok(index) => index,
+async fn resolve_block_at_time_or_exit(timestamp: u64) -> CachedBlock {
+    match find_block_at_time(timestamp).await {
+        Ok(block) => block,
         Err(e) => {
-            eprintln!("Error fetching on-chain variable borrow index: {}", e);
+            eprintln!("Error resolving the block at time {}: {}", timestamp, e);
             std::process::exit(1);
         }
-    };
-
-    // Convert database values to u128 for comparison
-    let db_liquidity_index = reserve_data
-        .liquidityIndex
-        .to_string()
-        .parse::<u128>()
-        .unwrap_or(0);
-    let db_variable_borrow_index = reserve_data
-        .variableBorrowIndex
-        .to_string()
-        .parse::<u128>()
-        .unwrap_or(0);
+    }
+}
 
-    println!("Liquidity Index:");
-    println!("  Database: {}", db_liquidity_index);
-    println!("  On-Chain: {}", on_chain_liquidity_index);
-    println!(
-        "  Difference: {}",
-        if db_liquidity_index > on_chain_liquidity_index {
-            db_liquidity_index - on_chain_liquidity_index
-        } else {
-            on_chain_liquidity_index - db_liquidity_index
-        }
-    );
+// Resolves --since to the first block at or after that time and --at-time to the
+// last block at or before it
+async fn resolve_block_range_or_exit(flags: &[Flag]) -> BlockRange {
+    let mut range = BlockRange::default();
 
-    println!("Variable Borrow Index:");
-    println!("  Database: {}", db_variable_borrow_index);
-    println!("  On-Chain: {}", on_chain_variable_borrow_index);
-    println!(
-        "  Difference: {}",
-        if db_variable_borrow_index > on_chain_variable_borrow_index {
-            db_variable_borrow_index - on_chain_variable_borrow_index
+    if let Some(since) = extract_optional_value_from_flags(flags, FlagType::Since) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let timestamp = parse_since_argument(&since, now).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+        let block = resolve_block_at_time_or_exit(timestamp).await;
+        range.from = Some(if block.timestamp < timestamp {
+            block.number + 1
         } else {
-            on_chain_variable_borrow_index - db_variable_borrow_index
-        }
-    );
-}
+            block.number
+        });
+    }
 
-pub async fn handle_validate_reserve_indexes(flags: Vec<Flag>) {
-    let reserve_address = extract_value_from_flags_or_exit(
-        flags.clone(),
-        FlagType::ValidateReserveIndexes,
-        "Error: --validate-reserve-indexes requires a reserve address to be specified.",
-    );
+    if let Some(at_time) = extract_optional_value_from_flags(flags, FlagType::AtTime) {
+        let timestamp = parse_time_argument(&at_time).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+        range.to = Some(resolve_block_at_time_or_exit(timestamp).await.number);
+    }
 
-    handle_validate_reserve_indexes_generic(reserve_address).await;
+    if !range.is_unbounded() {
+        println!("Restricting to {}", range);
+    }
+    range
 }
 
-pub async fn handle_validate_all_reserve_indexes() {
-    println!("Validating indexes for all reserves...");
-
-    let reserves = find_all_reserve_addresses().await;
-
-    if reserves.is_empty() {
-        println!("No reserves found.");
-        return;
+// The reserve passed to a flag whose argument is optional, every reserve when it is omitted
+async fn selected_reserves_or_exit(
+    flags: &[Flag],
+    flag_type: FlagType,
+) -> Vec<ReserveTokenDocument> {
+    match extract_optional_value_from_flags(flags, flag_type) {
+        Some(input) => vec![resolve_reserve_or_exit(&input).await.0],
+        None => match find_all_reserves().await {
+            Ok(reserves) => reserves,
+            Err(e) => {
+                eprintln!("Error fetching reserve tokens: {}", e);
+                std::process::exit(1);
+            }
+        },
     }
+}
 
-    println!("Found {} reserves to validate", reserves.len());
-
-    for reserve in reserves {
-        handle_validate_reserve_indexes_generic(reserve).await;
+async fn resolve_reserve_or_exit(input: &str) -> (ReserveTokenDocument, ReserveTokenField) {
+    match resolve_reserve(input).await {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
     }
+}
 
-    println!("\n🎉 Reserve index validation complete!");
+// Extracts the --reserve-token value and resolves it (symbol, reserve, aToken or
+// debt token address) to the reserve address stored in the database
+async fn extract_reserve_address_or_exit(flags: &[Flag], error_message: &str) -> String {
+    let input =
+        extract_value_from_flags_or_exit(flags.to_vec(), FlagType::ReserveToken, error_message);
+    let (reserve, _) = resolve_reserve_or_exit(&input).await;
+    reserve.reserveAddress
 }
@@ -1,10 +1,12 @@
 use crate::constants::RAY;
-use crate::db::{find_reserve_for_token, get_user_position};
+use crate::db::{find_all_reserves, find_reserve_for_token, get_user_position};
 use crate::evm::{get_atoken_liquidity_index, get_variable_borrow_index};
-use crate::models::UserAssetPositionDocument;
-use crate::structs::ReserveTokenField;
-use crate::functions::{ray_div, ray_mul};
+use crate::models::{MoneyMarketEventDocument, ReserveTokenDocument, UserAssetPositionDocument};
+use crate::structs::{DbAddress, ReserveBalances, ReserveTokenField, ScaledDelta, TimelineEntry};
+use crate::functions::{normalize_address, ray_div, ray_mul, scaled_balance_delta};
+use mongodb::bson::Decimal128;
 use primitive_types::U256;
+use std::collections::{BTreeMap, HashMap};
 // use mongodb::bson::Decimal128;
 
 // fn decimal128_to_u64_blocknumber(d: Decimal128) -> u64 {
@@ -19,16 +21,94 @@ pub async fn find_user_scaled_position(
     reserve_address: &str,
 ) -> Result<UserAssetPositionDocument, Box<dyn std::error::Error>> {
     let reserve_data = get_user_position(user_address).await?;
+    let reserve_address: DbAddress = reserve_address.parse()?;
 
     // Find the position for the specific reserve
     let position = reserve_data
         .positions
         .iter()
-        .find(|position| position.reserveAddress == reserve_address)
+        .find(|position| reserve_address.matches(&position.reserveAddress))
         .ok_or("No position found for the specified reserve")?;
     Ok(position.clone())
 }
 
+/// Resolves user input to a reserve record. The input can be a reserve symbol
+/// (case-insensitive) or a reserve, aToken or debt token address in any casing.
+/// Returns the reserve together with the field the input matched (symbols match
+/// as `ReserveTokenField::Reserve`).
+pub async fn resolve_reserve(
+    input: &str,
+) -> Result<(ReserveTokenDocument, ReserveTokenField), Box<dyn std::error::Error>> {
+    let reserves = find_all_reserves().await?;
+    select_reserve(input, match_reserves(reserves, input))
+}
+
+// The single match, or an error listing every candidate when the input is ambiguous
+fn select_reserve(
+    input: &str,
+    mut matches: Vec<(ReserveTokenDocument, ReserveTokenField)>,
+) -> Result<(ReserveTokenDocument, ReserveTokenField), Box<dyn std::error::Error>> {
+    match matches.len() {
+        0 => Err(format!(
+            "No reserve found for '{}' (expected a symbol, reserve, aToken or debt token address)",
+            input
+        )
+        .into()),
+        1 => Ok(matches.remove(0)),
+        _ => {
+            let candidates: Vec<String> = matches
+                .iter()
+                .map(|(reserve, field)| {
+                    format!(
+                        "{} [{:?}] (reserve {}, aToken {}, debt token {})",
+                        reserve.symbol,
+                        field,
+                        reserve.reserveAddress,
+                        reserve.aTokenAddress,
+                        reserve.variableDebtTokenAddress
+                    )
+                })
+                .collect();
+            Err(format!(
+                "'{}' is ambiguous, candidates:\n  {}",
+                input,
+                candidates.join("\n  ")
+            )
+            .into())
+        }
+    }
+}
+
+fn match_reserves(
+    reserves: Vec<ReserveTokenDocument>,
+    input: &str,
+) -> Vec<(ReserveTokenDocument, ReserveTokenField)> {
+    match normalize_address(input) {
+        Some(address) => {
+            let is_same = |stored: &str| normalize_address(stored).as_deref() == Some(&address);
+            reserves
+                .into_iter()
+                .filter_map(|reserve| {
+                    if is_same(&reserve.reserveAddress) {
+                        Some((reserve, ReserveTokenField::Reserve))
+                    } else if is_same(&reserve.aTokenAddress) {
+                        Some((reserve, ReserveTokenField::AToken))
+                    } else if is_same(&reserve.variableDebtTokenAddress) {
+                        Some((reserve, ReserveTokenField::VariableDebtToken))
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        }
+        None => reserves
+            .into_iter()
+            .filter(|reserve| reserve.symbol.eq_ignore_ascii_case(input.trim()))
+            .map(|reserve| (reserve, ReserveTokenField::Reserve))
+            .collect(),
+    }
+}
+
 fn calculate_real_balance(
     scaled_balance: u128,
     liquidity_index: u128,
@@ -45,6 +125,161 @@ fn calculate_real_balance(
         .map_err(|_| "Failed to convert real balance from U256 to u128")?)
 }
 
+/// Replays the events of a user, sorted by (blockNumber, logIndex), and returns the timeline
+/// with the user's balances in the event's reserve after each event, and the final balances
+/// per reserve. `token_reserves` maps lowercase aToken and debt token addresses to their
+/// lowercase reserve address.
+pub fn build_user_timeline(
+    user: &str,
+    events: &[MoneyMarketEventDocument],
+    token_reserves: &HashMap<String, String>,
+) -> (Vec<TimelineEntry>, BTreeMap<String, ReserveBalances>) {
+    let user = normalize_address(user).unwrap_or_else(|| user.to_lowercase());
+    let is_user = |address: &str| normalize_address(address).as_deref() == Some(user.as_str());
+    let amount = |value: &Decimal128| U256::from_dec_str(&value.to_string()).ok();
+
+    let mut balances: BTreeMap<String, ReserveBalances> = BTreeMap::new();
+    let mut timeline: Vec<TimelineEntry> = vec![];
+    for event in events {
+        let token = event.token().to_lowercase();
+        let reserve = token_reserves.get(&token).cloned().unwrap_or(token);
+        let entry = balances.entry(reserve.clone()).or_default();
+        let mut action = event.event_type().to_string();
+
+        let change: Option<BalanceChange> =
+            match event {
+                MoneyMarketEventDocument::ATokenMint(e) if is_user(&e.onBehalfOf) => Some(
+                    scaled_change(false, true, &e.value, &e.balanceIncrease, &e.index),
+                ),
+                MoneyMarketEventDocument::ATokenBurn(e) if is_user(&e.from) => Some(scaled_change(
+                    false,
+                    false,
+                    &e.value,
+                    &e.balanceIncrease,
+                    &e.index,
+                )),
+                MoneyMarketEventDocument::DebtTokenMint(e) if is_user(&e.onBehalfOf) => Some(
+                    scaled_change(true, true, &e.value, &e.balanceIncrease, &e.index),
+                ),
+                MoneyMarketEventDocument::DebtTokenBurn(e) if is_user(&e.from) => Some(
+                    scaled_change(true, false, &e.value, &e.balanceIncrease, &e.index),
+                ),
+                // the value of a BalanceTransfer is already scaled
+                MoneyMarketEventDocument::ATokenBalanceTransfer(e) => {
+                    let scaled = amount(&e.value);
+                    let index = amount(&e.index);
+                    let delta = if is_user(&e.to) && !is_user(&e.from) {
+                        action = "a-token-balance-transfer in".to_string();
+                        Some(scaled.map(ScaledDelta::Increase))
+                    } else if is_user(&e.from) && !is_user(&e.to) {
+                        action = "a-token-balance-transfer out".to_string();
+                        Some(scaled.map(ScaledDelta::Decrease))
+                    } else {
+                        None
+                    };
+                    delta.map(|delta| BalanceChange {
+                        is_debt: false,
+                        delta,
+                        amount: scaled.zip(index).and_then(|(s, i)| ray_mul(s, i).ok()),
+                        index,
+                    })
+                }
+                _ => None,
+            };
+
+        let event_amount = match (event, &change) {
+            (_, Some(change)) => change.amount,
+            (MoneyMarketEventDocument::Supply(e), _) => amount(&e.amount),
+            (MoneyMarketEventDocument::Withdraw(e), _) => amount(&e.amount),
+            (MoneyMarketEventDocument::Borrow(e), _) => amount(&e.amount),
+            (MoneyMarketEventDocument::Repay(e), _) => amount(&e.amount),
+            (MoneyMarketEventDocument::ATokenTransfer(e), _) => amount(&e.value),
+            _ => None,
+        };
+
+        if let Some(change) = change {
+            let (scaled, last_index) = if change.is_debt {
+                (&mut entry.scaled_debt, &mut entry.borrow_index)
+            } else {
+                (&mut entry.scaled_supply, &mut entry.liquidity_index)
+            };
+            match change.delta {
+                Some(ScaledDelta::Increase(delta)) => *scaled = scaled.saturating_add(delta),
+                Some(ScaledDelta::Decrease(delta)) => *scaled = scaled.saturating_sub(delta),
+                None => {}
+            }
+            if change.index.is_some() {
+                *last_index = change.index;
+            }
+        }
+
+        let real = |scaled: U256, index: Option<U256>| index.and_then(|i| ray_mul(scaled, i).ok());
+        timeline.push(TimelineEntry {
+            block_number: event.block_number(),
+            log_index: event.log_index(),
+            tx_hash: event.tx_hash().to_string(),
+            action,
+            reserve,
+            amount: event_amount,
+            balances: *entry,
+            supply: real(entry.scaled_supply, entry.liquidity_index),
+            debt: real(entry.scaled_debt, entry.borrow_index),
+        });
+    }
+    (timeline, balances)
+}
+
+/// Last timeline entry of each block for `reserve`, i.e. the replayed balances at the end of
+/// every block in which the user's position in that reserve changed
+pub fn timeline_checkpoints<'a>(
+    timeline: &'a [TimelineEntry],
+    reserve: &str,
+) -> Vec<&'a TimelineEntry> {
+    let mut checkpoints: Vec<&TimelineEntry> = vec![];
+    for entry in timeline
+        .iter()
+        .filter(|entry| entry.reserve.eq_ignore_ascii_case(reserve))
+    {
+        match checkpoints.last_mut() {
+            Some(last) if last.block_number == entry.block_number => *last = entry,
+            _ => checkpoints.push(entry),
+        }
+    }
+    checkpoints
+}
+
+// Change of a user's scaled balance in one reserve
+struct BalanceChange {
+    is_debt: bool,
+    delta: Option<ScaledDelta>,
+    /// underlying amount moved
+    amount: Option<U256>,
+    index: Option<U256>,
+}
+
+fn scaled_change(
+    is_debt: bool,
+    is_mint: bool,
+    value: &Decimal128,
+    balance_increase: &Decimal128,
+    index: &Decimal128,
+) -> BalanceChange {
+    let parse = |value: &Decimal128| U256::from_dec_str(&value.to_string()).ok();
+    let index = parse(index);
+    let computed = match (parse(value), parse(balance_increase), index) {
+        (Some(value), Some(balance_increase), Some(index)) => {
+            scaled_balance_delta(is_mint, value, balance_increase, index).ok()
+        }
+        _ => None,
+    };
+    BalanceChange {
+        is_debt,
+        delta: computed.map(|(delta, _)| delta),
+        amount: computed.map(|(_, amount)| amount),
+        index,
+    }
+}
+
 pub async fn calculate_user_supply_amount(
     user_address: &str,
     reserve_address: &str,
@@ -160,11 +395,7 @@ pub fn compare_and_report_diff(
     if calculated_amount == on_chain_amount {
         format!("✅ {} amounts match: {}", description, calculated_amount)
     } else {
-        let diff = if calculated_amount > on_chain_amount {
-            calculated_amount - on_chain_amount
-        } else {
-            on_chain_amount - calculated_amount
-        };
+        let diff = calculated_amount.abs_diff(on_chain_amount);
         let percentage = (diff as f64 / on_chain_amount as f64) * 100.0;
 
         // main condition to define an acceptable difference
@@ -192,3 +423,96 @@ pub fn compare_and_report_diff(
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::{
+        a_token_mint_event, reserve_token, supply_event, A_TOKEN_ADDRESS, DEBT_TOKEN_ADDRESS,
+        RESERVE_ADDRESS, USER_ADDRESS,
+    };
+
+    const OTHER_RESERVE_ADDRESS: &str = "0x29219dd400f2bf60e5a23d13be72b486d4038894";
+
+    fn reserves_sharing_a_symbol() -> Vec<ReserveTokenDocument> {
+        let mut other = reserve_token("0", "0");
+        other.reserveAddress = OTHER_RESERVE_ADDRESS.to_string();
+        other.aTokenAddress = "0x0000000000000000000000000000000000000001".to_string();
+        other.variableDebtTokenAddress = "0x0000000000000000000000000000000000000002".to_string();
+        vec![reserve_token("0", "0"), other]
+    }
+
+    #[test]
+    fn reserves_match_any_token_address_in_any_casing() {
+        let checksummed = crate::functions::checksum_address(A_TOKEN_ADDRESS).unwrap();
+        let matches = match_reserves(reserves_sharing_a_symbol(), &checksummed);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].0.reserveAddress, RESERVE_ADDRESS);
+        assert_eq!(matches[0].1, ReserveTokenField::AToken);
+
+        let matches = match_reserves(reserves_sharing_a_symbol(), DEBT_TOKEN_ADDRESS);
+        assert_eq!(matches[0].1, ReserveTokenField::VariableDebtToken);
+
+        let matches = match_reserves(reserves_sharing_a_symbol(), OTHER_RESERVE_ADDRESS);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].1, ReserveTokenField::Reserve);
+    }
+
+    #[test]
+    fn ambiguous_symbol_lists_every_candidate() {
+        let matches = match_reserves(reserves_sharing_a_symbol(), " test ");
+        assert_eq!(matches.len(), 2);
+
+        let error = select_reserve("test", matches).unwrap_err().to_string();
+        assert!(error.contains("'test' is ambiguous"));
+        assert!(error.contains(RESERVE_ADDRESS));
+        assert!(error.contains(OTHER_RESERVE_ADDRESS));
+
+        let error = select_reserve("USDC", match_reserves(reserves_sharing_a_symbol(), "USDC"))
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("No reserve found for 'USDC'"));
+    }
+
+    #[test]
+    fn user_timeline_running_balances() {
+        let token_reserves =
+            HashMap::from([(A_TOKEN_ADDRESS.to_string(), RESERVE_ADDRESS.to_string())]);
+        let events = vec![
+            supply_event("2000000"),
+            a_token_mint_event("2100000", "100000"),
+            a_token_mint_event("100000", "100000"),
+        ];
+
+        let (timeline, balances) = build_user_timeline(USER_ADDRESS, &events, &token_reserves);
+        assert_eq!(timeline.len(), 3);
+        assert_eq!(timeline[0].action, "supply");
+        assert_eq!(timeline[0].amount.unwrap().to_string(), "2000000");
+        assert!(timeline[0].supply.is_none());
+
+        assert_eq!(timeline[1].balances.scaled_supply.to_string(), "1904762");
+        assert_eq!(timeline[1].supply.unwrap().to_string(), "2000000");
+        // an interest-only mint leaves the scaled balance unchanged
+        assert_eq!(timeline[2].balances, timeline[1].balances);
+
+        let reserve = balances.get(RESERVE_ADDRESS).unwrap();
+        assert_eq!(reserve.scaled_supply.to_string(), "1904762");
+        assert!(reserve.scaled_debt.is_zero());
+    }
+
+    #[test]
+    fn timeline_checkpoints_keep_last_state_per_block() {
+        let token_reserves =
+            HashMap::from([(A_TOKEN_ADDRESS.to_string(), RESERVE_ADDRESS.to_string())]);
+        let events = vec![
+            supply_event("2000000"),
+            a_token_mint_event("2100000", "100000"),
+        ];
+        let (timeline, _) = build_user_timeline(USER_ADDRESS, &events, &token_reserves);
+
+        // both events are in the same block, only the state after the last one is kept
+        let checkpoints = timeline_checkpoints(&timeline, RESERVE_ADDRESS);
+        assert_eq!(checkpoints, vec![&timeline[1]]);
+        assert!(timeline_checkpoints(&timeline, DEBT_TOKEN_ADDRESS).is_empty());
+    }
+}
@@ -7,9 +7,17 @@ use sodax_backend_analizer::handlers::{
     handle_validate_token_all_scaled, handle_validate_users_all, handle_validate_users_all_scaled,
     handle_validate_user_all, handle_validate_user_all_scaled, handle_validate_all,
     handle_validate_all_scaled, handle_timestamp_coverage, handle_validate_timestamp,
-    handle_get_all_users, handle_get_all_reserves, handle_get_all_a_tokens, handle_get_all_debt_tokens,
-    handle_get_token_events, handle_get_user_events, handle_validate_reserve_indexes,
-    handle_validate_all_reserve_indexes,
+    handle_get_all_users, handle_get_all_reserves, handle_get_all_a_tokens,
+    handle_get_all_debt_tokens, handle_get_token_events, handle_get_user_events,
+    handle_validate_reserve_indexes, handle_validate_all_reserve_indexes,
+    handle_address_casing_report, handle_event_anomalies, handle_timestamp_backfill,
+    handle_block_at_time, handle_indexing_latency, handle_verify_events,
+    handle_find_missing_events, handle_reorg_check, handle_validate_event_math, handle_get_tx,
+    handle_user_timeline, handle_bisect_user, handle_reconcile_reserves,
+    handle_check_user_lists, handle_find_untracked_holders, handle_index_history,
+    handle_validate_rates, handle_validate_reserve_config,
+    handle_stable_debt, handle_reserve_health, handle_validate_account_data,
+    handle_health_factor_below,
 };
 use sodax_backend_analizer::cli::parse_args;
 use sodax_backend_analizer::structs::Flag;
@@ -95,9 +103,17 @@ async fn main() {
     // if the --timestamp-coverage flag was passed
     } else if flags
         .iter()
-        .any(|f: &Flag| matches!(f, Flag::TimestampCoverage))
+        .any(|f: &Flag| matches!(f, Flag::TimestampCoverage(_)))
     {
-        handle_timestamp_coverage().await;
+        handle_timestamp_coverage(flags).await;
+        std::process::exit(0);
+
+    // if the --timestamp-backfill flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::TimestampBackfill(_)))
+    {
+        handle_timestamp_backfill(flags).await;
         std::process::exit(0);
 
     // if the --validate-timestamps flag was passed
@@ -133,6 +149,120 @@ async fn main() {
         handle_validate_all_reserve_indexes().await;
         std::process::exit(0);
 
+    // if the --address-casing-report flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::AddressCasingReport))
+    {
+        handle_address_casing_report().await;
+        std::process::exit(0);
+
+    // if the --event-anomalies flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::EventAnomalies))
+    {
+        handle_event_anomalies().await;
+        std::process::exit(0);
+
+    // if the --reconcile-reserves flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::ReconcileReserves))
+    {
+        handle_reconcile_reserves().await;
+        std::process::exit(0);
+
+    // if the --check-user-lists flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::CheckUserLists(_)))
+    {
+        handle_check_user_lists(flags).await;
+        std::process::exit(0);
+
+    // if the --find-untracked-holders flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::FindUntrackedHolders(_)))
+    {
+        handle_find_untracked_holders(flags).await;
+        std::process::exit(0);
+
+    // if the --validate-rates flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::ValidateRates(_)))
+    {
+        handle_validate_rates(flags).await;
+        std::process::exit(0);
+
+    // if the --validate-reserve-config flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::ValidateReserveConfig(_)))
+    {
+        handle_validate_reserve_config(flags).await;
+        std::process::exit(0);
+
+    // if the --stable-debt flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::StableDebt(_)))
+    {
+        handle_stable_debt(flags).await;
+        std::process::exit(0);
+
+    // if the --reserve-health flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::ReserveHealth(_)))
+    {
+        handle_reserve_health(flags).await;
+        std::process::exit(0);
+
+    // if the --validate-account-data flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::ValidateAccountData(_)))
+    {
+        handle_validate_account_data(flags).await;
+        std::process::exit(0);
+
+    // if the --health-factor-below flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::HealthFactorBelow(_)))
+    {
+        handle_health_factor_below(flags).await;
+        std::process::exit(0);
+
+    // if the --index-history flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::IndexHistory(_)))
+    {
+        handle_index_history(flags).await;
+        std::process::exit(0);
+
+    // if the --user-timeline flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::UserTimeline(_)))
+    {
+        handle_user_timeline(flags).await;
+        std::process::exit(0);
+
+    // if the --bisect-user flag was passed
+    } else if flags.iter().any(|f: &Flag| matches!(f, Flag::BisectUser(_))) {
+        handle_bisect_user(flags).await;
+        std::process::exit(0);
+
+    // if the --get-tx flag was passed
+    } else if flags.iter().any(|f: &Flag| matches!(f, Flag::GetTx(_))) {
+        handle_get_tx(flags).await;
+        std::process::exit(0);
+
     // if the --get-token-events flag was passed
     } else if flags.iter().any(|f: &Flag| matches!(f, Flag::GetTokenEvents(_))) {
         handle_get_token_events(flags).await;
@@ -143,6 +273,54 @@ async fn main() {
         handle_get_user_events(flags).await;
         std::process::exit(0);
 
+    // if the --indexing-latency flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::IndexingLatency(_)))
+    {
+        handle_indexing_latency(flags).await;
+        std::process::exit(0);
+
+    // if the --verify-events flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::VerifyEvents(_)))
+    {
+        handle_verify_events(flags).await;
+        std::process::exit(0);
+
+    // if the --validate-event-math flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::ValidateEventMath))
+    {
+        handle_validate_event_math(flags).await;
+        std::process::exit(0);
+
+    // if the --reorg-check flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::ReorgCheck(_)))
+    {
+        handle_reorg_check(flags).await;
+        std::process::exit(0);
+
+    // if the --find-missing-events flag was passed
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::FindMissingEvents))
+    {
+        handle_find_missing_events(flags).await;
+        std::process::exit(0);
+
+    // if --at-time or --since was passed on its own
+    } else if flags
+        .iter()
+        .any(|f: &Flag| matches!(f, Flag::AtTime(_) | Flag::Since(_)))
+    {
+        handle_block_at_time(flags).await;
+        std::process::exit(0);
+
     // if the --validate-reserve-indexes flag was passed
     } else if flags.iter().any(|f: &Flag| matches!(f, Flag::ValidateReserveIndexes(_))) {
         handle_validate_reserve_indexes(flags).await;
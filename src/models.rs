@@ -45,6 +45,12 @@ pub struct UserPositionDocument {
     pub version: i32,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct UserAddressProjection {
+    pub userAddress: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[allow(non_snake_case)]
 pub struct ReserveTokenDocument {
@@ -141,6 +147,43 @@ pub struct SolverVolumeTimestampAndBlock {
     pub timestamp: Option<DateTime>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct BlockTimestampPresence {
+    pub blockNumber: u64,
+    pub hasTimestamp: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct IndexedDocumentProjection {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub blockNumber: u64,
+    pub createdAt: DateTime,
+    pub eventType: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct EventKeyProjection {
+    pub txHash: String,
+    pub logIndex: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct EventOrderingProjection {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub txHash: String,
+    pub logIndex: i64,
+    pub blockNumber: u64,
+    pub createdAt: Option<DateTime>,
+    #[serde(rename = "__v")]
+    pub version: Option<i32>,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[allow(non_snake_case)]
 pub struct CommonFields {
@@ -483,4 +526,168 @@ impl MoneyMarketEventDocument {
             Self::Withdraw(_) => "withdraw",
         }
     }
+
+    /// The reserve for pool events, the aToken or debt token for token events
+    pub fn token(&self) -> &str {
+        match self {
+            Self::ATokenBalanceTransfer(e) => &e.tokenAddress,
+            Self::ATokenBurn(e) => &e.tokenAddress,
+            Self::ATokenMint(e) => &e.tokenAddress,
+            Self::ATokenTransfer(e) => &e.tokenAddress,
+            Self::Borrow(e) => &e.reserve,
+            Self::DebtTokenBurn(e) => &e.tokenAddress,
+            Self::DebtTokenMint(e) => &e.tokenAddress,
+            Self::Repay(e) => &e.reserve,
+            Self::ReserveDataUpdated(e) => &e.reserve,
+            Self::Supply(e) => &e.reserve,
+            Self::Withdraw(e) => &e.reserve,
+        }
+    }
+
+    /// The users whose position the event changes
+    pub fn affected_users(&self) -> Vec<&str> {
+        match self {
+            Self::ATokenBalanceTransfer(e) => vec![&e.from, &e.to],
+            Self::ATokenBurn(e) => vec![&e.from],
+            Self::ATokenMint(e) => vec![&e.onBehalfOf],
+            Self::ATokenTransfer(e) => vec![&e.from, &e.to],
+            Self::Borrow(e) => vec![&e.onBehalfOf],
+            Self::DebtTokenBurn(e) => vec![&e.from],
+            Self::DebtTokenMint(e) => vec![&e.onBehalfOf],
+            Self::Repay(e) => vec![&e.user],
+            Self::ReserveDataUpdated(_) => vec![],
+            Self::Supply(e) => vec![&e.onBehalfOf],
+            Self::Withdraw(e) => vec![&e.user],
+        }
+    }
+}
+
+/// Documents shaped like the indexer's, for unit tests
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+    use mongodb::bson::{doc, Document};
+    use serde::de::DeserializeOwned;
+    use std::str::FromStr;
+
+    pub const RESERVE_ADDRESS: &str = "0xe801ca34e19abcbfea12025378d19c4fbe250131";
+    pub const A_TOKEN_ADDRESS: &str = "0x5c50cf875aebad8d5ba548f229960c90b1c1f8c3";
+    pub const DEBT_TOKEN_ADDRESS: &str = "0x96a4197803ac8b21a1b7aefe72e565c71a91a40f";
+    pub const USER_ADDRESS: &str = "0x6d7b6956589c17B2755193a67BF2d4B68827E58A";
+    pub const TREASURY_ADDRESS: &str = "0x0000000000000000000000000000000000000fee";
+    pub const TX_HASH: &str = "0x9f1c7a1f3f7c2d5a4b6e8d0c1b2a39485766554433221100ffeeddccbbaa9988";
+    /// 1.05 ray
+    pub const INDEX: &str = "1050000000000000000000000000";
+
+    pub fn decimal(value: &str) -> Decimal128 {
+        Decimal128::from_str(value).unwrap()
+    }
+
+    /// Deserializes `fields` into a document, with a new `_id` and the bookkeeping fields
+    /// filled in unless `fields` sets them
+    pub fn document<T: DeserializeOwned>(fields: Document) -> T {
+        let mut document = doc! {
+            "_id": ObjectId::new(),
+            "createdAt": DateTime::now(),
+            "updatedAt": DateTime::now(),
+            "__v": 0,
+        };
+        document.extend(fields);
+        mongodb::bson::from_document(document).unwrap()
+    }
+
+    /// A money_market_events document of `event_type` at log 0 of TX_HASH, in block 1000000
+    /// unless `fields` says otherwise
+    pub fn event<T: DeserializeOwned>(event_type: &str, fields: Document) -> T {
+        let mut document = doc! {
+            "eventType": event_type,
+            "txHash": TX_HASH,
+            "logIndex": 0_i64,
+            "chainId": 146_i64,
+            "blockNumber": 1_000_000_i64,
+        };
+        document.extend(fields);
+        self::document(document)
+    }
+
+    pub fn supply_event(amount: &str) -> MoneyMarketEventDocument {
+        event(
+            "supply",
+            doc! {
+                "logIndex": 3_i64,
+                "reserve": RESERVE_ADDRESS,
+                "user": USER_ADDRESS,
+                "onBehalfOf": USER_ADDRESS,
+                "amount": decimal(amount),
+                "referralCode": 0,
+            },
+        )
+    }
+
+    pub fn a_token_mint_event(value: &str, balance_increase: &str) -> MoneyMarketEventDocument {
+        event(
+            "a-token-mint",
+            doc! {
+                "logIndex": 2_i64,
+                "tokenAddress": A_TOKEN_ADDRESS,
+                "caller": USER_ADDRESS,
+                "onBehalfOf": USER_ADDRESS,
+                "value": decimal(value),
+                "balanceIncrease": decimal(balance_increase),
+                "index": decimal(INDEX),
+            },
+        )
+    }
+
+    /// A ReserveDataUpdated with zero rates, in block `block_number`
+    pub fn reserve_data_updated<T: DeserializeOwned>(
+        block_number: i64,
+        rates: (&str, &str),
+        indexes: (&str, &str),
+    ) -> T {
+        event(
+            "reserve-data-updated",
+            doc! {
+                "blockNumber": block_number,
+                "reserve": RESERVE_ADDRESS,
+                "liquidityRate": decimal(rates.0),
+                "stableBorrowRate": decimal("0"),
+                "variableBorrowRate": decimal(rates.1),
+                "liquidityIndex": decimal(indexes.0),
+                "variableBorrowIndex": decimal(indexes.1),
+            },
+        )
+    }
+
+    pub fn reserve_token(total_supply: &str, total_debt: &str) -> ReserveTokenDocument {
+        document(doc! {
+            "totalATokenBalance": decimal(total_supply),
+            "totalVariableDebtTokenBalance": decimal(total_debt),
+            "suppliers": [],
+            "borrowers": [],
+            "aTokenAddress": A_TOKEN_ADDRESS,
+            "variableDebtTokenAddress": DEBT_TOKEN_ADDRESS,
+            "reserveAddress": RESERVE_ADDRESS,
+            "symbol": "TEST",
+            "liquidityRate": decimal("0"),
+            "stableBorrowRate": decimal("0"),
+            "variableBorrowRate": decimal("0"),
+            "liquidityIndex": decimal(INDEX),
+            "variableBorrowIndex": decimal(INDEX),
+            "blockNumber": 1_000_000_i64,
+        })
+    }
+
+    pub fn user_position(user: &str, supply: &str, debt: &str) -> UserPositionDocument {
+        document(doc! {
+            "userAddress": user,
+            "positions": [{
+                "reserveAddress": RESERVE_ADDRESS,
+                "aTokenAddress": A_TOKEN_ADDRESS,
+                "variableDebtTokenAddress": DEBT_TOKEN_ADDRESS,
+                "aTokenBalance": decimal(supply),
+                "variableDebtTokenBalance": decimal(debt),
+            }],
+        })
+    }
 }
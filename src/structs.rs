@@ -1,3 +1,10 @@
+use alloy::primitives::{Address, B256};
+use mongodb::bson::{doc, oid::ObjectId, Bson, DateTime};
+use primitive_types::U256;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
 #[derive(Debug, Clone)]
 pub enum Flag {
     Help,
@@ -18,7 +25,8 @@ pub enum Flag {
     ValidateUsersAll,
     ValidateTokenAll,
     ValidateAll,
-    TimestampCoverage,
+    TimestampCoverage(Option<String>),
+    BucketSize(String),
     ValidateTimestamps(Option<String>),
     GetAllUsers,
     GetAllReserves,
@@ -28,6 +36,35 @@ pub enum Flag {
     GetUserEvents(String),
     ValidateReserveIndexes(String),
     ValidateAllReserveIndexes,
+    AddressCasingReport,
+    TimestampBackfill(String),
+    PatchFormat(String),
+    MaxDeviation(String),
+    AtTime(String),
+    Since(String),
+    IndexingLatency(Option<String>),
+    VerifyEvents(Option<String>),
+    FindMissingEvents,
+    EventAnomalies,
+    ReorgCheck(Option<String>),
+    ValidateEventMath,
+    GetTx(String),
+    Grouped,
+    UserTimeline(String),
+    Format(String),
+    BisectUser(String),
+    ReconcileReserves,
+    CheckUserLists(Option<String>),
+    OnChain,
+    FindUntrackedHolders(Option<String>),
+    IndexHistory(String),
+    Csv(String),
+    ValidateRates(Option<String>),
+    ValidateReserveConfig(Option<String>),
+    StableDebt(Option<String>),
+    ReserveHealth(Option<String>),
+    ValidateAccountData(Option<String>),
+    HealthFactorBelow(String),
 }
 #[derive(Debug, Clone)]
 pub struct EntryState {
@@ -38,11 +75,7 @@ pub struct EntryState {
 }
 impl EntryState {
     pub fn new(database_amount: u128, on_chain_amount: u128) -> Self {
-        let difference = if database_amount > on_chain_amount {
-            database_amount - on_chain_amount
-        } else {
-            on_chain_amount - database_amount
-        };
+        let difference = database_amount.abs_diff(on_chain_amount);
 
         // Handle division by zero and edge cases
         let percentage = if on_chain_amount == 0 {
@@ -153,7 +186,7 @@ impl Collections {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ReserveTokenField {
     Reserve,
     AToken,
@@ -171,7 +204,1498 @@ pub enum FlagType {
     ValidateUserBorrow,
     ValidateUserAll,
     ValidateTimestamps,
+    TimestampCoverage,
+    BucketSize,
+    TimestampBackfill,
+    PatchFormat,
+    MaxDeviation,
+    AtTime,
+    Since,
+    IndexingLatency,
+    VerifyEvents,
+    ReorgCheck,
+    GetTx,
+    UserTimeline,
+    Format,
+    BisectUser,
+    CheckUserLists,
+    FindUntrackedHolders,
+    IndexHistory,
+    ValidateRates,
+    ValidateReserveConfig,
+    StableDebt,
+    ReserveHealth,
+    ValidateAccountData,
+    HealthFactorBelow,
+    Csv,
     ValidateReserveIndexes,
     GetTokenEvents,
     GetUserEvents,
 }
+
+/// An EVM address used to query the database. Collections are not consistent
+/// about casing (reserve_tokens stores lowercase, money_market_events stores the
+/// reserve checksummed), so filters built from it match both canonical forms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DbAddress(Address);
+
+impl DbAddress {
+    pub fn address(&self) -> Address {
+        self.0
+    }
+
+    pub fn lowercase(&self) -> String {
+        format!("{:#x}", self.0)
+    }
+
+    pub fn checksum(&self) -> String {
+        self.0.to_checksum(None)
+    }
+
+    /// Both canonical string forms (a single entry when they are identical)
+    pub fn variants(&self) -> Vec<String> {
+        let lowercase = self.lowercase();
+        let checksum = self.checksum();
+        if lowercase == checksum {
+            vec![lowercase]
+        } else {
+            vec![lowercase, checksum]
+        }
+    }
+
+    /// Query operator matching any canonical form, i.e. `{ "$in": [lower, checksum] }`
+    pub fn filter(&self) -> Bson {
+        Bson::Document(doc! { "$in": self.variants() })
+    }
+
+    /// Returns true when the stored string refers to this address in any casing
+    pub fn matches(&self, stored: &str) -> bool {
+        DbAddress::from_str(stored).is_ok_and(|other| other == *self)
+    }
+}
+
+impl FromStr for DbAddress {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Address::from_str(input.trim())
+            .map(DbAddress)
+            .map_err(|_| format!("Invalid address: {}", input))
+    }
+}
+
+impl fmt::Display for DbAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.checksum())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddressForm {
+    Lowercase,
+    Checksum,
+    NonCanonical,
+    Invalid,
+}
+
+#[derive(Debug, Clone)]
+pub struct AddressFieldReport {
+    pub collection: String,
+    pub field: String,
+    pub lowercase: usize,
+    pub checksum: usize,
+    pub non_canonical: Vec<String>,
+    pub invalid: Vec<String>,
+}
+
+impl AddressFieldReport {
+    pub fn new(collection: &str, field: &str) -> Self {
+        AddressFieldReport {
+            collection: collection.to_string(),
+            field: field.to_string(),
+            lowercase: 0,
+            checksum: 0,
+            non_canonical: Vec::new(),
+            invalid: Vec::new(),
+        }
+    }
+
+    pub fn location(&self) -> String {
+        format!("{}.{}", self.collection, self.field)
+    }
+}
+
+/// Timestamp coverage of the documents falling in one bucket (block range or day)
+#[derive(Debug, Clone)]
+pub struct CoverageBucket {
+    pub label: String,
+    pub total: u64,
+    pub with_timestamp: u64,
+}
+
+impl CoverageBucket {
+    pub fn coverage(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            (self.with_timestamp as f64 / self.total as f64) * 100.0
+        }
+    }
+}
+
+/// A run of consecutive documents (ordered by block) that have no timestamp
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingTimestampRange {
+    pub from_block: u64,
+    pub to_block: u64,
+    pub documents: u64,
+}
+
+/// Builds the missing timestamp ranges from documents fed in block order, so a
+/// collection can be scanned as a stream without keeping every block in memory
+#[derive(Debug, Default)]
+pub struct MissingTimestampRanges {
+    pub ranges: Vec<MissingTimestampRange>,
+    in_gap: bool,
+}
+
+impl MissingTimestampRanges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, block_number: u64, has_timestamp: bool) {
+        if has_timestamp {
+            self.in_gap = false;
+            return;
+        }
+        match self.ranges.last_mut() {
+            Some(range) if self.in_gap => {
+                range.to_block = block_number;
+                range.documents += 1;
+            }
+            _ => self.ranges.push(MissingTimestampRange {
+                from_block: block_number,
+                to_block: block_number,
+                documents: 1,
+            }),
+        }
+        self.in_gap = true;
+    }
+
+    /// True when the last document pushed had no timestamp, i.e. the latest
+    /// range is still open at the head of the collection
+    pub fn is_open(&self) -> bool {
+        self.in_gap
+    }
+}
+
+/// Output format of a timestamp backfill patch file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatchFormat {
+    /// One relaxed extended JSON object per line: `_id`, `blockNumber` and the new `timestamp`
+    JsonLines,
+    /// One `updateOne` statement per line, runnable with mongosh
+    MongoScript,
+}
+
+impl FromStr for PatchFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "jsonl" | "json" => Ok(PatchFormat::JsonLines),
+            "mongo" | "js" => Ok(PatchFormat::MongoScript),
+            _ => Err(format!(
+                "Unknown patch format '{}', expected jsonl or mongo",
+                s
+            )),
+        }
+    }
+}
+
+/// Output format of commands that can print machine-readable output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    /// A single relaxed extended JSON document
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("Unknown format '{}', expected text or json", s)),
+        }
+    }
+}
+
+/// A proposed correction of the `timestamp` field of one document
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimestampPatch {
+    pub id: ObjectId,
+    pub block_number: u64,
+    pub current: Option<DateTime>,
+    pub timestamp: DateTime,
+}
+
+impl TimestampPatch {
+    /// Returns a patch when the stored timestamp is missing or deviates from the block
+    /// timestamp (in seconds) by more than `max_deviation` seconds
+    pub fn propose(
+        id: ObjectId,
+        block_number: u64,
+        current: Option<DateTime>,
+        block_timestamp: u64,
+        max_deviation: u64,
+    ) -> Option<Self> {
+        if let Some(current) = current {
+            let stored_seconds = current.timestamp_millis() / 1000;
+            if stored_seconds.abs_diff(block_timestamp as i64) <= max_deviation {
+                return None;
+            }
+        }
+        Some(TimestampPatch {
+            id,
+            block_number,
+            current,
+            timestamp: DateTime::from_millis(block_timestamp as i64 * 1000),
+        })
+    }
+
+    pub fn to_line(&self, format: PatchFormat, collection_name: &str) -> String {
+        match format {
+            PatchFormat::JsonLines => Bson::Document(doc! {
+                "_id": self.id,
+                "blockNumber": self.block_number as i64,
+                "timestamp": self.timestamp,
+            })
+            .into_relaxed_extjson()
+            .to_string(),
+            PatchFormat::MongoScript => format!(
+                "db.getCollection(\"{}\").updateOne({{ _id: ObjectId(\"{}\") }}, {{ $set: {{ timestamp: new Date(\"{}\") }} }}); // block {}",
+                collection_name,
+                self.id.to_hex(),
+                self.timestamp.try_to_rfc3339_string().unwrap_or_default(),
+                self.block_number
+            ),
+        }
+    }
+}
+
+/// A block header entry of the on-disk block cache
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CachedBlock {
+    pub number: u64,
+    pub timestamp: u64,
+    pub hash: B256,
+}
+
+impl CachedBlock {
+    /// One line of the cache file: `<number> <timestamp> <hash>`
+    pub fn to_line(&self) -> String {
+        format!("{} {} {}", self.number, self.timestamp, self.hash)
+    }
+
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let block = CachedBlock {
+            number: parts.next()?.parse().ok()?,
+            timestamp: parts.next()?.parse().ok()?,
+            hash: parts.next()?.parse().ok()?,
+        };
+        match parts.next() {
+            Some(_) => None,
+            None => Some(block),
+        }
+    }
+}
+
+/// Inclusive block number bounds used to filter documents by `blockNumber`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BlockRange {
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+}
+
+impl BlockRange {
+    pub fn is_unbounded(&self) -> bool {
+        self.from.is_none() && self.to.is_none()
+    }
+
+    pub fn contains(&self, block_number: u64) -> bool {
+        self.from.is_none_or(|from| block_number >= from)
+            && self.to.is_none_or(|to| block_number <= to)
+    }
+
+    /// `blockNumber` condition for a MongoDB filter, `None` when the range is unbounded
+    pub fn filter(&self) -> Option<Bson> {
+        let mut condition = mongodb::bson::Document::new();
+        if let Some(from) = self.from {
+            condition.insert("$gte", from as i64);
+        }
+        if let Some(to) = self.to {
+            condition.insert("$lte", to as i64);
+        }
+        if condition.is_empty() {
+            None
+        } else {
+            Some(Bson::Document(condition))
+        }
+    }
+}
+
+impl fmt::Display for BlockRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.from, self.to) {
+            (Some(from), Some(to)) => write!(f, "blocks {} - {}", from, to),
+            (Some(from), None) => write!(f, "blocks from {}", from),
+            (None, Some(to)) => write!(f, "blocks up to {}", to),
+            (None, None) => write!(f, "all blocks"),
+        }
+    }
+}
+
+/// Seconds between a block and the moment the backend wrote a document for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencySample {
+    pub id: String,
+    pub block_number: u64,
+    pub latency: i64,
+}
+
+/// Latency percentiles (nearest rank) over a set of samples, in seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub p50: i64,
+    pub p95: i64,
+    pub p99: i64,
+    pub max: i64,
+}
+
+impl LatencyStats {
+    pub fn from_latencies(latencies: &[i64]) -> Option<Self> {
+        if latencies.is_empty() {
+            return None;
+        }
+        let mut sorted = latencies.to_vec();
+        sorted.sort_unstable();
+        let percentile = |p: usize| sorted[(sorted.len() * p).div_ceil(100).max(1) - 1];
+        Some(LatencyStats {
+            count: sorted.len(),
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+/// Accumulates indexing latencies of one collection (or event type), overall and per day
+#[derive(Debug, Default)]
+pub struct LatencyReport {
+    latencies: Vec<i64>,
+    by_day: BTreeMap<String, Vec<i64>>,
+    worst: Vec<LatencySample>,
+    worst_limit: usize,
+}
+
+impl LatencyReport {
+    pub fn new(worst_limit: usize) -> Self {
+        LatencyReport {
+            worst_limit,
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, sample: LatencySample, day: String) {
+        self.latencies.push(sample.latency);
+        self.by_day.entry(day).or_default().push(sample.latency);
+        self.worst.push(sample);
+        // trim lazily so pushing stays cheap
+        if self.worst.len() >= self.worst_limit * 2 {
+            self.trim_worst();
+        }
+    }
+
+    pub fn stats(&self) -> Option<LatencyStats> {
+        LatencyStats::from_latencies(&self.latencies)
+    }
+
+    pub fn daily_stats(&self) -> Vec<(String, LatencyStats)> {
+        self.by_day
+            .iter()
+            .filter_map(|(day, latencies)| {
+                LatencyStats::from_latencies(latencies).map(|stats| (day.clone(), stats))
+            })
+            .collect()
+    }
+
+    pub fn worst_offenders(&mut self) -> &[LatencySample] {
+        self.trim_worst();
+        &self.worst
+    }
+
+    fn trim_worst(&mut self) {
+        self.worst
+            .sort_by_key(|sample| std::cmp::Reverse(sample.latency));
+        self.worst.truncate(self.worst_limit);
+    }
+}
+
+/// A value stored in the database that differs from the on-chain one
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldMismatch {
+    pub field: String,
+    pub stored: String,
+    pub on_chain: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventVerificationStatus {
+    Verified,
+    MissingReceipt,
+    MissingLog,
+    Mismatch(Vec<FieldMismatch>),
+}
+
+/// Result of checking one stored event against the log at its `txHash` / `logIndex`
+#[derive(Debug, Clone)]
+pub struct EventVerification {
+    pub id: String,
+    pub event_type: String,
+    pub tx_hash: String,
+    pub log_index: i64,
+    pub status: EventVerificationStatus,
+}
+
+impl fmt::Display for EventVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} (tx {}, log {})",
+            self.id, self.event_type, self.tx_hash, self.log_index
+        )?;
+        match &self.status {
+            EventVerificationStatus::Verified => write!(f, ": verified"),
+            EventVerificationStatus::MissingReceipt => write!(f, ": transaction receipt not found"),
+            EventVerificationStatus::MissingLog => write!(f, ": no log at this index"),
+            EventVerificationStatus::Mismatch(mismatches) => {
+                write!(f, ":")?;
+                for mismatch in mismatches {
+                    write!(
+                        f,
+                        "\n    {}: stored {}, on-chain {}",
+                        mismatch.field, mismatch.stored, mismatch.on_chain
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Scaled balances of a user in one reserve, with the last indexes seen for it
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReserveBalances {
+    pub scaled_supply: U256,
+    pub scaled_debt: U256,
+    pub liquidity_index: Option<U256>,
+    pub borrow_index: Option<U256>,
+}
+
+/// One event of a user timeline with the user's balances in the event's reserve afterwards
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineEntry {
+    pub block_number: u64,
+    pub log_index: i64,
+    pub tx_hash: String,
+    pub action: String,
+    /// lowercase reserve address, or the token address when its reserve is unknown
+    pub reserve: String,
+    /// amount of underlying moved by the event
+    pub amount: Option<U256>,
+    pub balances: ReserveBalances,
+    /// real balances at the last index seen, `None` before any index is known
+    pub supply: Option<U256>,
+    pub debt: Option<U256>,
+}
+
+/// A final timeline balance next to the stored and on-chain scaled balances
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalanceComparison {
+    pub reserve: String,
+    /// "supply" or "debt"
+    pub balance: &'static str,
+    pub timeline_scaled: U256,
+    pub stored: Option<U256>,
+    pub on_chain: Result<U256, String>,
+}
+
+impl BalanceComparison {
+    /// A reserve missing from user_positions counts as a zero stored balance
+    pub fn matches(&self) -> bool {
+        let stored = self.stored.unwrap_or_default();
+        self.timeline_scaled == stored && self.on_chain.as_ref().ok() == Some(&stored)
+    }
+}
+
+/// High-level action of a transaction and the companion events it lacks
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionCheck {
+    pub tx_hash: String,
+    pub block_number: u64,
+    pub action: String,
+    pub missing_companions: Vec<String>,
+}
+
+impl fmt::Display for TransactionCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Transaction {} (block {}): {}",
+            self.tx_hash, self.block_number, self.action
+        )
+    }
+}
+
+/// Change of a user's scaled balance behind a Mint / Burn event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScaledDelta {
+    Increase(U256),
+    Decrease(U256),
+}
+
+impl fmt::Display for ScaledDelta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScaledDelta::Increase(amount) => write!(f, "+{}", amount),
+            ScaledDelta::Decrease(amount) => write!(f, "-{}", amount),
+        }
+    }
+}
+
+/// Scaled totals of a reserve document next to the sum of the matching `user_positions`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReserveReconciliation {
+    pub reserve: String,
+    pub symbol: String,
+    /// `totalATokenBalance` and `totalVariableDebtTokenBalance`
+    pub total_supply: U256,
+    pub total_debt: U256,
+    /// sums over every position but the treasury's
+    pub users_supply: U256,
+    pub users_debt: U256,
+    pub suppliers: u64,
+    pub borrowers: u64,
+    /// lowercase address of the treasury, `None` when it is counted with the users
+    pub treasury: Option<String>,
+    /// scaled aToken balance of the treasury, `None` when it has no position in the reserve
+    pub treasury_supply: Option<U256>,
+}
+
+impl ReserveReconciliation {
+    /// Total minus the users' sum and the treasury, positive when the total is larger
+    pub fn supply_residual(&self) -> ScaledDelta {
+        let accounted = self
+            .users_supply
+            .saturating_add(self.treasury_supply.unwrap_or_default());
+        signed_difference(self.total_supply, accounted)
+    }
+
+    pub fn debt_residual(&self) -> ScaledDelta {
+        signed_difference(self.total_debt, self.users_debt)
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.supply_residual() == ScaledDelta::Increase(U256::zero())
+            && self.debt_residual() == ScaledDelta::Increase(U256::zero())
+    }
+}
+
+/// Reserve-level accounting of a reserve at a pinned block. Supply amounts are scaled, the
+/// isolation mode debt has 2 decimals like the debt ceiling.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReserveHealth {
+    pub block_number: u64,
+    pub configuration: ReserveConfiguration,
+    /// on-chain `scaledTotalSupply` of the aToken
+    pub scaled_total_supply: U256,
+    /// sum of the `user_positions` balances, the treasury's aside
+    pub users_supply: U256,
+    /// on-chain scaled aToken balance of the treasury
+    pub treasury_supply: U256,
+    /// reserve factor accrued but not minted to the treasury yet
+    pub accrued_to_treasury: U256,
+    /// on-chain liquidity index at the pinned block, to turn scaled amounts into tokens
+    pub liquidity_index: U256,
+    pub unbacked: U256,
+    pub isolation_mode_total_debt: U256,
+}
+
+impl ReserveHealth {
+    /// Scaled total supply minus the users and the treasury balance; positive when the total is
+    /// larger. `accrued_to_treasury` is left out: it is only minted at the next
+    /// `mintToTreasury`, so it is neither in the total supply nor in any balance yet.
+    pub fn supply_residual(&self) -> ScaledDelta {
+        let accounted = self.users_supply.saturating_add(self.treasury_supply);
+        signed_difference(self.scaled_total_supply, accounted)
+    }
+}
+
+/// `getUserAccountData` of a user, amounts in the oracle base currency, percentages in basis
+/// points and the health factor in wad
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AccountData {
+    pub total_collateral_base: U256,
+    pub total_debt_base: U256,
+    pub available_borrows_base: U256,
+    pub current_liquidation_threshold: U256,
+    pub ltv: U256,
+    pub health_factor: U256,
+    /// the oracle's `BASE_CURRENCY_UNIT`
+    pub base_currency_unit: U256,
+}
+
+impl AccountData {
+    /// The health factor as a number, infinite without debt
+    pub fn health_factor_value(&self) -> f64 {
+        if self.health_factor == U256::MAX {
+            return f64::INFINITY;
+        }
+        self.health_factor
+            .to_string()
+            .parse::<f64>()
+            .unwrap_or(f64::NAN)
+            / 1e18
+    }
+}
+
+impl fmt::Display for AccountData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let to_f64 = |value: U256| value.to_string().parse::<f64>().unwrap_or(f64::NAN);
+        let base = |value: U256| to_f64(value) / to_f64(self.base_currency_unit);
+        let percent = |bps: U256| bps.low_u64() as f64 / 100.0;
+        write!(
+            f,
+            "collateral {:.2}, debt {:.2}, available borrows {:.2}, liquidation threshold {:.2}%, LTV {:.2}%, health factor {:.4}",
+            base(self.total_collateral_base),
+            base(self.total_debt_base),
+            base(self.available_borrows_base),
+            percent(self.current_liquidation_threshold),
+            percent(self.ltv),
+            self.health_factor_value()
+        )
+    }
+}
+
+/// What the account totals of a user need from a reserve: its on-chain id, decimals and
+/// liquidation threshold (basis points), current indexes (ray) and oracle price
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PricedReserve {
+    pub id: u16,
+    pub decimals: u8,
+    pub liquidation_threshold: u16,
+    pub liquidity_index: U256,
+    pub variable_borrow_index: U256,
+    pub price: U256,
+}
+
+/// Account totals of a user recomputed from `user_positions`, in the oracle base currency
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AccountTotals {
+    pub collateral_base: U256,
+    pub debt_base: U256,
+    /// average liquidation threshold of the collateral, basis points
+    pub liquidation_threshold: U256,
+    /// wad, `U256::MAX` without debt
+    pub health_factor: U256,
+}
+
+/// Oracle prices of reserves, keyed by lowercase reserve address, to express token amounts
+/// in USD
+#[derive(Debug, Clone, Default)]
+pub struct PriceBook {
+    /// block the prices were read at, `None` for the latest one
+    pub block_number: Option<u64>,
+    /// the oracle's `BASE_CURRENCY_UNIT`, one USD
+    base_currency_unit: U256,
+    /// price in the base currency and decimals of the reserve
+    prices: BTreeMap<String, (U256, u8)>,
+}
+
+impl PriceBook {
+    pub fn new(block_number: Option<u64>, base_currency_unit: U256) -> Self {
+        PriceBook {
+            block_number,
+            base_currency_unit,
+            prices: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, reserve: &str, price: U256, decimals: u8) {
+        self.prices
+            .insert(reserve.to_lowercase(), (price, decimals));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prices.is_empty()
+    }
+
+    /// USD price of one token of the reserve
+    pub fn price(&self, reserve: &str) -> Option<f64> {
+        let to_f64 = |value: &U256| value.to_string().parse::<f64>().unwrap_or(f64::NAN);
+        self.prices
+            .get(&reserve.to_lowercase())
+            .map(|(price, _)| to_f64(price) / to_f64(&self.base_currency_unit))
+    }
+
+    /// USD value of an amount of the reserve in its smallest unit
+    pub fn usd_value(&self, reserve: &str, amount: u128) -> Option<f64> {
+        let (_, decimals) = self.prices.get(&reserve.to_lowercase())?;
+        self.price(reserve)
+            .map(|price| amount as f64 / 10f64.powi(*decimals as i32) * price)
+    }
+}
+
+/// Differences between a reserve's `suppliers` or `borrowers` list and the users holding
+/// a balance of its aToken or debt token
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserListCheck {
+    pub list: String,
+    /// users with a non-zero balance that are not listed
+    pub missing: Vec<String>,
+    /// listed users without a balance
+    pub stale: Vec<String>,
+    /// users listed more than once
+    pub duplicates: Vec<String>,
+}
+
+impl UserListCheck {
+    pub fn is_consistent(&self) -> bool {
+        self.missing.is_empty() && self.stale.is_empty() && self.duplicates.is_empty()
+    }
+}
+
+impl fmt::Display for UserListCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} missing, {} stale, {} duplicated",
+            self.list,
+            self.missing.len(),
+            self.stale.len(),
+            self.duplicates.len()
+        )
+    }
+}
+
+/// An address that received a reserve token but has no position in that reserve in
+/// `user_positions`
+#[derive(Debug, Clone, PartialEq)]
+pub struct UntrackedHolder {
+    pub address: String,
+    /// whether the address has a `user_positions` document at all
+    pub has_user_position: bool,
+}
+
+impl fmt::Display for UntrackedHolder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.has_user_position {
+            write!(f, "{} (no position for this reserve)", self.address)
+        } else {
+            write!(f, "{} (no user position)", self.address)
+        }
+    }
+}
+
+/// How an index of a ReserveDataUpdated event compares with the one of the previous event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndexStatus {
+    /// first event of the reserve, nothing to compare with
+    First,
+    /// equals the previous index grown by the previous rate over the elapsed time
+    Consistent,
+    /// the previous index although the rate and time say it should have grown, which Aave
+    /// does for the borrow index while there is no debt
+    Unchanged,
+    Decreased,
+    Mismatch {
+        expected: U256,
+    },
+    /// the block timestamp of this or the previous event is unknown
+    NoTimestamp,
+}
+
+impl IndexStatus {
+    pub fn is_error(&self) -> bool {
+        matches!(self, IndexStatus::Decreased | IndexStatus::Mismatch { .. })
+    }
+}
+
+impl fmt::Display for IndexStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexStatus::First => write!(f, "first"),
+            IndexStatus::Consistent => write!(f, "ok"),
+            IndexStatus::Unchanged => write!(f, "unchanged"),
+            IndexStatus::Decreased => write!(f, "decreased"),
+            IndexStatus::Mismatch { expected } => write!(f, "expected {}", expected),
+            IndexStatus::NoTimestamp => write!(f, "no timestamp"),
+        }
+    }
+}
+
+/// One ReserveDataUpdated event of a reserve with the checks of both indexes
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexHistoryEntry {
+    pub block_number: u64,
+    pub log_index: i64,
+    pub tx_hash: String,
+    pub timestamp: Option<u64>,
+    pub liquidity_rate: U256,
+    pub variable_borrow_rate: U256,
+    pub liquidity_index: U256,
+    pub variable_borrow_index: U256,
+    pub liquidity_index_status: IndexStatus,
+    pub borrow_index_status: IndexStatus,
+}
+
+impl IndexHistoryEntry {
+    pub const CSV_HEADER: &'static str = "blockNumber,logIndex,txHash,timestamp,liquidityRate,variableBorrowRate,liquidityIndex,variableBorrowIndex,liquidityIndexStatus,borrowIndexStatus";
+
+    pub fn to_csv_line(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{}",
+            self.block_number,
+            self.log_index,
+            self.tx_hash,
+            self.timestamp.map(|t| t.to_string()).unwrap_or_default(),
+            self.liquidity_rate,
+            self.variable_borrow_rate,
+            self.liquidity_index,
+            self.variable_borrow_index,
+            self.liquidity_index_status,
+            self.borrow_index_status
+        )
+    }
+}
+
+/// Parameters of a reserve's interest rate strategy, all in ray
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateStrategyParams {
+    pub optimal_usage_ratio: U256,
+    pub base_variable_borrow_rate: U256,
+    pub variable_rate_slope1: U256,
+    pub variable_rate_slope2: U256,
+}
+
+/// Debt of a reserve as its rate strategy sees it: variable and stable debt in underlying
+/// units, and the average stable rate (ray)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ReserveDebt {
+    pub variable: U256,
+    pub stable: U256,
+    pub average_stable_rate: U256,
+}
+
+/// Utilization and rates recomputed from a reserve's balances and rate strategy, in ray
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecomputedRates {
+    pub borrow_usage_ratio: U256,
+    pub supply_usage_ratio: U256,
+    pub liquidity_rate: U256,
+    pub variable_borrow_rate: U256,
+}
+
+/// Reserve settings decoded from the `ReserveConfigurationMap` bitmap. Percentages are in
+/// basis points, caps in whole tokens (0 means no cap) and the debt ceiling has 2 decimals.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ReserveConfiguration {
+    pub ltv: u16,
+    pub liquidation_threshold: u16,
+    pub liquidation_bonus: u16,
+    pub decimals: u8,
+    pub active: bool,
+    pub frozen: bool,
+    pub borrowing_enabled: bool,
+    pub stable_borrowing_enabled: bool,
+    pub paused: bool,
+    pub borrowable_in_isolation: bool,
+    pub siloed_borrowing: bool,
+    pub flash_loan_enabled: bool,
+    pub reserve_factor: u16,
+    pub borrow_cap: u64,
+    pub supply_cap: u64,
+    pub liquidation_protocol_fee: u16,
+    pub emode_category: u8,
+    pub unbacked_mint_cap: u64,
+    pub debt_ceiling: u64,
+}
+
+impl fmt::Display for ReserveConfiguration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |bps: u16| format!("{:.2}%", bps as f64 / 100.0);
+        let cap = |cap: u64| {
+            if cap == 0 {
+                "none".to_string()
+            } else {
+                cap.to_string()
+            }
+        };
+        let status = match (self.active, self.paused, self.frozen) {
+            (false, _, _) => "inactive",
+            (true, true, _) => "paused",
+            (true, false, true) => "frozen",
+            (true, false, false) => "active",
+        };
+        writeln!(f, "  status: {}", status)?;
+        writeln!(
+            f,
+            "  LTV {}, liquidation threshold {}, liquidation bonus {}, liquidation protocol fee {}",
+            percent(self.ltv),
+            percent(self.liquidation_threshold),
+            percent(self.liquidation_bonus),
+            percent(self.liquidation_protocol_fee)
+        )?;
+        writeln!(
+            f,
+            "  decimals {}, reserve factor {}, e-mode category {}",
+            self.decimals,
+            percent(self.reserve_factor),
+            self.emode_category
+        )?;
+        writeln!(
+            f,
+            "  borrowing {}, stable borrowing {}, flash loans {}, borrowable in isolation {}, siloed borrowing {}",
+            self.borrowing_enabled,
+            self.stable_borrowing_enabled,
+            self.flash_loan_enabled,
+            self.borrowable_in_isolation,
+            self.siloed_borrowing
+        )?;
+        write!(
+            f,
+            "  supply cap {}, borrow cap {}, unbacked mint cap {}, debt ceiling {:.2}",
+            cap(self.supply_cap),
+            cap(self.borrow_cap),
+            cap(self.unbacked_mint_cap),
+            self.debt_ceiling as f64 / 100.0
+        )
+    }
+}
+
+fn signed_difference(a: U256, b: U256) -> ScaledDelta {
+    if a >= b {
+        ScaledDelta::Increase(a - b)
+    } else {
+        ScaledDelta::Decrease(b - a)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventMathStatus {
+    Consistent,
+    /// consistent, but the transaction has no ReserveDataUpdated of the reserve to compare the index with
+    NoReserveUpdate,
+    Inconsistent(Vec<String>),
+}
+
+/// Result of checking the amounts and index of one Mint / Burn event
+#[derive(Debug, Clone)]
+pub struct EventMathCheck {
+    pub id: String,
+    pub event_type: String,
+    pub tx_hash: String,
+    pub log_index: i64,
+    pub scaled_delta: Option<ScaledDelta>,
+    pub status: EventMathStatus,
+}
+
+impl fmt::Display for EventMathCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} (tx {}, log {})",
+            self.id, self.event_type, self.tx_hash, self.log_index
+        )?;
+        if let Some(delta) = &self.scaled_delta {
+            write!(f, ", scaled delta {}", delta)?;
+        }
+        match &self.status {
+            EventMathStatus::Consistent => write!(f, ": consistent"),
+            EventMathStatus::NoReserveUpdate => {
+                write!(
+                    f,
+                    ": no ReserveDataUpdated of the reserve in the transaction"
+                )
+            }
+            EventMathStatus::Inconsistent(issues) => {
+                write!(f, ":")?;
+                for issue in issues {
+                    write!(f, "\n    {}", issue)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Kind of contract whose logs are indexed into money_market_events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventEmitter {
+    Pool,
+    AToken,
+    DebtToken,
+}
+
+/// Where the transaction of an indexed event is included now
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxInclusionStatus {
+    /// included in the canonical block at the stored height, with the stored log at its `logIndex`
+    Canonical,
+    /// no receipt: the transaction was dropped from the chain
+    Missing,
+    /// included in another block than the stored one
+    Moved { block_number: u64, block_hash: B256 },
+    /// included at the stored height but in a block that is no longer the canonical one
+    Reorged {
+        canonical_hash: B256,
+        block_hash: B256,
+    },
+    /// included in the same block, but the log at the stored `logIndex` is gone or differs
+    LogChanged(EventVerificationStatus),
+}
+
+impl TxInclusionStatus {
+    /// `inclusion` is the (block number, block hash) of the current receipt, `canonical_hash`
+    /// the hash of the block now at the stored height and `log_status` the result of checking
+    /// the stored event against the receipt log at its `logIndex`
+    pub fn classify(
+        stored_block: u64,
+        canonical_hash: B256,
+        inclusion: Option<(u64, B256)>,
+        log_status: EventVerificationStatus,
+    ) -> Self {
+        match (log_status, inclusion) {
+            (EventVerificationStatus::MissingReceipt, _) => TxInclusionStatus::Missing,
+            (_, Some((block_number, block_hash))) if block_number != stored_block => {
+                TxInclusionStatus::Moved {
+                    block_number,
+                    block_hash,
+                }
+            }
+            (_, Some((_, block_hash))) if block_hash != canonical_hash => {
+                TxInclusionStatus::Reorged {
+                    canonical_hash,
+                    block_hash,
+                }
+            }
+            (EventVerificationStatus::Verified, _) => TxInclusionStatus::Canonical,
+            (log_status, _) => TxInclusionStatus::LogChanged(log_status),
+        }
+    }
+}
+
+impl fmt::Display for TxInclusionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxInclusionStatus::Canonical => write!(f, "canonical"),
+            TxInclusionStatus::Missing => write!(f, "transaction no longer on chain"),
+            TxInclusionStatus::Moved {
+                block_number,
+                block_hash,
+            } => write!(f, "moved to block {} ({})", block_number, block_hash),
+            TxInclusionStatus::Reorged {
+                canonical_hash,
+                block_hash,
+            } => write!(
+                f,
+                "receipt from block {}, the canonical block is now {}",
+                block_hash, canonical_hash
+            ),
+            TxInclusionStatus::LogChanged(EventVerificationStatus::Mismatch(mismatches)) => {
+                write!(f, "the log at the stored logIndex differs:")?;
+                for mismatch in mismatches {
+                    write!(
+                        f,
+                        "\n    {}: stored {}, on-chain {}",
+                        mismatch.field, mismatch.stored, mismatch.on_chain
+                    )?;
+                }
+                Ok(())
+            }
+            TxInclusionStatus::LogChanged(_) => write!(f, "no log at the stored logIndex"),
+        }
+    }
+}
+
+/// The fields of a stored event needed to check its ordering
+#[derive(Debug, Clone)]
+pub struct EventOrderingRecord {
+    pub id: String,
+    pub tx_hash: String,
+    pub block_number: u64,
+    pub log_index: i64,
+    pub created_at: Option<i64>,
+    pub version: i32,
+}
+
+/// Ordering anomalies found while scanning events sorted by (blockNumber, logIndex)
+#[derive(Debug, Default)]
+pub struct EventOrderingScan {
+    /// ids of documents created before a document of an earlier block
+    pub created_out_of_order: Vec<String>,
+    /// (block, logIndex, ids) claimed by more than one transaction
+    pub log_index_conflicts: Vec<(u64, i64, Vec<String>)>,
+    /// (txHash, missing logIndexes) between the first and last stored log of a transaction
+    pub log_index_gaps: Vec<(String, Vec<i64>)>,
+    /// ids of documents rewritten after ingestion (`__v` > 0)
+    pub rewritten: Vec<String>,
+    max_created_before_block: Option<i64>,
+    current_block: Vec<EventOrderingRecord>,
+}
+
+impl EventOrderingScan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records must be pushed in (blockNumber, logIndex) order
+    pub fn push(&mut self, record: EventOrderingRecord) {
+        if self
+            .current_block
+            .first()
+            .is_some_and(|first| first.block_number != record.block_number)
+        {
+            self.finish_block();
+        }
+        if record.version > 0 {
+            self.rewritten.push(record.id.clone());
+        }
+        if let (Some(created_at), Some(max_before)) =
+            (record.created_at, self.max_created_before_block)
+            && created_at < max_before
+        {
+            self.created_out_of_order.push(record.id.clone());
+        }
+        self.current_block.push(record);
+    }
+
+    pub fn finish(&mut self) {
+        self.finish_block();
+    }
+
+    fn finish_block(&mut self) {
+        let records = std::mem::take(&mut self.current_block);
+        let Some(first) = records.first() else {
+            return;
+        };
+        let block_number = first.block_number;
+
+        let mut by_log_index: BTreeMap<i64, Vec<&EventOrderingRecord>> = BTreeMap::new();
+        let mut by_tx: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+        for record in &records {
+            by_log_index
+                .entry(record.log_index)
+                .or_default()
+                .push(record);
+            by_tx
+                .entry(record.tx_hash.to_lowercase())
+                .or_default()
+                .push(record.log_index);
+        }
+
+        for (log_index, claimed) in by_log_index {
+            let first_tx = claimed[0].tx_hash.to_lowercase();
+            if claimed
+                .iter()
+                .any(|record| record.tx_hash.to_lowercase() != first_tx)
+            {
+                let ids = claimed.iter().map(|record| record.id.clone()).collect();
+                self.log_index_conflicts
+                    .push((block_number, log_index, ids));
+            }
+        }
+
+        for (tx_hash, mut indexes) in by_tx {
+            indexes.sort_unstable();
+            indexes.dedup();
+            let missing: Vec<i64> = indexes
+                .windows(2)
+                .flat_map(|pair| pair[0] + 1..pair[1])
+                .collect();
+            if !missing.is_empty() {
+                self.log_index_gaps.push((tx_hash, missing));
+            }
+        }
+
+        let max_created = records.iter().filter_map(|record| record.created_at).max();
+        self.max_created_before_block = self.max_created_before_block.max(max_created);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balance_comparison_treats_missing_position_as_zero() {
+        let comparison = BalanceComparison {
+            reserve: "0xabc".to_string(),
+            balance: "supply",
+            timeline_scaled: U256::zero(),
+            stored: None,
+            on_chain: Ok(U256::zero()),
+        };
+        assert!(comparison.matches());
+
+        let held_on_chain = BalanceComparison {
+            on_chain: Ok(U256::from(10u64)),
+            ..comparison.clone()
+        };
+        assert!(!held_on_chain.matches());
+        let failed_read = BalanceComparison {
+            on_chain: Err("rpc error".to_string()),
+            ..comparison
+        };
+        assert!(!failed_read.matches());
+    }
+
+    #[test]
+    fn price_book_values_amounts_in_usd() {
+        let mut prices = PriceBook::new(None, U256::exp10(8));
+        // 1.5 USD, 6 decimals
+        prices.insert("0xAbC", U256::from(150_000_000u64), 6);
+
+        assert_eq!(prices.price("0xabc"), Some(1.5));
+        assert_eq!(prices.usd_value("0xABC", 2_000_000), Some(3.0));
+        assert_eq!(prices.usd_value("0xdef", 2_000_000), None);
+
+        // an oracle with an 18 decimals base unit
+        let mut prices = PriceBook::new(None, U256::exp10(18));
+        prices.insert("0xabc", U256::exp10(18) * 2, 6);
+        assert_eq!(prices.usd_value("0xabc", 2_000_000), Some(4.0));
+    }
+
+    #[test]
+    fn missing_ranges_merge_consecutive_documents() {
+        let mut ranges = MissingTimestampRanges::new();
+        for (block, has_timestamp) in [(1, true), (2, false), (2, false), (5, false), (7, true)] {
+            ranges.push(block, has_timestamp);
+        }
+        assert_eq!(
+            ranges.ranges,
+            vec![MissingTimestampRange {
+                from_block: 2,
+                to_block: 5,
+                documents: 3
+            }]
+        );
+        assert!(!ranges.is_open());
+    }
+
+    #[test]
+    fn missing_ranges_stay_open_at_the_head() {
+        let mut ranges = MissingTimestampRanges::new();
+        for (block, has_timestamp) in [(1, false), (2, true), (3, false), (4, false)] {
+            ranges.push(block, has_timestamp);
+        }
+        assert_eq!(ranges.ranges.len(), 2);
+        assert_eq!(ranges.ranges[1].from_block, 3);
+        assert!(ranges.is_open());
+    }
+
+    #[test]
+    fn timestamp_patch_only_for_missing_or_deviating_values() {
+        let id = ObjectId::new();
+        let block_timestamp = 1_700_000_000;
+        let exact = DateTime::from_millis(1_700_000_000_500);
+        let off = DateTime::from_millis(1_700_000_120_000);
+
+        assert_eq!(
+            TimestampPatch::propose(id, 1, Some(exact), block_timestamp, 0),
+            None
+        );
+        assert_eq!(
+            TimestampPatch::propose(id, 1, Some(off), block_timestamp, 120),
+            None
+        );
+
+        let patch = TimestampPatch::propose(id, 1, Some(off), block_timestamp, 60).unwrap();
+        assert_eq!(patch.timestamp, DateTime::from_millis(1_700_000_000_000));
+        assert!(TimestampPatch::propose(id, 1, None, block_timestamp, 60).is_some());
+    }
+
+    #[test]
+    fn timestamp_patch_lines() {
+        let id = ObjectId::parse_str("65a1b2c3d4e5f60718293a4b").unwrap();
+        let patch = TimestampPatch::propose(id, 42, None, 1_700_000_000, 0).unwrap();
+
+        assert_eq!(
+            patch.to_line(PatchFormat::JsonLines, "solver_volume"),
+            r#"{"_id":{"$oid":"65a1b2c3d4e5f60718293a4b"},"blockNumber":42,"timestamp":{"$date":"2023-11-14T22:13:20Z"}}"#
+        );
+        assert_eq!(
+            patch.to_line(PatchFormat::MongoScript, "solver_volume"),
+            r#"db.getCollection("solver_volume").updateOne({ _id: ObjectId("65a1b2c3d4e5f60718293a4b") }, { $set: { timestamp: new Date("2023-11-14T22:13:20Z") } }); // block 42"#
+        );
+    }
+
+    #[test]
+    fn cached_block_line_round_trip() {
+        let block = CachedBlock {
+            number: 1_000_000,
+            timestamp: 1_735_000_000,
+            hash: B256::repeat_byte(0xab),
+        };
+        assert_eq!(CachedBlock::from_line(&block.to_line()), Some(block));
+        assert_eq!(CachedBlock::from_line("1000000 1735000000"), None);
+        assert_eq!(CachedBlock::from_line("not a block"), None);
+    }
+
+    #[test]
+    fn block_range_bounds() {
+        let range = BlockRange {
+            from: Some(10),
+            to: Some(20),
+        };
+        assert!(range.contains(10) && range.contains(20));
+        assert!(!range.contains(9) && !range.contains(21));
+        assert_eq!(
+            range.filter(),
+            Some(Bson::Document(doc! { "$gte": 10_i64, "$lte": 20_i64 }))
+        );
+        assert!(BlockRange::default().is_unbounded());
+        assert_eq!(BlockRange::default().filter(), None);
+    }
+
+    #[test]
+    fn latency_percentiles_use_nearest_rank() {
+        let latencies: Vec<i64> = (1..=100).rev().collect();
+        let stats = LatencyStats::from_latencies(&latencies).unwrap();
+        assert_eq!(
+            (stats.p50, stats.p95, stats.p99, stats.max),
+            (50, 95, 99, 100)
+        );
+        assert_eq!(stats.count, 100);
+
+        let single = LatencyStats::from_latencies(&[7]).unwrap();
+        assert_eq!((single.p50, single.p99, single.max), (7, 7, 7));
+        assert_eq!(LatencyStats::from_latencies(&[]), None);
+    }
+
+    #[test]
+    fn latency_report_keeps_worst_offenders_and_days() {
+        let mut report = LatencyReport::new(2);
+        for (i, latency) in [5, 300, 2, 40, 1000].iter().enumerate() {
+            let day = if i < 3 { "2026-10-01" } else { "2026-10-02" };
+            report.push(
+                LatencySample {
+                    id: i.to_string(),
+                    block_number: i as u64,
+                    latency: *latency,
+                },
+                day.to_string(),
+            );
+        }
+        let worst: Vec<i64> = report.worst_offenders().iter().map(|s| s.latency).collect();
+        assert_eq!(worst, vec![1000, 300]);
+
+        let days = report.daily_stats();
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].1.max, 300);
+        assert_eq!(days[1].1.count, 2);
+    }
+
+    fn ordering_record(
+        id: &str,
+        tx: &str,
+        block: u64,
+        log: i64,
+        created: i64,
+    ) -> EventOrderingRecord {
+        EventOrderingRecord {
+            id: id.to_string(),
+            tx_hash: tx.to_string(),
+            block_number: block,
+            log_index: log,
+            created_at: Some(created),
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn event_ordering_scan_finds_anomalies() {
+        let mut scan = EventOrderingScan::new();
+        scan.push(ordering_record("a", "0xAA", 10, 1, 1_000));
+        scan.push(ordering_record("b", "0xaa", 10, 4, 1_000));
+        scan.push(ordering_record("c", "0xbb", 10, 4, 1_001));
+        scan.push(ordering_record("d", "0xcc", 11, 0, 2_000));
+        // created before the documents of block 11
+        scan.push(ordering_record("e", "0xdd", 12, 0, 1_500));
+        scan.push(EventOrderingRecord {
+            version: 2,
+            ..ordering_record("f", "0xee", 12, 1, 3_000)
+        });
+        scan.finish();
+
+        assert_eq!(scan.created_out_of_order, vec!["e".to_string()]);
+        assert_eq!(
+            scan.log_index_conflicts,
+            vec![(10, 4, vec!["b".to_string(), "c".to_string()])]
+        );
+        assert_eq!(scan.log_index_gaps, vec![("0xaa".to_string(), vec![2, 3])]);
+        assert_eq!(scan.rewritten, vec!["f".to_string()]);
+    }
+
+    #[test]
+    fn tx_inclusion_status_compares_block_hash_and_log() {
+        let hash = B256::repeat_byte(1);
+        let other = B256::repeat_byte(2);
+        let verified = || EventVerificationStatus::Verified;
+        assert_eq!(
+            TxInclusionStatus::classify(10, hash, None, EventVerificationStatus::MissingReceipt),
+            TxInclusionStatus::Missing
+        );
+        assert_eq!(
+            TxInclusionStatus::classify(10, hash, Some((10, hash)), verified()),
+            TxInclusionStatus::Canonical
+        );
+        assert_eq!(
+            TxInclusionStatus::classify(10, hash, Some((11, other)), verified()),
+            TxInclusionStatus::Moved {
+                block_number: 11,
+                block_hash: other
+            }
+        );
+        // same height, but the receipt comes from a block that is no longer canonical
+        assert_eq!(
+            TxInclusionStatus::classify(10, other, Some((10, hash)), verified()),
+            TxInclusionStatus::Reorged {
+                canonical_hash: other,
+                block_hash: hash
+            }
+        );
+        // same block, but the transaction emitted its logs in another order
+        assert_eq!(
+            TxInclusionStatus::classify(
+                10,
+                hash,
+                Some((10, hash)),
+                EventVerificationStatus::MissingLog
+            ),
+            TxInclusionStatus::LogChanged(EventVerificationStatus::MissingLog)
+        );
+        // a receipt without logs has no inclusion to compare, only the missing log
+        assert_eq!(
+            TxInclusionStatus::classify(10, hash, None, EventVerificationStatus::MissingLog),
+            TxInclusionStatus::LogChanged(EventVerificationStatus::MissingLog)
+        );
+    }
+}
@@ -8,7 +8,28 @@ use crate::helpers::{
     get_token_scaled_borrow_amount, calculate_token_supply_amount, calculate_token_borrow_amount,
     find_user_scaled_position,
 };
+use crate::evm::{PoolEvents, ScaledTokenEvents, POOL_ADDRESS};
+use crate::constants::{INDEX_ROUNDING_TOLERANCE, RAY, STABLE_INTEREST_RATE_MODE};
+use crate::functions::{
+    compounded_interest, health_factor, linear_interest, normalize_address, ray_mul,
+    scaled_balance_delta, user_reserve_flags, MathError,
+};
+use crate::models::{
+    BorrowEvent, MoneyMarketEventDocument, ReserveDataUpdatedEvent, ReserveTokenDocument,
+    UserPositionDocument,
+};
+use crate::structs::{
+    AccountTotals, PricedReserve, EventMathCheck, EventMathStatus, IndexHistoryEntry, IndexStatus,
+    EventVerification, EventVerificationStatus, FieldMismatch, ReserveConfiguration, ReserveHealth,
+    ReserveReconciliation, ScaledDelta, TransactionCheck, UntrackedHolder, UserListCheck,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use alloy::primitives::{Address, U256};
+use alloy::rpc::types::Log;
+use alloy::sol_types::SolEvent;
 use futures::future::join_all;
+use mongodb::bson::Decimal128;
+use primitive_types::U256 as RayU256;
 // use mongodb::bson::Decimal128;
 
 // fn decimal128_to_u64_blocknumber(d: Decimal128) -> u64 {
@@ -412,3 +433,1554 @@ pub async fn validate_all_scaled_reserves()
 
     Ok(results)
 }
+
+/// Checks a stored money market event against the on-chain log at its `logIndex`.
+/// `logs` are the logs of the transaction receipt, `None` when the receipt was not found.
+pub fn verify_event_log(
+    event: &MoneyMarketEventDocument,
+    logs: Option<&[Log]>,
+    chain_id: u64,
+) -> EventVerification {
+    let mut verification = EventVerification {
+        id: event.id().to_hex(),
+        event_type: event.event_type().to_string(),
+        tx_hash: event.tx_hash().to_string(),
+        log_index: event.log_index(),
+        status: EventVerificationStatus::Verified,
+    };
+
+    let Some(logs) = logs else {
+        verification.status = EventVerificationStatus::MissingReceipt;
+        return verification;
+    };
+    let Some(log) = logs
+        .iter()
+        .find(|log| log.log_index.map(|index| index as i64) == Some(event.log_index()))
+    else {
+        verification.status = EventVerificationStatus::MissingLog;
+        return verification;
+    };
+
+    let mut mismatches = EventFieldChecks::default();
+    mismatches.value("chainId", event.chain_id(), chain_id);
+    if let Some(block_number) = log.block_number {
+        mismatches.value("blockNumber", event.block_number(), block_number);
+    }
+    if log.removed {
+        mismatches.value("removed", false, true);
+    }
+
+    let decoded =
+        match event {
+            MoneyMarketEventDocument::Supply(doc) => PoolEvents::Supply::decode_log(&log.inner)
+                .map(|decoded| {
+                    mismatches.address("contract", POOL_ADDRESS, decoded.address);
+                    mismatches.address("reserve", &doc.reserve, decoded.reserve);
+                    mismatches.address("user", &doc.user, decoded.user);
+                    mismatches.address("onBehalfOf", &doc.onBehalfOf, decoded.onBehalfOf);
+                    mismatches.amount("amount", &doc.amount, decoded.amount);
+                    mismatches.value("referralCode", doc.referralCode, decoded.referralCode);
+                }),
+            MoneyMarketEventDocument::Withdraw(doc) => PoolEvents::Withdraw::decode_log(&log.inner)
+                .map(|decoded| {
+                    mismatches.address("contract", POOL_ADDRESS, decoded.address);
+                    mismatches.address("reserve", &doc.reserve, decoded.reserve);
+                    mismatches.address("user", &doc.user, decoded.user);
+                    mismatches.address("to", &doc.to, decoded.to);
+                    mismatches.amount("amount", &doc.amount, decoded.amount);
+                }),
+            MoneyMarketEventDocument::Borrow(doc) => PoolEvents::Borrow::decode_log(&log.inner)
+                .map(|decoded| {
+                    mismatches.address("contract", POOL_ADDRESS, decoded.address);
+                    mismatches.address("reserve", &doc.reserve, decoded.reserve);
+                    mismatches.address("user", &doc.user, decoded.user);
+                    mismatches.address("onBehalfOf", &doc.onBehalfOf, decoded.onBehalfOf);
+                    mismatches.amount("amount", &doc.amount, decoded.amount);
+                    mismatches.value(
+                        "interestRateMode",
+                        doc.interestRateMode,
+                        decoded.interestRateMode,
+                    );
+                    mismatches.amount("borrowRate", &doc.borrowRate, decoded.borrowRate);
+                    mismatches.value("referralCode", doc.referralCode, decoded.referralCode);
+                }),
+            MoneyMarketEventDocument::Repay(doc) => {
+                PoolEvents::Repay::decode_log(&log.inner).map(|decoded| {
+                    mismatches.address("contract", POOL_ADDRESS, decoded.address);
+                    mismatches.address("reserve", &doc.reserve, decoded.reserve);
+                    mismatches.address("user", &doc.user, decoded.user);
+                    mismatches.address("repayer", &doc.repayer, decoded.repayer);
+                    mismatches.amount("amount", &doc.amount, decoded.amount);
+                    mismatches.value("useATokens", doc.useATokens, decoded.useATokens);
+                })
+            }
+            MoneyMarketEventDocument::ReserveDataUpdated(doc) => {
+                PoolEvents::ReserveDataUpdated::decode_log(&log.inner).map(|decoded| {
+                    mismatches.address("contract", POOL_ADDRESS, decoded.address);
+                    mismatches.address("reserve", &doc.reserve, decoded.reserve);
+                    mismatches.amount("liquidityRate", &doc.liquidityRate, decoded.liquidityRate);
+                    mismatches.amount(
+                        "stableBorrowRate",
+                        &doc.stableBorrowRate,
+                        decoded.stableBorrowRate,
+                    );
+                    mismatches.amount(
+                        "variableBorrowRate",
+                        &doc.variableBorrowRate,
+                        decoded.variableBorrowRate,
+                    );
+                    mismatches.amount(
+                        "liquidityIndex",
+                        &doc.liquidityIndex,
+                        decoded.liquidityIndex,
+                    );
+                    mismatches.amount(
+                        "variableBorrowIndex",
+                        &doc.variableBorrowIndex,
+                        decoded.variableBorrowIndex,
+                    );
+                })
+            }
+            MoneyMarketEventDocument::ATokenMint(doc) => {
+                ScaledTokenEvents::Mint::decode_log(&log.inner).map(|decoded| {
+                    mismatches.address("contract", &doc.tokenAddress, decoded.address);
+                    mismatches.address("caller", &doc.caller, decoded.caller);
+                    mismatches.address("onBehalfOf", &doc.onBehalfOf, decoded.onBehalfOf);
+                    mismatches.amount("value", &doc.value, decoded.value);
+                    mismatches.amount(
+                        "balanceIncrease",
+                        &doc.balanceIncrease,
+                        decoded.balanceIncrease,
+                    );
+                    mismatches.amount("index", &doc.index, decoded.index);
+                })
+            }
+            MoneyMarketEventDocument::DebtTokenMint(doc) => {
+                ScaledTokenEvents::Mint::decode_log(&log.inner).map(|decoded| {
+                    mismatches.address("contract", &doc.tokenAddress, decoded.address);
+                    mismatches.address("caller", &doc.caller, decoded.caller);
+                    mismatches.address("onBehalfOf", &doc.onBehalfOf, decoded.onBehalfOf);
+                    mismatches.amount("value", &doc.value, decoded.value);
+                    mismatches.amount(
+                        "balanceIncrease",
+                        &doc.balanceIncrease,
+                        decoded.balanceIncrease,
+                    );
+                    mismatches.amount("index", &doc.index, decoded.index);
+                })
+            }
+            MoneyMarketEventDocument::ATokenBurn(doc) => {
+                ScaledTokenEvents::Burn::decode_log(&log.inner).map(|decoded| {
+                    mismatches.address("contract", &doc.tokenAddress, decoded.address);
+                    mismatches.address("from", &doc.from, decoded.from);
+                    mismatches.address("target", &doc.target, decoded.target);
+                    mismatches.amount("value", &doc.value, decoded.value);
+                    mismatches.amount(
+                        "balanceIncrease",
+                        &doc.balanceIncrease,
+                        decoded.balanceIncrease,
+                    );
+                    mismatches.amount("index", &doc.index, decoded.index);
+                })
+            }
+            MoneyMarketEventDocument::DebtTokenBurn(doc) => {
+                ScaledTokenEvents::Burn::decode_log(&log.inner).map(|decoded| {
+                    mismatches.address("contract", &doc.tokenAddress, decoded.address);
+                    mismatches.address("from", &doc.from, decoded.from);
+                    mismatches.address("target", &doc.target, decoded.target);
+                    mismatches.amount("value", &doc.value, decoded.value);
+                    mismatches.amount(
+                        "balanceIncrease",
+                        &doc.balanceIncrease,
+                        decoded.balanceIncrease,
+                    );
+                    mismatches.amount("index", &doc.index, decoded.index);
+                })
+            }
+            MoneyMarketEventDocument::ATokenBalanceTransfer(doc) => {
+                ScaledTokenEvents::BalanceTransfer::decode_log(&log.inner).map(|decoded| {
+                    mismatches.address("contract", &doc.tokenAddress, decoded.address);
+                    mismatches.address("from", &doc.from, decoded.from);
+                    mismatches.address("to", &doc.to, decoded.to);
+                    mismatches.amount("value", &doc.value, decoded.value);
+                    mismatches.amount("index", &doc.index, decoded.index);
+                })
+            }
+            MoneyMarketEventDocument::ATokenTransfer(doc) => {
+                ScaledTokenEvents::Transfer::decode_log(&log.inner).map(|decoded| {
+                    mismatches.address("contract", &doc.tokenAddress, decoded.address);
+                    mismatches.address("from", &doc.from, decoded.from);
+                    mismatches.address("to", &doc.to, decoded.to);
+                    mismatches.amount("value", &doc.value, decoded.value);
+                })
+            }
+        };
+
+    // the log at this index is a different event
+    if decoded.is_err() {
+        mismatches.value(
+            "event signature",
+            event.event_type(),
+            log.topic0()
+                .map(|topic| topic.to_string())
+                .unwrap_or_else(|| "anonymous log".to_string()),
+        );
+    }
+
+    if !mismatches.0.is_empty() {
+        verification.status = EventVerificationStatus::Mismatch(mismatches.0);
+    }
+    verification
+}
+
+/// Checks the amounts of a Mint / Burn event and compares its index with the
+/// ReserveDataUpdated events of the same transaction (`tx_events`), and the underlying amount
+/// derived from `value` and `balanceIncrease` with the Supply / Withdraw / Borrow / Repay of
+/// the same user and reserve, when the transaction has one. `reserve` is the reserve the
+/// event's token belongs to. Returns `None` for other event types.
+pub fn check_event_math(
+    event: &MoneyMarketEventDocument,
+    reserve: Option<&str>,
+    tx_events: &[MoneyMarketEventDocument],
+) -> Option<EventMathCheck> {
+    let (is_mint, is_debt, user, value, balance_increase, index) = match event {
+        MoneyMarketEventDocument::ATokenMint(e) => (
+            true,
+            false,
+            &e.onBehalfOf,
+            &e.value,
+            &e.balanceIncrease,
+            &e.index,
+        ),
+        MoneyMarketEventDocument::ATokenBurn(e) => (
+            false,
+            false,
+            &e.from,
+            &e.value,
+            &e.balanceIncrease,
+            &e.index,
+        ),
+        MoneyMarketEventDocument::DebtTokenMint(e) => (
+            true,
+            true,
+            &e.onBehalfOf,
+            &e.value,
+            &e.balanceIncrease,
+            &e.index,
+        ),
+        MoneyMarketEventDocument::DebtTokenBurn(e) => {
+            (false, true, &e.from, &e.value, &e.balanceIncrease, &e.index)
+        }
+        _ => return None,
+    };
+
+    let mut check = EventMathCheck {
+        id: event.id().to_hex(),
+        event_type: event.event_type().to_string(),
+        tx_hash: event.tx_hash().to_string(),
+        log_index: event.log_index(),
+        scaled_delta: None,
+        status: EventMathStatus::Consistent,
+    };
+    let mut issues: Vec<String> = vec![];
+
+    let parse = |field: &str, amount: &Decimal128, issues: &mut Vec<String>| {
+        let parsed = RayU256::from_dec_str(&amount.to_string()).ok();
+        if parsed.is_none() {
+            issues.push(format!("{} {} is not an unsigned integer", field, amount));
+        }
+        parsed
+    };
+    let value = parse("value", value, &mut issues);
+    let balance_increase = parse("balanceIncrease", balance_increase, &mut issues);
+    let index = parse("index", index, &mut issues);
+
+    // indexes start at RAY and only grow
+    if let Some(index) = index
+        && index < RayU256::from(RAY)
+    {
+        issues.push(format!("index {} is below 1 ray", index));
+    }
+
+    if let (Some(value), Some(balance_increase), Some(index)) = (value, balance_increase, index) {
+        match scaled_balance_delta(is_mint, value, balance_increase, index) {
+            Ok((delta, amount)) => {
+                let scaled = match delta {
+                    ScaledDelta::Increase(scaled) | ScaledDelta::Decrease(scaled) => scaled,
+                };
+                if !is_mint && amount.is_zero() {
+                    issues.push("burn of a zero amount".to_string());
+                } else if !amount.is_zero() && scaled.is_zero() {
+                    // the token contract reverts on a zero scaled amount
+                    issues.push(format!("amount {} rounds to a zero scaled amount", amount));
+                }
+                let is_increase = matches!(delta, ScaledDelta::Increase(_));
+                if !amount.is_zero()
+                    && let Some(reserve) = reserve
+                {
+                    let (action, pool_amounts) =
+                        pool_action_amounts(tx_events, reserve, user, is_debt, is_increase);
+                    if !pool_amounts.is_empty() && !pool_amounts.contains(&amount) {
+                        let pool_amounts: Vec<String> =
+                            pool_amounts.iter().map(|a| a.to_string()).collect();
+                        issues.push(format!(
+                            "amount {} (scaled {}) differs from the {} amount ({})",
+                            amount,
+                            scaled,
+                            action,
+                            pool_amounts.join(", ")
+                        ));
+                    }
+                }
+                check.scaled_delta = Some(delta);
+            }
+            Err(e) => issues.push(format!("scaled delta cannot be computed: {:?}", e)),
+        }
+    }
+
+    let (index_field, token_kind) = if is_debt {
+        ("variableBorrowIndex", "debt token")
+    } else {
+        ("liquidityIndex", "aToken")
+    };
+    let reserve_indexes: Vec<RayU256> = match reserve.and_then(normalize_address) {
+        Some(reserve) => tx_events
+            .iter()
+            .filter_map(|tx_event| match tx_event {
+                MoneyMarketEventDocument::ReserveDataUpdated(update)
+                    if normalize_address(&update.reserve).as_deref() == Some(reserve.as_str()) =>
+                {
+                    let reserve_index = if is_debt {
+                        &update.variableBorrowIndex
+                    } else {
+                        &update.liquidityIndex
+                    };
+                    RayU256::from_dec_str(&reserve_index.to_string()).ok()
+                }
+                _ => None,
+            })
+            .collect(),
+        None => {
+            issues.push(format!(
+                "token {} is not the {} of a known reserve",
+                event.token(),
+                token_kind
+            ));
+            vec![]
+        }
+    };
+    if let Some(index) = index
+        && !reserve_indexes.is_empty()
+        && !reserve_indexes.contains(&index)
+    {
+        let reserve_indexes: Vec<String> = reserve_indexes.iter().map(|i| i.to_string()).collect();
+        issues.push(format!(
+            "index {} differs from the ReserveDataUpdated {} ({})",
+            index,
+            index_field,
+            reserve_indexes.join(", ")
+        ));
+    }
+
+    check.status = if !issues.is_empty() {
+        EventMathStatus::Inconsistent(issues)
+    } else if reserve_indexes.is_empty() {
+        EventMathStatus::NoReserveUpdate
+    } else {
+        EventMathStatus::Consistent
+    };
+    Some(check)
+}
+
+// Amounts of the pool events of `tx_events` that move the balance of `user` on `reserve` the
+// way a Mint / Burn does: Supply or Withdraw / aToken Repay for aTokens, Borrow or Repay for
+// debt tokens
+fn pool_action_amounts(
+    tx_events: &[MoneyMarketEventDocument],
+    reserve: &str,
+    user: &str,
+    is_debt: bool,
+    is_increase: bool,
+) -> (&'static str, Vec<RayU256>) {
+    let same = |a: &str, b: &str| {
+        normalize_address(a).is_some() && normalize_address(a) == normalize_address(b)
+    };
+    let action = match (is_debt, is_increase) {
+        (false, true) => "Supply",
+        (false, false) => "Withdraw",
+        (true, true) => "Borrow",
+        (true, false) => "Repay",
+    };
+    let amounts = tx_events
+        .iter()
+        .filter_map(|tx_event| {
+            let amount = match (tx_event, is_debt, is_increase) {
+                (MoneyMarketEventDocument::Supply(e), false, true)
+                    if same(&e.reserve, reserve) && same(&e.onBehalfOf, user) =>
+                {
+                    &e.amount
+                }
+                (MoneyMarketEventDocument::Withdraw(e), false, false)
+                    if same(&e.reserve, reserve) && same(&e.user, user) =>
+                {
+                    &e.amount
+                }
+                // a repayment with aTokens burns them for the repaid amount
+                (MoneyMarketEventDocument::Repay(e), false, false)
+                    if e.useATokens && same(&e.reserve, reserve) && same(&e.user, user) =>
+                {
+                    &e.amount
+                }
+                (MoneyMarketEventDocument::Borrow(e), true, true)
+                    if same(&e.reserve, reserve) && same(&e.onBehalfOf, user) =>
+                {
+                    &e.amount
+                }
+                (MoneyMarketEventDocument::Repay(e), true, false)
+                    if same(&e.reserve, reserve) && same(&e.user, user) =>
+                {
+                    &e.amount
+                }
+                _ => return None,
+            };
+            RayU256::from_dec_str(&amount.to_string()).ok()
+        })
+        .collect();
+    (action, amounts)
+}
+
+/// Labels the user action behind the events of one transaction and lists the companion
+/// events Aave emits with it that are missing. `reserve_tokens` maps a lowercase reserve
+/// address to its lowercase (aToken, variable debt token) addresses.
+pub fn check_transaction_events(
+    events: &[MoneyMarketEventDocument],
+    reserve_tokens: &HashMap<String, (String, String)>,
+) -> TransactionCheck {
+    let same = |a: &str, b: &str| {
+        normalize_address(a).is_some() && normalize_address(a) == normalize_address(b)
+    };
+    // tokens are only compared when the reserve is known
+    let is_token = |token: &str, reserve: &str, debt: bool| match normalize_address(reserve)
+        .and_then(|reserve| reserve_tokens.get(&reserve))
+    {
+        Some((a_token, debt_token)) => same(token, if debt { debt_token } else { a_token }),
+        None => true,
+    };
+    let has_reserve_update = |reserve: &str| {
+        events.iter().any(|event| {
+            matches!(event, MoneyMarketEventDocument::ReserveDataUpdated(update) if same(&update.reserve, reserve))
+        })
+    };
+    // a burn smaller than the accrued interest is emitted as a Mint
+    let has_burn = |reserve: &str, user: &str, debt: bool| {
+        events.iter().any(|event| match event {
+            MoneyMarketEventDocument::ATokenBurn(e) if !debt => {
+                same(&e.from, user) && is_token(&e.tokenAddress, reserve, debt)
+            }
+            MoneyMarketEventDocument::ATokenMint(e) if !debt => {
+                same(&e.onBehalfOf, user) && is_token(&e.tokenAddress, reserve, debt)
+            }
+            MoneyMarketEventDocument::DebtTokenBurn(e) if debt => {
+                same(&e.from, user) && is_token(&e.tokenAddress, reserve, debt)
+            }
+            MoneyMarketEventDocument::DebtTokenMint(e) if debt => {
+                same(&e.onBehalfOf, user) && is_token(&e.tokenAddress, reserve, debt)
+            }
+            _ => false,
+        })
+    };
+    let has_mint = |reserve: &str, user: &str, debt: bool| {
+        events.iter().any(|event| match event {
+            MoneyMarketEventDocument::ATokenMint(e) if !debt => {
+                same(&e.onBehalfOf, user) && is_token(&e.tokenAddress, reserve, debt)
+            }
+            MoneyMarketEventDocument::DebtTokenMint(e) if debt => {
+                same(&e.onBehalfOf, user) && is_token(&e.tokenAddress, reserve, debt)
+            }
+            _ => false,
+        })
+    };
+
+    let mut actions: Vec<&str> = vec![];
+    let mut missing: Vec<String> = vec![];
+    for event in events {
+        let (reserve, action) = match event {
+            MoneyMarketEventDocument::Supply(e) => {
+                if !has_mint(&e.reserve, &e.onBehalfOf, false) {
+                    missing.push(format!("a-token-mint for {} (supply)", e.onBehalfOf));
+                }
+                (&e.reserve, "supply")
+            }
+            MoneyMarketEventDocument::Withdraw(e) => {
+                if !has_burn(&e.reserve, &e.user, false) {
+                    missing.push(format!("a-token-burn for {} (withdraw)", e.user));
+                }
+                (&e.reserve, "withdraw")
+            }
+            MoneyMarketEventDocument::Borrow(e) => {
+                if !has_mint(&e.reserve, &e.onBehalfOf, true) {
+                    missing.push(format!("debt-token-mint for {} (borrow)", e.onBehalfOf));
+                }
+                (&e.reserve, "borrow")
+            }
+            MoneyMarketEventDocument::Repay(e) => {
+                if !has_burn(&e.reserve, &e.user, true) {
+                    missing.push(format!("debt-token-burn for {} (repay)", e.user));
+                }
+                if e.useATokens && !has_burn(&e.reserve, &e.repayer, false) {
+                    missing.push(format!(
+                        "a-token-burn for {} (repay with aTokens)",
+                        e.repayer
+                    ));
+                }
+                let action = if e.useATokens {
+                    "repay with aTokens"
+                } else {
+                    "repay"
+                };
+                (&e.reserve, action)
+            }
+            _ => continue,
+        };
+        if !has_reserve_update(reserve) {
+            missing.push(format!("reserve-data-updated for {} ({})", reserve, action));
+        }
+        actions.push(action);
+    }
+
+    // transactions without an indexed pool event
+    if actions.is_empty() {
+        let has_event =
+            |event_type: &str| events.iter().any(|event| event.event_type() == event_type);
+        let action = if has_event("debt-token-burn")
+            && (has_event("a-token-burn") || has_event("a-token-balance-transfer"))
+        {
+            "liquidation (inferred)"
+        } else if has_event("a-token-balance-transfer") {
+            "aToken transfer"
+        } else if events
+            .iter()
+            .all(|event| event.event_type() == "reserve-data-updated")
+        {
+            "reserve update"
+        } else {
+            "token events"
+        };
+        actions.push(action);
+    }
+
+    TransactionCheck {
+        tx_hash: events
+            .first()
+            .map(|e| e.tx_hash().to_string())
+            .unwrap_or_default(),
+        block_number: events.first().map(|e| e.block_number()).unwrap_or_default(),
+        action: actions.join(" + "),
+        missing_companions: missing,
+    }
+}
+
+/// Reconciliations of the reserve documents' totals with no user position counted yet, and
+/// the reserves that can't be reconciled. `user_positions` documents are then added one at a
+/// time with `reconcile_user_positions`, the position of `treasury` kept apart.
+pub fn reserve_reconciliations(
+    reserves: &[ReserveTokenDocument],
+    treasury: Option<&str>,
+) -> (Vec<ReserveReconciliation>, Vec<String>) {
+    let parse = |value: &Decimal128| RayU256::from_dec_str(&value.to_string()).ok();
+    let mut issues: Vec<String> = vec![];
+    let mut reconciliations: Vec<ReserveReconciliation> = vec![];
+    for reserve in reserves {
+        let (Some(total_supply), Some(total_debt)) = (
+            parse(&reserve.totalATokenBalance),
+            parse(&reserve.totalVariableDebtTokenBalance),
+        ) else {
+            issues.push(format!(
+                "reserve {} has non-integer totals",
+                reserve.reserveAddress
+            ));
+            continue;
+        };
+        reconciliations.push(ReserveReconciliation {
+            reserve: reserve.reserveAddress.clone(),
+            symbol: reserve.symbol.clone(),
+            total_supply,
+            total_debt,
+            treasury: treasury.and_then(normalize_address),
+            ..Default::default()
+        });
+    }
+    (reconciliations, issues)
+}
+
+/// Adds the scaled aToken and debt balances of a user to the reconciliations of its
+/// reserves, keeping the treasury apart. Positions that can't be counted (unknown reserve or
+/// non-integer balance) are added to `issues`.
+pub fn reconcile_user_positions(
+    reconciliations: &mut [ReserveReconciliation],
+    user: &UserPositionDocument,
+    issues: &mut Vec<String>,
+) {
+    let parse = |value: &Decimal128| RayU256::from_dec_str(&value.to_string()).ok();
+    let user_address = normalize_address(&user.userAddress);
+    for position in &user.positions {
+        let Some(reconciliation) = reconciliations.iter_mut().find(|reconciliation| {
+            reconciliation
+                .reserve
+                .eq_ignore_ascii_case(&position.reserveAddress)
+        }) else {
+            issues.push(format!(
+                "user {} has a position in unknown reserve {}",
+                user.userAddress, position.reserveAddress
+            ));
+            continue;
+        };
+        let (Some(supply), Some(debt)) = (
+            parse(&position.aTokenBalance),
+            parse(&position.variableDebtTokenBalance),
+        ) else {
+            issues.push(format!(
+                "user {} has non-integer balances in reserve {}",
+                user.userAddress, position.reserveAddress
+            ));
+            continue;
+        };
+        if reconciliation.treasury.is_some() && reconciliation.treasury == user_address {
+            reconciliation.treasury_supply = Some(supply);
+        } else {
+            reconciliation.users_supply = reconciliation.users_supply.saturating_add(supply);
+            if !supply.is_zero() {
+                reconciliation.suppliers += 1;
+            }
+        }
+        reconciliation.users_debt = reconciliation.users_debt.saturating_add(debt);
+        if !debt.is_zero() {
+            reconciliation.borrowers += 1;
+        }
+    }
+}
+
+/// Adds a user to the lowercase addresses with a non-zero scaled aToken and debt token
+/// balance per lowercase reserve address, according to its `user_positions` document
+pub fn add_position_holders(
+    holders: &mut HashMap<String, (BTreeSet<String>, BTreeSet<String>)>,
+    user: &UserPositionDocument,
+) {
+    let is_zero = |value: &Decimal128| {
+        RayU256::from_dec_str(&value.to_string()).is_ok_and(|value| value.is_zero())
+    };
+    let address = list_address(&user.userAddress);
+    for position in &user.positions {
+        let (suppliers, borrowers) = holders
+            .entry(position.reserveAddress.to_lowercase())
+            .or_default();
+        if !is_zero(&position.aTokenBalance) {
+            suppliers.insert(address.clone());
+        }
+        if !is_zero(&position.variableDebtTokenBalance) {
+            borrowers.insert(address.clone());
+        }
+    }
+}
+
+/// Compares a `suppliers` / `borrowers` list with the lowercase addresses holding the
+/// matching token
+pub fn check_user_list(list: &str, listed: &[String], holders: &BTreeSet<String>) -> UserListCheck {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for address in listed {
+        *counts.entry(list_address(address)).or_default() += 1;
+    }
+    UserListCheck {
+        list: list.to_string(),
+        missing: holders
+            .iter()
+            .filter(|holder| !counts.contains_key(*holder))
+            .cloned()
+            .collect(),
+        stale: counts
+            .keys()
+            .filter(|address| !holders.contains(*address))
+            .cloned()
+            .collect(),
+        duplicates: counts
+            .iter()
+            .filter(|(_, count)| **count > 1)
+            .map(|(address, _)| address.clone())
+            .collect(),
+    }
+}
+
+/// Lowercase recipients of the ERC20 Transfer logs per lowercase token address. Mints are
+/// transfers from the zero address, so every holder of an aToken or debt token is among them.
+pub fn transfer_recipients(logs: &[Log]) -> HashMap<String, BTreeSet<String>> {
+    let mut recipients: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for log in logs {
+        let Ok(transfer) = ScaledTokenEvents::Transfer::decode_log(&log.inner) else {
+            continue;
+        };
+        if transfer.to != Address::ZERO {
+            recipients
+                .entry(format!("{:#x}", log.address()))
+                .or_default()
+                .insert(format!("{:#x}", transfer.to));
+        }
+    }
+    recipients
+}
+
+/// Lowercase address of a user and the lowercase reserves of its `user_positions` document
+pub fn tracked_reserves(user: &UserPositionDocument) -> (String, BTreeSet<String>) {
+    (
+        list_address(&user.userAddress),
+        user.positions
+            .iter()
+            .map(|position| position.reserveAddress.to_lowercase())
+            .collect(),
+    )
+}
+
+/// Candidates (lowercase addresses) without a position in `reserve` in `user_positions`,
+/// given the `tracked_reserves` of every user
+pub fn find_untracked_holders(
+    reserve: &str,
+    candidates: &BTreeSet<String>,
+    tracked: &HashMap<String, BTreeSet<String>>,
+) -> Vec<UntrackedHolder> {
+    let reserve = reserve.to_lowercase();
+    candidates
+        .iter()
+        .filter_map(|address| match tracked.get(address) {
+            Some(reserves) if reserves.contains(&reserve) => None,
+            Some(_) => Some(UntrackedHolder {
+                address: address.clone(),
+                has_user_position: true,
+            }),
+            None => Some(UntrackedHolder {
+                address: address.clone(),
+                has_user_position: false,
+            }),
+        })
+        .collect()
+}
+
+/// Checks that both indexes of a reserve's ReserveDataUpdated events, given in (blockNumber,
+/// logIndex) order, never decrease and grow by the rates of the previous event over the
+/// elapsed time: linear interest for the liquidity index, compounded interest for the borrow
+/// index. `timestamps` maps block numbers to block timestamps.
+pub fn check_index_history(
+    events: &[ReserveDataUpdatedEvent],
+    timestamps: &HashMap<u64, u64>,
+) -> Vec<IndexHistoryEntry> {
+    let parse = |value: &Decimal128| RayU256::from_dec_str(&value.to_string()).unwrap_or_default();
+    let mut entries: Vec<IndexHistoryEntry> = vec![];
+    for event in events {
+        let mut entry = IndexHistoryEntry {
+            block_number: event.common.blockNumber,
+            log_index: event.common.logIndex,
+            tx_hash: event.common.txHash.clone(),
+            timestamp: timestamps.get(&event.common.blockNumber).copied(),
+            liquidity_rate: parse(&event.liquidityRate),
+            variable_borrow_rate: parse(&event.variableBorrowRate),
+            liquidity_index: parse(&event.liquidityIndex),
+            variable_borrow_index: parse(&event.variableBorrowIndex),
+            liquidity_index_status: IndexStatus::First,
+            borrow_index_status: IndexStatus::First,
+        };
+        if let Some(previous) = entries.last() {
+            let elapsed = entry
+                .timestamp
+                .zip(previous.timestamp)
+                .map(|(current, previous)| current.saturating_sub(previous));
+            entry.liquidity_index_status = index_status(
+                previous.liquidity_index,
+                entry.liquidity_index,
+                elapsed.map(|elapsed| linear_interest(previous.liquidity_rate, elapsed)),
+                false,
+            );
+            entry.borrow_index_status = index_status(
+                previous.variable_borrow_index,
+                entry.variable_borrow_index,
+                elapsed.map(|elapsed| compounded_interest(previous.variable_borrow_rate, elapsed)),
+                true,
+            );
+        }
+        entries.push(entry);
+    }
+    entries
+}
+
+// `growth` is the interest factor (in ray) since the previous index, `None` without timestamps.
+// `may_stay` accepts an index that didn't move, Aave skips the borrow index while there is no debt.
+fn index_status(
+    previous: RayU256,
+    current: RayU256,
+    growth: Option<Result<RayU256, MathError>>,
+    may_stay: bool,
+) -> IndexStatus {
+    if current < previous {
+        return IndexStatus::Decreased;
+    }
+    let Some(growth) = growth else {
+        return IndexStatus::NoTimestamp;
+    };
+    let expected = growth
+        .and_then(|growth| ray_mul(growth, previous))
+        .unwrap_or_default();
+    let difference = if expected > current {
+        expected - current
+    } else {
+        current - expected
+    };
+    if difference <= RayU256::from(INDEX_ROUNDING_TOLERANCE) {
+        IndexStatus::Consistent
+    } else if may_stay && current == previous {
+        IndexStatus::Unchanged
+    } else {
+        IndexStatus::Mismatch { expected }
+    }
+}
+
+/// Number of stable rate borrows per borrower (`onBehalfOf`, lowercase)
+pub fn stable_borrowers(borrows: &[BorrowEvent]) -> BTreeMap<String, usize> {
+    let mut borrowers: BTreeMap<String, usize> = BTreeMap::new();
+    for borrow in borrows
+        .iter()
+        .filter(|borrow| borrow.interestRateMode == STABLE_INTEREST_RATE_MODE)
+    {
+        *borrowers
+            .entry(list_address(&borrow.onBehalfOf))
+            .or_default() += 1;
+    }
+    borrowers
+}
+
+/// Problems with the configuration of a reserve: supply or borrow above its cap (amounts in
+/// underlying units), or indexed activity that the reserve status forbids. `activity` counts
+/// the indexed events of the reserve by event type.
+pub fn check_reserve_configuration(
+    configuration: &ReserveConfiguration,
+    total_supply: RayU256,
+    total_debt: RayU256,
+    activity: &BTreeMap<&str, usize>,
+) -> Vec<String> {
+    let mut issues = vec![];
+    let unit = RayU256::exp10(configuration.decimals as usize);
+    for (name, amount, cap) in [
+        ("supply", total_supply, configuration.supply_cap),
+        ("borrow", total_debt, configuration.borrow_cap),
+    ] {
+        if cap != 0 && amount > RayU256::from(cap) * unit {
+            issues.push(format!(
+                "total {} {} exceeds the {} cap of {} tokens",
+                name, amount, name, cap
+            ));
+        }
+    }
+
+    let count = |event_types: &[&str]| -> usize {
+        event_types
+            .iter()
+            .map(|event_type| activity.get(event_type).copied().unwrap_or_default())
+            .sum()
+    };
+    let all_activity: usize = activity
+        .iter()
+        .filter(|(event_type, _)| **event_type != "reserve-data-updated")
+        .map(|(_, count)| count)
+        .sum();
+    if !configuration.active && all_activity > 0 {
+        issues.push(format!(
+            "the reserve is inactive but {} events were indexed",
+            all_activity
+        ));
+    } else if configuration.paused && all_activity > 0 {
+        issues.push(format!(
+            "the reserve is paused but {} events were indexed",
+            all_activity
+        ));
+    } else if configuration.frozen && count(&["supply", "borrow"]) > 0 {
+        issues.push(format!(
+            "the reserve is frozen but {} supply / borrow events were indexed",
+            count(&["supply", "borrow"])
+        ));
+    }
+    if !configuration.borrowing_enabled && count(&["borrow"]) > 0 {
+        issues.push(format!(
+            "borrowing is disabled but {} borrow events were indexed",
+            count(&["borrow"])
+        ));
+    }
+    issues
+}
+
+/// Problems in the reserve-level accounting: a total supply that isn't the sum of the users,
+/// the treasury and its accrued share, isolation mode debt above the debt ceiling (or left
+/// over without one), and unbacked supply above the unbacked mint cap
+pub fn check_reserve_health(health: &ReserveHealth) -> Vec<String> {
+    let mut issues = vec![];
+    let residual = health.supply_residual();
+    if residual != ScaledDelta::Increase(RayU256::zero()) {
+        issues.push(format!(
+            "scaled total supply {} differs from users {} + treasury {} by {}",
+            health.scaled_total_supply, health.users_supply, health.treasury_supply, residual
+        ));
+    }
+
+    let configuration = &health.configuration;
+    if configuration.debt_ceiling != 0 {
+        if health.isolation_mode_total_debt > RayU256::from(configuration.debt_ceiling) {
+            issues.push(format!(
+                "isolation mode debt {} exceeds the debt ceiling {}",
+                health.isolation_mode_total_debt, configuration.debt_ceiling
+            ));
+        }
+    } else if !health.isolation_mode_total_debt.is_zero() {
+        issues.push(format!(
+            "isolation mode debt {} without a debt ceiling",
+            health.isolation_mode_total_debt
+        ));
+    }
+
+    let unbacked_cap = RayU256::from(configuration.unbacked_mint_cap)
+        * RayU256::exp10(configuration.decimals as usize);
+    if health.unbacked > unbacked_cap {
+        issues.push(format!(
+            "unbacked {} exceeds the unbacked mint cap of {} tokens",
+            health.unbacked, configuration.unbacked_mint_cap
+        ));
+    }
+    issues
+}
+
+/// Collateral, debt and health factor of a user recomputed the way `getUserAccountData` does
+/// from its `user_positions` balances, with the reserves keyed by lowercase address.
+/// `stable_debt` holds the on-chain stable debt of the user per lowercase reserve address, in
+/// underlying units, which `user_positions` doesn't track. Returns the totals and the positions
+/// that couldn't be valued.
+pub fn recompute_account_totals(
+    user: &UserPositionDocument,
+    reserves: &HashMap<String, PricedReserve>,
+    stable_debt: &HashMap<String, RayU256>,
+    user_configuration: RayU256,
+) -> (AccountTotals, Vec<String>) {
+    let parse = |value: &Decimal128| RayU256::from_dec_str(&value.to_string()).ok();
+    let mut totals = AccountTotals::default();
+    let mut weighted_threshold = RayU256::zero();
+    let mut issues = vec![];
+    for position in &user.positions {
+        let Some(reserve) = reserves.get(&position.reserveAddress.to_lowercase()) else {
+            issues.push(format!("no price for reserve {}", position.reserveAddress));
+            continue;
+        };
+        let (Some(supply), Some(debt)) = (
+            parse(&position.aTokenBalance),
+            parse(&position.variableDebtTokenBalance),
+        ) else {
+            issues.push(format!(
+                "non-integer balances in reserve {}",
+                position.reserveAddress
+            ));
+            continue;
+        };
+        let unit = RayU256::exp10(reserve.decimals as usize);
+        let value = |scaled: RayU256, index: RayU256| {
+            ray_mul(scaled, index).map(|amount| amount * reserve.price / unit)
+        };
+        let (borrowing, collateral) = user_reserve_flags(user_configuration, reserve.id);
+
+        if collateral && reserve.liquidation_threshold != 0 && !supply.is_zero() {
+            match value(supply, reserve.liquidity_index) {
+                Ok(collateral_base) => {
+                    totals.collateral_base += collateral_base;
+                    weighted_threshold +=
+                        collateral_base * RayU256::from(reserve.liquidation_threshold);
+                }
+                Err(e) => issues.push(format!(
+                    "supply of reserve {} overflows: {:?}",
+                    position.reserveAddress, e
+                )),
+            }
+        }
+        if !debt.is_zero() {
+            if !borrowing {
+                issues.push(format!(
+                    "debt in reserve {} that the user configuration doesn't flag as borrowed",
+                    position.reserveAddress
+                ));
+            }
+            match value(debt, reserve.variable_borrow_index) {
+                Ok(debt_base) => totals.debt_base += debt_base,
+                Err(e) => issues.push(format!(
+                    "debt of reserve {} overflows: {:?}",
+                    position.reserveAddress, e
+                )),
+            }
+        }
+    }
+    for (reserve_address, amount) in stable_debt.iter().filter(|(_, amount)| !amount.is_zero()) {
+        let Some(reserve) = reserves.get(reserve_address) else {
+            issues.push(format!("no price for reserve {}", reserve_address));
+            continue;
+        };
+        let (borrowing, _) = user_reserve_flags(user_configuration, reserve.id);
+        if !borrowing {
+            issues.push(format!(
+                "stable debt in reserve {} that the user configuration doesn't flag as borrowed",
+                reserve_address
+            ));
+        }
+        totals.debt_base += *amount * reserve.price / RayU256::exp10(reserve.decimals as usize);
+    }
+
+    if !totals.collateral_base.is_zero() {
+        totals.liquidation_threshold = weighted_threshold / totals.collateral_base;
+    }
+    totals.health_factor = health_factor(
+        totals.collateral_base,
+        totals.debt_base,
+        totals.liquidation_threshold,
+    )
+    .unwrap_or(RayU256::MAX);
+    (totals, issues)
+}
+
+fn list_address(address: &str) -> String {
+    normalize_address(address).unwrap_or_else(|| address.to_lowercase())
+}
+
+#[derive(Default)]
+struct EventFieldChecks(Vec<FieldMismatch>);
+
+impl EventFieldChecks {
+    fn value(&mut self, field: &str, stored: impl ToString, on_chain: impl ToString) {
+        let (stored, on_chain) = (stored.to_string(), on_chain.to_string());
+        if stored != on_chain {
+            self.push(field, stored, on_chain);
+        }
+    }
+
+    fn address(&mut self, field: &str, stored: &str, on_chain: Address) {
+        let on_chain = format!("{:#x}", on_chain);
+        if normalize_address(stored).as_deref() != Some(on_chain.as_str()) {
+            self.push(field, stored.to_string(), on_chain);
+        }
+    }
+
+    fn amount(&mut self, field: &str, stored: &Decimal128, on_chain: U256) {
+        let stored = stored.to_string();
+        if stored.parse::<U256>().ok() != Some(on_chain) {
+            self.push(field, stored, on_chain.to_string());
+        }
+    }
+
+    fn push(&mut self, field: &str, stored: String, on_chain: String) {
+        self.0.push(FieldMismatch {
+            field: field.to_string(),
+            stored,
+            on_chain,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::{
+        a_token_mint_event, decimal, event, reserve_data_updated, reserve_token, supply_event,
+        user_position, A_TOKEN_ADDRESS, DEBT_TOKEN_ADDRESS, INDEX, RESERVE_ADDRESS,
+        TREASURY_ADDRESS, TX_HASH, USER_ADDRESS,
+    };
+    use mongodb::bson::doc;
+
+    fn supply_log(amount: u64) -> Log {
+        let user: Address = USER_ADDRESS.parse().unwrap();
+        let event = PoolEvents::Supply {
+            reserve: RESERVE_ADDRESS.parse().unwrap(),
+            user,
+            onBehalfOf: user,
+            amount: U256::from(amount),
+            referralCode: 0,
+        };
+        Log {
+            inner: alloy::primitives::Log {
+                address: POOL_ADDRESS.parse().unwrap(),
+                data: event.encode_log_data(),
+            },
+            block_number: Some(1_000_000),
+            log_index: Some(3),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn verify_supply_event_against_logs() {
+        let event = supply_event("1500000");
+
+        let verified = verify_event_log(&event, Some(&[supply_log(1_500_000)]), 146);
+        assert_eq!(verified.status, EventVerificationStatus::Verified);
+
+        let mismatched = verify_event_log(&event, Some(&[supply_log(1_400_000)]), 146);
+        match mismatched.status {
+            EventVerificationStatus::Mismatch(fields) => {
+                assert_eq!(fields.len(), 1);
+                assert_eq!(fields[0].field, "amount");
+                assert_eq!(fields[0].on_chain, "1400000");
+            }
+            status => panic!("expected an amount mismatch, got {:?}", status),
+        }
+
+        let wrong_chain = verify_event_log(&event, Some(&[supply_log(1_500_000)]), 1);
+        assert!(matches!(
+            wrong_chain.status,
+            EventVerificationStatus::Mismatch(_)
+        ));
+
+        let missing = verify_event_log(&event, Some(&[]), 146);
+        assert_eq!(missing.status, EventVerificationStatus::MissingLog);
+        let no_receipt = verify_event_log(&event, None, 146);
+        assert_eq!(no_receipt.status, EventVerificationStatus::MissingReceipt);
+    }
+
+    #[test]
+    fn mint_math_checks_index_and_pool_amount() {
+        let mint = a_token_mint_event("2100000", "100000");
+        let update = reserve_data_updated(1_000_000, ("0", "0"), (INDEX, INDEX));
+        let tx_events = vec![update, mint.clone()];
+
+        let consistent = check_event_math(&mint, Some(RESERVE_ADDRESS), &tx_events).unwrap();
+        assert_eq!(consistent.status, EventMathStatus::Consistent);
+        assert_eq!(consistent.scaled_delta.unwrap().to_string(), "+1904762");
+
+        let stale_index = vec![reserve_data_updated(
+            1_000_000,
+            ("0", "0"),
+            ("1040000000000000000000000000", INDEX),
+        )];
+        let inconsistent = check_event_math(&mint, Some(RESERVE_ADDRESS), &stale_index).unwrap();
+        assert!(matches!(
+            inconsistent.status,
+            EventMathStatus::Inconsistent(_)
+        ));
+
+        let without_update = check_event_math(&mint, Some(RESERVE_ADDRESS), &[]).unwrap();
+        assert_eq!(without_update.status, EventMathStatus::NoReserveUpdate);
+
+        // a mint with value == balanceIncrease only accrues interest
+        let accrual = a_token_mint_event("100000", "100000");
+        let accrued = check_event_math(&accrual, Some(RESERVE_ADDRESS), &tx_events).unwrap();
+        assert_eq!(accrued.status, EventMathStatus::Consistent);
+        let unknown_token = check_event_math(&accrual, None, &tx_events).unwrap();
+        assert!(matches!(
+            unknown_token.status,
+            EventMathStatus::Inconsistent(_)
+        ));
+
+        assert!(check_event_math(&tx_events[0], Some(RESERVE_ADDRESS), &tx_events).is_none());
+
+        // value - balanceIncrease must be the supplied amount
+        let with_supply = vec![
+            reserve_data_updated(1_000_000, ("0", "0"), (INDEX, INDEX)),
+            supply_event("2000000"),
+            mint.clone(),
+        ];
+        let matching = check_event_math(&mint, Some(RESERVE_ADDRESS), &with_supply).unwrap();
+        assert_eq!(matching.status, EventMathStatus::Consistent);
+        let with_other_supply = vec![
+            reserve_data_updated(1_000_000, ("0", "0"), (INDEX, INDEX)),
+            supply_event("1900000"),
+            mint.clone(),
+        ];
+        let mismatch = check_event_math(&mint, Some(RESERVE_ADDRESS), &with_other_supply).unwrap();
+        match mismatch.status {
+            EventMathStatus::Inconsistent(issues) => {
+                assert_eq!(issues.len(), 1);
+                assert!(issues[0].contains("differs from the Supply amount (1900000)"));
+            }
+            status => panic!("expected an amount mismatch, got {:?}", status),
+        }
+    }
+
+    #[test]
+    fn transaction_events_need_companions() {
+        let reserve_tokens = HashMap::from([(
+            RESERVE_ADDRESS.to_string(),
+            (A_TOKEN_ADDRESS.to_string(), DEBT_TOKEN_ADDRESS.to_string()),
+        )]);
+        let supply = supply_event("2000000");
+        let mint = a_token_mint_event("2100000", "100000");
+        let update: MoneyMarketEventDocument =
+            reserve_data_updated(1_000_000, ("0", "0"), (INDEX, INDEX));
+
+        let complete = check_transaction_events(
+            &[update.clone(), mint.clone(), supply.clone()],
+            &reserve_tokens,
+        );
+        assert_eq!(complete.action, "supply");
+        assert!(complete.missing_companions.is_empty());
+
+        let without_mint = check_transaction_events(&[update.clone(), supply], &reserve_tokens);
+        assert_eq!(without_mint.missing_companions.len(), 1);
+        assert!(without_mint.missing_companions[0].starts_with("a-token-mint"));
+
+        let accrual = check_transaction_events(&[mint], &reserve_tokens);
+        assert_eq!(accrual.action, "token events");
+        let reserve_update = check_transaction_events(&[update], &reserve_tokens);
+        assert_eq!(reserve_update.action, "reserve update");
+    }
+
+    fn reconcile(
+        reserves: &[ReserveTokenDocument],
+        users: &[UserPositionDocument],
+        treasury: Option<&str>,
+    ) -> (Vec<ReserveReconciliation>, Vec<String>) {
+        let (mut reconciliations, mut issues) = reserve_reconciliations(reserves, treasury);
+        for user in users {
+            reconcile_user_positions(&mut reconciliations, user, &mut issues);
+        }
+        (reconciliations, issues)
+    }
+
+    #[test]
+    fn reserve_totals_keep_treasury_apart() {
+        let reserves = vec![reserve_token("1500", "300")];
+        let users = vec![
+            user_position(USER_ADDRESS, "1000", "300"),
+            user_position(TREASURY_ADDRESS, "400", "0"),
+        ];
+
+        let (reconciliations, issues) = reconcile(&reserves, &users, Some(TREASURY_ADDRESS));
+        assert!(issues.is_empty());
+        let reconciliation = &reconciliations[0];
+        assert_eq!(reconciliation.users_supply.to_string(), "1000");
+        assert_eq!(reconciliation.treasury_supply.unwrap().to_string(), "400");
+        assert_eq!(reconciliation.suppliers, 1);
+        assert_eq!(reconciliation.borrowers, 1);
+        assert_eq!(reconciliation.supply_residual().to_string(), "+100");
+        assert_eq!(reconciliation.debt_residual().to_string(), "+0");
+        assert!(!reconciliation.is_consistent());
+
+        // without the treasury address its position is counted as a user
+        let (reconciliations, _) = reconcile(&reserves, &users, None);
+        assert_eq!(reconciliations[0].suppliers, 2);
+        assert!(reconciliations[0].treasury_supply.is_none());
+        assert_eq!(reconciliations[0].supply_residual().to_string(), "+100");
+
+        let (reconciliations, _) = reconcile(&[reserve_token("900", "300")], &users, None);
+        assert_eq!(reconciliations[0].supply_residual().to_string(), "-500");
+    }
+
+    #[test]
+    fn user_list_missing_stale_and_duplicates() {
+        let users = vec![
+            user_position(USER_ADDRESS, "1000", "0"),
+            user_position(TREASURY_ADDRESS, "400", "10"),
+        ];
+        let mut holders = HashMap::new();
+        for user in &users {
+            add_position_holders(&mut holders, user);
+        }
+        let (suppliers, borrowers) = &holders[&RESERVE_ADDRESS.to_lowercase()];
+        assert_eq!(suppliers.len(), 2);
+        assert_eq!(borrowers.len(), 1);
+
+        let stale_user = "0x00000000000000000000000000000000000000aa".to_string();
+        let listed = vec![
+            USER_ADDRESS.to_uppercase().replace("0X", "0x"),
+            USER_ADDRESS.to_string(),
+            stale_user.clone(),
+        ];
+        let check = check_user_list("suppliers", &listed, suppliers);
+        assert_eq!(check.missing, vec![TREASURY_ADDRESS.to_string()]);
+        assert_eq!(check.stale, vec![stale_user]);
+        assert_eq!(check.duplicates, vec![USER_ADDRESS.to_lowercase()]);
+        assert!(!check.is_consistent());
+
+        let check = check_user_list("borrowers", &[TREASURY_ADDRESS.to_string()], borrowers);
+        assert!(check.is_consistent());
+    }
+
+    fn transfer_log(from: Address, to: &str) -> Log {
+        let event = ScaledTokenEvents::Transfer {
+            from,
+            to: to.parse().unwrap(),
+            value: U256::from(1_000),
+        };
+        Log {
+            inner: alloy::primitives::Log {
+                address: A_TOKEN_ADDRESS.parse().unwrap(),
+                data: event.encode_log_data(),
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn untracked_holders_from_transfer_recipients() {
+        let other_user = "0x00000000000000000000000000000000000000aa";
+        let logs = vec![
+            transfer_log(Address::ZERO, USER_ADDRESS),
+            transfer_log(Address::ZERO, TREASURY_ADDRESS),
+            transfer_log(USER_ADDRESS.parse().unwrap(), other_user),
+            // burns are transfers to the zero address
+            transfer_log(
+                USER_ADDRESS.parse().unwrap(),
+                &format!("{:#x}", Address::ZERO),
+            ),
+        ];
+        let recipients = transfer_recipients(&logs);
+        let candidates = recipients.get(&A_TOKEN_ADDRESS.to_lowercase()).unwrap();
+        assert_eq!(candidates.len(), 3);
+
+        let mut other_reserve_position = user_position(TREASURY_ADDRESS, "400", "0");
+        other_reserve_position.positions[0].reserveAddress =
+            "0x00000000000000000000000000000000000000bb".to_string();
+        let tracked: HashMap<String, BTreeSet<String>> = [
+            user_position(USER_ADDRESS, "1000", "0"),
+            other_reserve_position,
+        ]
+        .iter()
+        .map(tracked_reserves)
+        .collect();
+        let untracked = find_untracked_holders(RESERVE_ADDRESS, candidates, &tracked);
+        assert_eq!(
+            untracked,
+            vec![
+                UntrackedHolder {
+                    address: other_user.to_string(),
+                    has_user_position: false,
+                },
+                UntrackedHolder {
+                    address: TREASURY_ADDRESS.to_string(),
+                    has_user_position: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn index_history_follows_rates() {
+        const RAY: &str = "1000000000000000000000000000";
+        let rates = ("100000000000000000000000000", "200000000000000000000000000");
+        let events = vec![
+            reserve_data_updated(100, rates, (RAY, RAY)),
+            // a year later at 10%, the borrow index didn't move as there was no debt
+            reserve_data_updated(200, rates, ("1100000000000000000000000000", RAY)),
+            reserve_data_updated(300, rates, (RAY, "2000000000000000000000000000")),
+            reserve_data_updated(400, rates, ("1200000000000000000000000000", RAY)),
+        ];
+        let timestamps =
+            HashMap::from([(100, 1_000), (200, 1_000 + 31_536_000), (300, 31_537_100)]);
+
+        let history = check_index_history(&events, &timestamps);
+        assert_eq!(history[0].liquidity_index_status, IndexStatus::First);
+        assert_eq!(history[1].liquidity_index_status, IndexStatus::Consistent);
+        assert_eq!(history[1].borrow_index_status, IndexStatus::Unchanged);
+        assert_eq!(history[2].liquidity_index_status, IndexStatus::Decreased);
+        assert!(matches!(
+            history[2].borrow_index_status,
+            IndexStatus::Mismatch { .. }
+        ));
+        assert_eq!(history[3].liquidity_index_status, IndexStatus::NoTimestamp);
+        assert_eq!(history[3].borrow_index_status, IndexStatus::Decreased);
+        assert_eq!(
+            history[1].to_csv_line(),
+            format!(
+                "200,0,{},31537000,{},{},1100000000000000000000000000,{},ok,unchanged",
+                TX_HASH, rates.0, rates.1, RAY
+            )
+        );
+    }
+
+    #[test]
+    fn reserve_configuration_caps_and_status() {
+        let configuration = ReserveConfiguration {
+            decimals: 6,
+            active: true,
+            borrowing_enabled: true,
+            supply_cap: 1_000,
+            borrow_cap: 500,
+            ..Default::default()
+        };
+        let unit = RayU256::exp10(6);
+        let zero = RayU256::zero();
+        let no_activity = BTreeMap::new();
+
+        // at the caps
+        assert!(
+            check_reserve_configuration(&configuration, unit * 1_000, unit * 500, &no_activity)
+                .is_empty()
+        );
+        // one unit over both caps
+        let issues = check_reserve_configuration(
+            &configuration,
+            unit * 1_000 + 1,
+            unit * 500 + 1,
+            &no_activity,
+        );
+        assert_eq!(issues.len(), 2);
+
+        // a frozen reserve can still be withdrawn from and repaid
+        let frozen = ReserveConfiguration {
+            frozen: true,
+            ..configuration
+        };
+        let activity = BTreeMap::from([("withdraw", 3), ("repay", 1)]);
+        assert!(check_reserve_configuration(&frozen, zero, zero, &activity).is_empty());
+        let activity = BTreeMap::from([("supply", 2), ("withdraw", 3)]);
+        let issues = check_reserve_configuration(&frozen, zero, zero, &activity);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].contains("frozen"));
+
+        // a paused reserve accepts nothing, index updates aside
+        let paused = ReserveConfiguration {
+            paused: true,
+            ..configuration
+        };
+        let activity = BTreeMap::from([("reserve-data-updated", 1)]);
+        assert!(check_reserve_configuration(&paused, zero, zero, &activity).is_empty());
+        let activity = BTreeMap::from([("repay", 1)]);
+        assert!(check_reserve_configuration(&paused, zero, zero, &activity)[0].contains("paused"));
+    }
+
+    fn borrow_event(on_behalf_of: &str, interest_rate_mode: i32) -> BorrowEvent {
+        event(
+            "borrow",
+            doc! {
+                "reserve": RESERVE_ADDRESS,
+                "user": on_behalf_of,
+                "onBehalfOf": on_behalf_of,
+                "amount": decimal("1000"),
+                "interestRateMode": interest_rate_mode,
+                "borrowRate": decimal("0"),
+                "referralCode": 0,
+            },
+        )
+    }
+
+    #[test]
+    fn stable_borrowers_grouped_by_address() {
+        let checksummed = "0x00000000000000000000000000000000000000AA";
+        let borrows = vec![
+            borrow_event(checksummed, 1),
+            borrow_event(USER_ADDRESS, 2),
+            borrow_event(&checksummed.to_lowercase(), 1),
+        ];
+
+        let borrowers = stable_borrowers(&borrows);
+        assert_eq!(borrowers.len(), 1);
+        assert_eq!(
+            borrowers.get("0x00000000000000000000000000000000000000aa"),
+            Some(&2)
+        );
+    }
+
+    #[test]
+    fn reserve_health_residual_ceiling_and_unbacked() {
+        let health = ReserveHealth {
+            block_number: 1_000_000,
+            configuration: ReserveConfiguration {
+                decimals: 6,
+                debt_ceiling: 100_000,
+                ..Default::default()
+            },
+            scaled_total_supply: RayU256::from(1_000u64),
+            users_supply: RayU256::from(900u64),
+            treasury_supply: RayU256::from(100u64),
+            accrued_to_treasury: RayU256::from(7u64),
+            liquidity_index: RayU256::exp10(27),
+            unbacked: RayU256::zero(),
+            isolation_mode_total_debt: RayU256::from(100_000u64),
+        };
+        assert!(check_reserve_health(&health).is_empty());
+
+        let missing_user = ReserveHealth {
+            users_supply: RayU256::from(890u64),
+            ..health.clone()
+        };
+        assert_eq!(missing_user.supply_residual().to_string(), "+10");
+        assert_eq!(check_reserve_health(&missing_user).len(), 1);
+
+        let over_ceiling = ReserveHealth {
+            isolation_mode_total_debt: RayU256::from(100_001u64),
+            unbacked: RayU256::one(),
+            ..health.clone()
+        };
+        let issues = check_reserve_health(&over_ceiling);
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].contains("debt ceiling"));
+        assert!(issues[1].contains("unbacked"));
+
+        // debt left over after the ceiling was removed
+        let no_ceiling = ReserveHealth {
+            configuration: ReserveConfiguration {
+                decimals: 6,
+                ..Default::default()
+            },
+            ..health
+        };
+        assert!(check_reserve_health(&no_ceiling)[0].contains("without a debt ceiling"));
+    }
+
+    #[test]
+    fn account_totals_from_positions_and_prices() {
+        let ray = RayU256::exp10(27);
+        let reserves = HashMap::from([(
+            RESERVE_ADDRESS.to_lowercase(),
+            PricedReserve {
+                id: 0,
+                decimals: 6,
+                liquidation_threshold: 8_000,
+                liquidity_index: ray * 11 / 10,
+                variable_borrow_index: ray * 12 / 10,
+                // 1 USD, 8 decimals
+                price: RayU256::exp10(8),
+            },
+        )]);
+        let user = user_position(USER_ADDRESS, "1000000000", "500000000");
+
+        // borrowing and using the reserve as collateral
+        let (totals, issues) =
+            recompute_account_totals(&user, &reserves, &HashMap::new(), RayU256::from(0b11u64));
+        assert!(issues.is_empty());
+        assert_eq!(
+            totals.collateral_base,
+            RayU256::from(1_100u64) * RayU256::exp10(8)
+        );
+        assert_eq!(totals.debt_base, RayU256::from(600u64) * RayU256::exp10(8));
+        assert_eq!(totals.liquidation_threshold, RayU256::from(8_000u64));
+        // 1100 * 80% / 600
+        assert_eq!(
+            totals.health_factor,
+            RayU256::from(1_466_666_666_666_666_667u64)
+        );
+
+        // collateral disabled
+        let (totals, _) =
+            recompute_account_totals(&user, &reserves, &HashMap::new(), RayU256::from(0b01u64));
+        assert!(totals.collateral_base.is_zero());
+        assert!(totals.health_factor.is_zero());
+
+        let (_, issues) = recompute_account_totals(
+            &user,
+            &HashMap::new(),
+            &HashMap::new(),
+            RayU256::from(0b11u64),
+        );
+        assert_eq!(issues.len(), 1);
+
+        // 250 tokens of stable debt on top of the variable debt, not scaled by any index
+        let stable_debt = HashMap::from([(
+            RESERVE_ADDRESS.to_lowercase(),
+            RayU256::from(250_000_000u64),
+        )]);
+        let (totals, issues) =
+            recompute_account_totals(&user, &reserves, &stable_debt, RayU256::from(0b11u64));
+        assert!(issues.is_empty());
+        assert_eq!(totals.debt_base, RayU256::from(850u64) * RayU256::exp10(8));
+
+        // stable debt in a reserve the configuration doesn't flag as borrowed
+        let (_, issues) =
+            recompute_account_totals(&user, &reserves, &stable_debt, RayU256::from(0b10u64));
+        assert_eq!(issues.len(), 2);
+    }
+}
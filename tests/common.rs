@@ -9,7 +9,7 @@ pub const A_TOKEN_ADDRESS: &str = "0x5c50cf875aebad8d5ba548f229960c90b1c1f8c3";
 pub const USER_ADDRESS: &str = "0x6d7b6956589c17B2755193a67BF2d4B68827E58A";
 #[allow(dead_code)]
 // pub const VARIABLE_DEBT_TOKEN_ADDRESS: &'static str = "0x19c023ff9c8105bf58e022c17a636b8e55ed8fe4";
-pub const VARIABLE_DEBT_TOKEN_ADDRESS: &'static str = "0x96a4197803ac8b21a1b7aefe72e565c71a91a40f";
+pub const VARIABLE_DEBT_TOKEN_ADDRESS: &str = "0x96a4197803ac8b21a1b7aefe72e565c71a91a40f";
 
 // Common handler for Result types in tests
 pub fn common_handler<T, E: std::fmt::Display>(
@@ -29,6 +29,7 @@ pub fn common_handler<T, E: std::fmt::Display>(
     }
 }
 
+#[allow(dead_code)]
 pub fn common_vec_handler<T, E: std::fmt::Display>(
     result: Result<Vec<T>, E>,
     success_msg: &str,
@@ -1,7 +1,9 @@
 use sodax_backend_analizer::evm::{
     get_atoken_liquidity_index, get_balance_of, get_last_block, get_reserve_data, get_total_supply,
-    get_variable_borrow_index, get_block_timestamp,
+    get_variable_borrow_index, get_block_timestamp, prefetch_blocks, cached_block,
+    find_block_at_time, get_logs_in_range, indexed_event_signatures, POOL_ADDRESS,
 };
+use sodax_backend_analizer::structs::EventEmitter;
 
 // Import common test utilities
 mod common;
@@ -84,3 +86,58 @@ async fn test_get_block_timestamp() {
         "Failed to retrieve block timestamp",
     );
 }
+
+#[tokio::test]
+async fn test_prefetch_blocks() {
+    let result = prefetch_blocks(&[1_000_000, 1_000_000, 1_000_001]).await;
+    if let Ok(failed) = &result
+        && failed.is_empty()
+    {
+        assert!(cached_block(1_000_001).is_some());
+        assert_eq!(
+            cached_block(1_000_000).map(|block| block.timestamp),
+            get_block_timestamp(1_000_000).await.ok()
+        );
+    }
+    common_handler(
+        result,
+        "Blocks prefetched successfully",
+        "Failed to prefetch blocks",
+    );
+}
+
+#[tokio::test]
+async fn test_find_block_at_time() {
+    let block_number: u64 = 1_000_000;
+    let result = match get_block_timestamp(block_number).await {
+        Ok(timestamp) => find_block_at_time(timestamp).await,
+        Err(e) => Err(e),
+    };
+    if let Ok(block) = &result {
+        // several blocks may share a timestamp, the last one is returned
+        assert!(block.number >= block_number);
+    }
+    common_handler(
+        result,
+        "Block at time resolved successfully",
+        "Failed to resolve block at time",
+    );
+}
+
+#[tokio::test]
+async fn test_get_logs_in_range() {
+    let pool = POOL_ADDRESS.parse().unwrap();
+    let topics: Vec<_> = indexed_event_signatures(EventEmitter::Pool)
+        .into_iter()
+        .map(|(topic, _)| topic)
+        .collect();
+    let result = get_logs_in_range(&[pool], &topics, 1_000_000, 1_000_100).await;
+    if let Ok(logs) = &result {
+        assert!(logs.iter().all(|log| log.address() == pool));
+    }
+    common_handler(
+        result.map(|logs| logs.len()),
+        "Logs retrieved successfully",
+        "Failed to retrieve logs",
+    );
+}
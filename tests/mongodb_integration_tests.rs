@@ -8,8 +8,13 @@ use sodax_backend_analizer::db::{
     find_timestamp_and_block_from_solver_volume,
     find_user_events,
     find_token_events,
+    count_solver_volume,
+    count_docs_with_non_null_timestamp,
+    stream_user_addresses,
     // get_user_position,
 };
+use futures::stream::StreamExt;
+use sodax_backend_analizer::helpers::resolve_reserve;
 use sodax_backend_analizer::structs::ReserveTokenField;
 
 // Import common test utilities
@@ -17,6 +22,7 @@ mod common;
 use common::{
     common_handler,
     common_vec_handler,
+    common_option_handler,
     common_result_option_handler,
     A_TOKEN_ADDRESS,
     RESERVE_TOKEN_ADDRESS,
@@ -149,6 +155,45 @@ async fn test_find_token_events() {
         "Token events not found, which is valid for test data",
     );
 }
+#[tokio::test]
+async fn test_resolve_reserve_from_checksummed_a_token() {
+    // EIP-55 form of A_TOKEN_ADDRESS
+    let checksummed = "0x5C50cF875AebAD8D5BA548F229960c90b1c1F8c3";
+    assert_eq!(checksummed.to_lowercase(), A_TOKEN_ADDRESS);
+    let result = resolve_reserve(checksummed).await;
+    common_handler(
+        result,
+        "Reserve resolved from aToken address",
+        "Failed to resolve reserve from aToken address",
+    );
+}
+
+#[tokio::test]
+async fn test_timestamp_counts() {
+    let total = count_solver_volume().await;
+    assert!(total.is_ok(), "Should count solver volume documents");
+
+    let with_timestamp = count_docs_with_non_null_timestamp().await;
+    assert!(
+        with_timestamp.is_ok(),
+        "Should count documents with timestamp"
+    );
+    assert!(with_timestamp.unwrap() <= total.unwrap());
+}
+
+#[tokio::test]
+async fn test_stream_user_addresses() {
+    let result = stream_user_addresses().await;
+    assert!(result.is_ok(), "Should open a cursor over user addresses");
+
+    let first = result.unwrap().next().await;
+    common_option_handler(
+        first.map(|user| user.map(|u| u.userAddress).is_ok()),
+        "First user address streamed",
+        "No users found, which is valid for test data",
+    );
+}
+
 // #[ignore]
 // #[tokio::test]
 // async fn test_get_user_position() {